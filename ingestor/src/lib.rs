@@ -0,0 +1,3 @@
+pub mod yellowstone;
+
+pub use stream_common::shutdown;
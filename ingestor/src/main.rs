@@ -1,6 +1,6 @@
 use std::error::Error;
 
-mod yellowstone;
+use ingestor::{shutdown, yellowstone};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
@@ -14,6 +14,9 @@ async fn main() -> Result<(), Box<dyn Error>> {
         std::env::var("KAFKA_TOPIC_PREFIX").unwrap_or_else(|_| "ingest".to_string());
     let subscription_config =
         yellowstone::subscriptions::SubscriptionConfig::from_env(kafka_topic_prefix.clone());
+    let checkpoint = yellowstone::checkpoint::SlotCheckpoint::from_env();
+    let record = yellowstone::recorder::RecordConfig::from_env();
+    let shutdown_timeout = shutdown::timeout_from_env();
 
     println!("Starting Yellowstone ingestor...");
     println!("   Endpoint: {}", yellowstone_endpoint);
@@ -42,8 +45,11 @@ async fn main() -> Result<(), Box<dyn Error>> {
         yellowstone_token,
         kafka_brokers,
         subscription_config,
+        checkpoint,
+        record,
+        shutdown_timeout,
     );
-    worker.run().await;
+    worker.run(shutdown::listen_for_signals()).await;
 
     Ok(())
 }
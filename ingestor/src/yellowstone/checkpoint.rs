@@ -0,0 +1,91 @@
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Minimum time between saves while updates keep arriving.
+const SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Last slot fully published to Kafka, persisted so a restart can resume with `from_slot`.
+#[derive(Debug, Clone)]
+pub struct SlotCheckpoint {
+    path: Option<PathBuf>,
+    last_slot: Option<u64>,
+    /// Set after a failed publish: later slots no longer move the checkpoint, so the
+    /// resubscribe that follows resumes from the last slot that was published.
+    held: bool,
+    saved_slot: Option<u64>,
+    last_save: Option<Instant>,
+}
+
+impl SlotCheckpoint {
+    pub fn from_env() -> Self {
+        let path = std::env::var("INGESTOR_CHECKPOINT_PATH")
+            .ok()
+            .filter(|value| !value.trim().is_empty())
+            .map(PathBuf::from);
+        Self::load(path)
+    }
+
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let last_slot = path
+            .as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|contents| contents.trim().parse::<u64>().ok());
+
+        Self {
+            path,
+            last_slot,
+            held: false,
+            saved_slot: last_slot,
+            last_save: None,
+        }
+    }
+
+    pub fn last_slot(&self) -> Option<u64> {
+        self.last_slot
+    }
+
+    pub fn observe(&mut self, slot: u64) {
+        if !self.held && self.last_slot.is_none_or(|last| slot > last) {
+            self.last_slot = Some(slot);
+        }
+    }
+
+    /// Keeps the checkpoint where it is until [`Self::release`].
+    pub fn hold(&mut self) {
+        self.held = true;
+    }
+
+    /// Lets the checkpoint move again, once a new subscription replays from it.
+    pub fn release(&mut self) {
+        self.held = false;
+    }
+
+    /// Saves if the slot moved and the last save was long enough ago, so a crash
+    /// replays at most a few seconds of updates.
+    pub fn save_if_due(&mut self) -> std::io::Result<()> {
+        if self
+            .last_save
+            .is_some_and(|last| last.elapsed() < SAVE_INTERVAL)
+        {
+            return Ok(());
+        }
+        self.save()
+    }
+
+    /// Writes the slot if it moved since the last save.
+    pub fn save(&mut self) -> std::io::Result<()> {
+        let (Some(path), Some(slot)) = (self.path.as_ref(), self.last_slot) else {
+            return Ok(());
+        };
+        if self.saved_slot == Some(slot) {
+            return Ok(());
+        }
+
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, format!("{slot}\n"))?;
+        std::fs::rename(&tmp_path, path)?;
+        self.saved_slot = Some(slot);
+        self.last_save = Some(Instant::now());
+        Ok(())
+    }
+}
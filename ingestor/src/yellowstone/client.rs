@@ -5,8 +5,12 @@ pub async fn connect(
     endpoint: &str,
     x_token: Option<String>,
 ) -> Result<GeyserGrpcClient<impl Interceptor>, GeyserGrpcBuilderError> {
-    let mut builder = GeyserGrpcClient::build_from_shared(endpoint.to_string())?
-        .tls_config(ClientTlsConfig::new().with_native_roots())?;
+    let mut builder = GeyserGrpcClient::build_from_shared(endpoint.to_string())?;
+
+    // Plain http is only used for local endpoints such as the mock Geyser server.
+    if endpoint.starts_with("https://") {
+        builder = builder.tls_config(ClientTlsConfig::new().with_native_roots())?;
+    }
 
     if let Some(token) = x_token {
         builder = builder.x_token(Some(token))?;
@@ -1,9 +1,26 @@
+use std::convert::Infallible;
+use std::future::Future;
 use std::time::Duration;
 
 use rdkafka::error::KafkaError;
 use rdkafka::producer::{FutureProducer, FutureRecord, Producer};
 use rdkafka::{ClientConfig, util::Timeout};
 
+/// Destination for routed payloads. `KafkaPublisher` in production; tests plug in
+/// an in-memory sink so the worker can run against the mock Geyser server.
+pub trait Publisher {
+    type Error: std::fmt::Display;
+
+    fn send_to(
+        &self,
+        topic: &str,
+        key: &str,
+        payload: &str,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    fn flush(&self, timeout: Duration) -> Result<(), Self::Error>;
+}
+
 pub struct KafkaPublisher {
     producer: FutureProducer,
 }
@@ -24,8 +41,12 @@ impl KafkaPublisher {
             .fetch_metadata(None, Timeout::After(Duration::from_secs(5)))?;
         Ok(())
     }
+}
 
-    pub async fn send_to(&self, topic: &str, key: &str, payload: &str) -> Result<(), KafkaError> {
+impl Publisher for KafkaPublisher {
+    type Error = KafkaError;
+
+    async fn send_to(&self, topic: &str, key: &str, payload: &str) -> Result<(), KafkaError> {
         let record = FutureRecord::to(topic).payload(payload).key(key);
 
         match self
@@ -37,4 +58,23 @@ impl KafkaPublisher {
             Err((err, _)) => Err(err),
         }
     }
+
+    fn flush(&self, timeout: Duration) -> Result<(), KafkaError> {
+        self.producer.flush(Timeout::After(timeout))
+    }
+}
+
+/// Publisher that drops every payload; used in record mode where Kafka is not needed.
+pub struct Discard;
+
+impl Publisher for Discard {
+    type Error = Infallible;
+
+    async fn send_to(&self, _topic: &str, _key: &str, _payload: &str) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    fn flush(&self, _timeout: Duration) -> Result<(), Infallible> {
+        Ok(())
+    }
 }
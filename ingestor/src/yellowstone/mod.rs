@@ -1,8 +1,11 @@
 use futures::{SinkExt, StreamExt};
+use tokio::sync::watch;
 use yellowstone_grpc_proto::prelude::SubscribeUpdate;
 
+pub mod checkpoint;
 pub mod client;
 pub mod kafka;
+pub mod recorder;
 pub mod router;
 pub mod subscriptions;
 
@@ -11,6 +14,9 @@ pub struct YellowstoneWorker {
     x_token: Option<String>,
     kafka_brokers: String,
     subscription_config: subscriptions::SubscriptionConfig,
+    checkpoint: checkpoint::SlotCheckpoint,
+    record: Option<recorder::RecordConfig>,
+    shutdown_timeout: std::time::Duration,
 }
 
 impl YellowstoneWorker {
@@ -19,18 +25,31 @@ impl YellowstoneWorker {
         x_token: Option<String>,
         kafka_brokers: String,
         subscription_config: subscriptions::SubscriptionConfig,
+        checkpoint: checkpoint::SlotCheckpoint,
+        record: Option<recorder::RecordConfig>,
+        shutdown_timeout: std::time::Duration,
     ) -> Self {
         Self {
             endpoint,
             x_token,
             kafka_brokers,
             subscription_config,
+            checkpoint,
+            record,
+            shutdown_timeout,
         }
     }
 
-    pub async fn run(self) {
-        let endpoint = self.endpoint.clone();
-        let x_token = self.x_token.clone();
+    pub async fn run(self, shutdown: watch::Receiver<bool>) {
+        if let Some(record) = self.record.as_ref() {
+            println!(
+                "Record mode: writing updates to {} (zstd: {}), Kafka disabled",
+                record.path.display(),
+                record.zstd
+            );
+            self.run_with(&kafka::Discard, shutdown).await;
+            return;
+        }
 
         let publisher = match kafka::KafkaPublisher::new(&self.kafka_brokers) {
             Ok(p) => p,
@@ -50,69 +69,178 @@ impl YellowstoneWorker {
             self.kafka_brokers, self.subscription_config.topic_prefix
         );
 
+        self.run_with(&publisher, shutdown).await;
+    }
+
+    /// Subscribe/publish loop, generic over the sink so it can run without Kafka.
+    pub async fn run_with<P: kafka::Publisher>(
+        self,
+        publisher: &P,
+        mut shutdown: watch::Receiver<bool>,
+    ) {
+        let endpoint = self.endpoint.clone();
+        let x_token = self.x_token.clone();
+        let mut checkpoint = match self.record.as_ref() {
+            Some(record) => checkpoint::SlotCheckpoint::load(record.checkpoint.clone()),
+            None => self.checkpoint.clone(),
+        };
+        if let Some(slot) = checkpoint.last_slot() {
+            println!("Resuming from checkpoint slot {}", slot);
+        }
+
+        let mut recorder = match self
+            .record
+            .as_ref()
+            .map(recorder::UpdateRecorder::create)
+            .transpose()
+        {
+            Ok(recorder) => recorder,
+            Err(e) => {
+                eprintln!("Failed to create record file: {}", e);
+                return;
+            }
+        };
+
         let mut backoff = std::time::Duration::from_secs(1);
 
-        loop {
+        while !*shutdown.borrow() {
             println!("Yellowstone Worker started! Connecting to {}...", endpoint);
 
             let mut client = match client::connect(&endpoint, x_token.clone()).await {
                 Ok(c) => c,
                 Err(e) => {
                     eprintln!("Failed to connect to Yellowstone gRPC: {}", e);
-                    tokio::time::sleep(backoff).await;
-                    backoff = (backoff * 2).min(std::time::Duration::from_secs(30));
+                    sleep_or_shutdown(&mut backoff, &mut shutdown).await;
                     continue;
                 }
             };
 
             println!("Connected to Yellowstone gRPC!");
 
-            let request = subscriptions::create_subscription_request(&self.subscription_config);
+            let request = subscriptions::create_subscription_request(
+                &self.subscription_config,
+                checkpoint.last_slot(),
+            );
 
             let (mut subscribe_tx, mut stream) = match client.subscribe().await {
                 Ok(res) => res,
                 Err(e) => {
                     eprintln!("Failed to subscribe: {}", e);
-                    tokio::time::sleep(backoff).await;
-                    backoff = (backoff * 2).min(std::time::Duration::from_secs(30));
+                    sleep_or_shutdown(&mut backoff, &mut shutdown).await;
                     continue;
                 }
             };
 
             if let Err(e) = subscribe_tx.send(request).await {
                 eprintln!("Failed to send subscription request: {}", e);
-                tokio::time::sleep(backoff).await;
-                backoff = (backoff * 2).min(std::time::Duration::from_secs(30));
+                sleep_or_shutdown(&mut backoff, &mut shutdown).await;
                 continue;
             }
 
             println!("Subscribed to updates! Waiting for data...");
             backoff = std::time::Duration::from_secs(1);
+            // Anything not published before is replayed from the checkpoint.
+            checkpoint.release();
 
             let mut should_reconnect = false;
-            while let Some(message) = stream.next().await {
-                match message {
-                    Ok(update) => {
-                        self.log_update(&publisher, update).await;
-                    }
-                    Err(e) => {
-                        eprintln!("Stream error: {}", e);
-                        should_reconnect = true;
-                        break;
-                    }
+            loop {
+                tokio::select! {
+                    _ = crate::shutdown::requested(&mut shutdown) => break,
+                    message = stream.next() => match message {
+                        Some(Ok(update)) => {
+                            let published = self
+                                .log_update(publisher, &mut checkpoint, &mut recorder, update)
+                                .await;
+                            if !published {
+                                // Resubscribe from the held checkpoint so the update
+                                // is replayed instead of skipped.
+                                eprintln!("Resubscribing after a failed publish");
+                                should_reconnect = true;
+                                break;
+                            }
+                        }
+                        Some(Err(e)) => {
+                            eprintln!("Stream error: {}", e);
+                            should_reconnect = true;
+                            break;
+                        }
+                        None => break,
+                    },
                 }
             }
 
+            if *shutdown.borrow() {
+                break;
+            }
+
             if !should_reconnect {
                 println!("Stream ended");
             }
 
-            tokio::time::sleep(backoff).await;
-            backoff = (backoff * 2).min(std::time::Duration::from_secs(30));
+            if let Err(e) = checkpoint.save() {
+                eprintln!("Failed to write slot checkpoint: {}", e);
+            }
+
+            sleep_or_shutdown(&mut backoff, &mut shutdown).await;
+        }
+
+        self.drain(publisher, &mut checkpoint, recorder.as_mut());
+    }
+
+    fn drain<P: kafka::Publisher>(
+        &self,
+        publisher: &P,
+        checkpoint: &mut checkpoint::SlotCheckpoint,
+        recorder: Option<&mut recorder::UpdateRecorder>,
+    ) {
+        if let Some(recorder) = recorder {
+            match recorder.flush() {
+                Ok(()) => println!("Recorded {} updates", recorder.count()),
+                Err(e) => eprintln!("Failed to flush record file: {}", e),
+            }
+        }
+
+        println!(
+            "Flushing Kafka producer (timeout: {}s)...",
+            self.shutdown_timeout.as_secs()
+        );
+        if let Err(e) = publisher.flush(self.shutdown_timeout) {
+            eprintln!("Kafka flush failed: {}", e);
         }
+
+        match checkpoint.save() {
+            Ok(()) => {
+                if let Some(slot) = checkpoint.last_slot() {
+                    println!("Saved checkpoint at slot {}", slot);
+                }
+            }
+            Err(e) => eprintln!("Failed to write slot checkpoint: {}", e),
+        }
+
+        println!("Yellowstone Worker stopped");
     }
 
-    async fn log_update(&self, publisher: &kafka::KafkaPublisher, update: SubscribeUpdate) {
+    /// Publishes (or records) one update. Returns `false` if a Kafka send failed.
+    async fn log_update<P: kafka::Publisher>(
+        &self,
+        publisher: &P,
+        checkpoint: &mut checkpoint::SlotCheckpoint,
+        recorder: &mut Option<recorder::UpdateRecorder>,
+        update: SubscribeUpdate,
+    ) -> bool {
+        if let Some(recorder) = recorder.as_mut() {
+            match recorder.write(&update) {
+                Ok(()) => {
+                    if let Some(slot) = router::update_slot(&update) {
+                        checkpoint.observe(slot);
+                    }
+                }
+                Err(err) => eprintln!("Failed to record update: {}", err),
+            }
+            save_checkpoint_if_due(checkpoint);
+            return true;
+        }
+
         let payload = router::build_payload(&update);
         let payload_json = router::serialize_payload(&payload);
         let topics = self
@@ -120,13 +248,41 @@ impl YellowstoneWorker {
             .topics_for_update(&payload.filters, &payload.program_ids);
         println!("{payload_json}");
 
+        let mut published = true;
         for topic in topics {
             if let Err(err) = publisher
                 .send_to(&topic, &payload.event_id, &payload_json)
                 .await
             {
                 eprintln!("Kafka send failed: {}", err);
+                published = false;
             }
         }
+
+        if !published {
+            checkpoint.hold();
+        } else if let Some(slot) = payload.slot {
+            checkpoint.observe(slot);
+        }
+        save_checkpoint_if_due(checkpoint);
+
+        published
+    }
+}
+
+fn save_checkpoint_if_due(checkpoint: &mut checkpoint::SlotCheckpoint) {
+    if let Err(e) = checkpoint.save_if_due() {
+        eprintln!("Failed to write slot checkpoint: {}", e);
+    }
+}
+
+async fn sleep_or_shutdown(
+    backoff: &mut std::time::Duration,
+    shutdown: &mut watch::Receiver<bool>,
+) {
+    tokio::select! {
+        _ = tokio::time::sleep(*backoff) => {}
+        _ = crate::shutdown::requested(shutdown) => {}
     }
+    *backoff = (*backoff * 2).min(std::time::Duration::from_secs(30));
 }
@@ -0,0 +1,79 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+use prost::Message as _;
+use yellowstone_grpc_proto::prelude::SubscribeUpdate;
+
+const DEFAULT_ZSTD_LEVEL: i32 = 3;
+
+#[derive(Debug, Clone)]
+pub struct RecordConfig {
+    pub path: PathBuf,
+    pub zstd: bool,
+    /// Resume point of the recording, kept apart from the Kafka checkpoint so a
+    /// recording session never moves it past slots that were not published.
+    pub checkpoint: Option<PathBuf>,
+}
+
+impl RecordConfig {
+    /// `INGESTOR_MODE=record` enables recording to `RECORD_PATH` instead of publishing.
+    pub fn from_env() -> Option<Self> {
+        let mode = std::env::var("INGESTOR_MODE").unwrap_or_default();
+        if !mode.eq_ignore_ascii_case("record") {
+            return None;
+        }
+
+        let path = std::env::var("RECORD_PATH")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| PathBuf::from("updates.bin"));
+        let zstd = std::env::var("RECORD_ZSTD")
+            .map(|value| value == "1" || value.eq_ignore_ascii_case("true"))
+            .unwrap_or_else(|_| path.extension().is_some_and(|ext| ext == "zst"));
+        let checkpoint = std::env::var("RECORD_CHECKPOINT_PATH")
+            .ok()
+            .filter(|value| !value.trim().is_empty())
+            .map(PathBuf::from);
+
+        Some(Self {
+            path,
+            zstd,
+            checkpoint,
+        })
+    }
+}
+
+/// Writes raw updates as varint length-delimited protobuf (`encode_length_delimited`),
+/// optionally wrapped in a zstd stream. Readers detect zstd from the frame magic.
+pub struct UpdateRecorder {
+    writer: Box<dyn Write + Send>,
+    count: u64,
+}
+
+impl UpdateRecorder {
+    pub fn create(config: &RecordConfig) -> std::io::Result<Self> {
+        let file = BufWriter::new(File::create(&config.path)?);
+        let writer: Box<dyn Write + Send> = if config.zstd {
+            Box::new(zstd::Encoder::new(file, DEFAULT_ZSTD_LEVEL)?.auto_finish())
+        } else {
+            Box::new(file)
+        };
+
+        Ok(Self { writer, count: 0 })
+    }
+
+    pub fn write(&mut self, update: &SubscribeUpdate) -> std::io::Result<()> {
+        self.writer
+            .write_all(&update.encode_length_delimited_to_vec())?;
+        self.count += 1;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+}
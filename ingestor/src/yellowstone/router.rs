@@ -31,6 +31,19 @@ pub fn serialize_payload(payload: &KafkaPayload) -> String {
     serde_json::to_string(payload).expect("Kafka payload serialization failed")
 }
 
+pub fn update_slot(update: &SubscribeUpdate) -> Option<u64> {
+    match update.update_oneof.as_ref()? {
+        UpdateOneof::Account(account) => Some(account.slot),
+        UpdateOneof::Slot(slot) => Some(slot.slot),
+        UpdateOneof::Transaction(tx) => Some(tx.slot),
+        UpdateOneof::TransactionStatus(status) => Some(status.slot),
+        UpdateOneof::Block(block) => Some(block.slot),
+        UpdateOneof::BlockMeta(meta) => Some(meta.slot),
+        UpdateOneof::Entry(entry) => Some(entry.slot),
+        UpdateOneof::Ping(_) | UpdateOneof::Pong(_) => None,
+    }
+}
+
 fn normalize(update: &SubscribeUpdate) -> KafkaPayload {
     let filters = update.filters.clone();
     let created_at = update
@@ -1,18 +1,44 @@
 use std::collections::HashMap;
 
 use yellowstone_grpc_proto::geyser::{
-    CommitmentLevel, SubscribeRequest, SubscribeRequestFilterTransactions,
+    CommitmentLevel, SubscribeRequest, SubscribeRequestFilterAccounts,
+    SubscribeRequestFilterAccountsFilter, SubscribeRequestFilterAccountsFilterMemcmp,
+    SubscribeRequestFilterBlocksMeta, SubscribeRequestFilterTransactions,
+    subscribe_request_filter_accounts_filter::Filter,
+    subscribe_request_filter_accounts_filter_memcmp::Data,
 };
 
 const DEFAULT_TOKEN_OWNERS: [&str; 2] = [
     "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
     "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb",
 ];
+/// Token-2022 mints carry the transfer fee config the token indexer splits fees
+/// with; PumpSwap base mints of either program carry the supply market caps need.
+const DEFAULT_MINT_OWNERS: &str = "token=TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb;\
+    pumpswap=TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA,TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb";
+/// The PumpSwap global config holds the fee rates trades without an event are
+/// priced with.
+const DEFAULT_ACCOUNTS: &str = "pumpswap=ADyA8hdefvWN2dbGGWFotbzWxrAvLW83WG6QCVXvJKqw";
+
+/// Block metas carry the block time the indexers stamp their events with, so they
+/// are published to every filter's topic.
+const BLOCKS_META_FILTER: &str = "blocks_meta";
+
+/// Size of a mint without extensions; Token-2022 mints with extensions are longer
+/// and carry their account type after the 165 bytes of a token account.
+const MINT_LEN: u64 = 82;
+const ACCOUNT_TYPE_OFFSET: u64 = 165;
+const ACCOUNT_TYPE_MINT: u8 = 1;
 
 #[derive(Debug, Clone)]
 pub struct FilterConfig {
     pub name: String,
     pub owners: Vec<String>,
+    /// Token programs whose mint account writes are also published to this
+    /// filter's topic.
+    pub mint_owners: Vec<String>,
+    /// Accounts whose writes are also published to this filter's topic.
+    pub accounts: Vec<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -24,7 +50,7 @@ pub struct SubscriptionConfig {
 
 impl SubscriptionConfig {
     pub fn from_env(topic_prefix: String) -> Self {
-        let filters = std::env::var("YELLOWSTONE_FILTERS")
+        let mut filters = std::env::var("YELLOWSTONE_FILTERS")
             .ok()
             .and_then(|value| {
                 let parsed = parse_filters(&value);
@@ -35,6 +61,26 @@ impl SubscriptionConfig {
                 }
             })
             .unwrap_or_else(default_filters);
+        let mint_owners = std::env::var("YELLOWSTONE_MINT_OWNERS")
+            .unwrap_or_else(|_| DEFAULT_MINT_OWNERS.to_string());
+        for mint_filter in parse_filters(&mint_owners) {
+            if let Some(filter) = filters
+                .iter_mut()
+                .find(|filter| filter.name == mint_filter.name)
+            {
+                filter.mint_owners = mint_filter.owners;
+            }
+        }
+        let accounts =
+            std::env::var("YELLOWSTONE_ACCOUNTS").unwrap_or_else(|_| DEFAULT_ACCOUNTS.to_string());
+        for account_filter in parse_filters(&accounts) {
+            if let Some(filter) = filters
+                .iter_mut()
+                .find(|filter| filter.name == account_filter.name)
+            {
+                filter.accounts = account_filter.owners;
+            }
+        }
         let max_filters = std::env::var("YELLOWSTONE_MAX_FILTERS")
             .ok()
             .and_then(|value| value.parse::<usize>().ok())
@@ -52,8 +98,18 @@ impl SubscriptionConfig {
         let mut seen = std::collections::HashSet::new();
         let mut matched = false;
 
+        if filters.iter().any(|name| name == BLOCKS_META_FILTER) {
+            for filter in &self.filters {
+                let topic = format!("{}.{}", self.topic_prefix, filter.name);
+                if seen.insert(topic.clone()) {
+                    topics.push(topic);
+                }
+            }
+            return topics;
+        }
+
         for filter in &self.filters {
-            if filters.iter().any(|name| name == &filter.name) {
+            if filters.iter().any(|name| routes_to(name, &filter.name)) {
                 let topic = format!("{}.{}", self.topic_prefix, filter.name);
                 if seen.insert(topic.clone()) {
                     topics.push(topic);
@@ -63,9 +119,18 @@ impl SubscriptionConfig {
         }
 
         if !matched {
+            // Merged account filters carry the owning program: mint writes go to
+            // the filters taking that token program's mints, other accounts to
+            // the filters subscribed to that program's transactions.
+            let kind = filters
+                .iter()
+                .find_map(|name| name.split_once('.').map(|(_, kind)| kind));
             for filter in &self.filters {
-                if filter
-                    .owners
+                let owners = match kind {
+                    Some("mints" | "extended_mints") => &filter.mint_owners,
+                    _ => &filter.owners,
+                };
+                if owners
                     .iter()
                     .any(|owner| program_ids.iter().any(|id| id == owner))
                 {
@@ -85,8 +150,19 @@ impl SubscriptionConfig {
     }
 }
 
-pub fn create_subscription_request(config: &SubscriptionConfig) -> SubscribeRequest {
+/// Whether an update matching the request filter `name` belongs to `filter`: its
+/// transaction filter, or one of its account filters named `<filter>.<kind>`.
+fn routes_to(name: &str, filter: &str) -> bool {
+    name.strip_prefix(filter)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+}
+
+pub fn create_subscription_request(
+    config: &SubscriptionConfig,
+    from_slot: Option<u64>,
+) -> SubscribeRequest {
     let mut transactions = HashMap::new();
+    let mut accounts = HashMap::new();
 
     let filter_limit = if config.max_filters == 0 {
         None
@@ -114,6 +190,22 @@ pub fn create_subscription_request(config: &SubscriptionConfig) -> SubscribeRequ
                     account_required: vec![],
                 },
             );
+
+            let mut mint_owners = std::collections::HashSet::new();
+            for filter in &config.filters {
+                mint_owners.extend(filter.mint_owners.iter().cloned());
+            }
+            insert_mint_filters(&mut accounts, "combined", mint_owners.into_iter().collect());
+
+            let mut account_keys = std::collections::HashSet::new();
+            for filter in &config.filters {
+                account_keys.extend(filter.accounts.iter().cloned());
+            }
+            insert_account_filter(
+                &mut accounts,
+                "combined",
+                account_keys.into_iter().collect(),
+            );
         } else {
             for filter in &config.filters {
                 if filter.owners.is_empty() {
@@ -131,6 +223,8 @@ pub fn create_subscription_request(config: &SubscriptionConfig) -> SubscribeRequ
                         account_required: vec![],
                     },
                 );
+                insert_mint_filters(&mut accounts, &filter.name, filter.mint_owners.clone());
+                insert_account_filter(&mut accounts, &filter.name, filter.accounts.clone());
             }
         }
     } else {
@@ -150,24 +244,92 @@ pub fn create_subscription_request(config: &SubscriptionConfig) -> SubscribeRequ
                     account_required: vec![],
                 },
             );
+            insert_mint_filters(&mut accounts, &filter.name, filter.mint_owners.clone());
+            insert_account_filter(&mut accounts, &filter.name, filter.accounts.clone());
         }
     }
 
+    let mut blocks_meta = HashMap::new();
+    blocks_meta.insert(
+        BLOCKS_META_FILTER.to_string(),
+        SubscribeRequestFilterBlocksMeta {},
+    );
+
     SubscribeRequest {
-        accounts: HashMap::new(),
+        accounts,
         slots: HashMap::new(),
         transactions,
         transactions_status: HashMap::new(),
         blocks: HashMap::new(),
-        blocks_meta: HashMap::new(),
+        blocks_meta,
         entry: HashMap::new(),
         commitment: Some(CommitmentLevel::Confirmed as i32),
         accounts_data_slice: vec![],
         ping: None,
-        from_slot: None,
+        from_slot,
     }
 }
 
+/// Subscribes to writes of mints owned by `owners` as `<name>.mints` (no
+/// extensions) and `<name>.extended_mints` (Token-2022 mints with extensions),
+/// leaving token accounts out.
+fn insert_mint_filters(
+    accounts: &mut HashMap<String, SubscribeRequestFilterAccounts>,
+    name: &str,
+    owners: Vec<String>,
+) {
+    if owners.is_empty() {
+        return;
+    }
+
+    accounts.insert(
+        format!("{name}.mints"),
+        SubscribeRequestFilterAccounts {
+            account: vec![],
+            owner: owners.clone(),
+            filters: vec![SubscribeRequestFilterAccountsFilter {
+                filter: Some(Filter::Datasize(MINT_LEN)),
+            }],
+            nonempty_txn_signature: None,
+        },
+    );
+    accounts.insert(
+        format!("{name}.extended_mints"),
+        SubscribeRequestFilterAccounts {
+            account: vec![],
+            owner: owners,
+            filters: vec![SubscribeRequestFilterAccountsFilter {
+                filter: Some(Filter::Memcmp(SubscribeRequestFilterAccountsFilterMemcmp {
+                    offset: ACCOUNT_TYPE_OFFSET,
+                    data: Some(Data::Bytes(vec![ACCOUNT_TYPE_MINT])),
+                })),
+            }],
+            nonempty_txn_signature: None,
+        },
+    );
+}
+
+/// Subscribes to writes of `account_keys` as `<name>.accounts`.
+fn insert_account_filter(
+    accounts: &mut HashMap<String, SubscribeRequestFilterAccounts>,
+    name: &str,
+    account_keys: Vec<String>,
+) {
+    if account_keys.is_empty() {
+        return;
+    }
+
+    accounts.insert(
+        format!("{name}.accounts"),
+        SubscribeRequestFilterAccounts {
+            account: account_keys,
+            owner: vec![],
+            filters: vec![],
+            nonempty_txn_signature: None,
+        },
+    );
+}
+
 fn default_filters() -> Vec<FilterConfig> {
     vec![FilterConfig {
         name: "token".to_string(),
@@ -175,6 +337,8 @@ fn default_filters() -> Vec<FilterConfig> {
             .iter()
             .map(|id| (*id).to_string())
             .collect(),
+        mint_owners: Vec::new(),
+        accounts: Vec::new(),
     }]
 }
 
@@ -206,6 +370,8 @@ fn parse_filters(raw: &str) -> Vec<FilterConfig> {
         filters.push(FilterConfig {
             name: name.to_string(),
             owners,
+            mint_owners: Vec::new(),
+            accounts: Vec::new(),
         });
     }
 
@@ -0,0 +1,441 @@
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+
+use ingestor::yellowstone::YellowstoneWorker;
+use ingestor::yellowstone::checkpoint::SlotCheckpoint;
+use ingestor::yellowstone::kafka::{Discard, Publisher};
+use ingestor::yellowstone::recorder::RecordConfig;
+use ingestor::yellowstone::subscriptions::{
+    FilterConfig, SubscriptionConfig, create_subscription_request,
+};
+use mock_geyser::{MockGeyser, MockGeyserConfig, recording, synthetic};
+use solana_sdk::pubkey::Pubkey;
+use tokio::sync::watch;
+
+#[derive(Default)]
+struct MemorySink {
+    sent: Mutex<Vec<(String, String)>>,
+    flushed: AtomicBool,
+    /// Sends whose key contains this fail, as if Kafka had rejected them, until
+    /// `failures_left` runs out.
+    failing_key: Option<String>,
+    failures_left: AtomicUsize,
+}
+
+impl MemorySink {
+    fn topics(&self) -> Vec<String> {
+        self.sent
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(topic, _)| topic.clone())
+            .collect()
+    }
+
+    fn len(&self) -> usize {
+        self.sent.lock().unwrap().len()
+    }
+}
+
+impl Publisher for MemorySink {
+    type Error = String;
+
+    async fn send_to(&self, topic: &str, key: &str, _payload: &str) -> Result<(), String> {
+        if self
+            .failing_key
+            .as_ref()
+            .is_some_and(|failing| key.contains(failing.as_str()))
+            && self
+                .failures_left
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| {
+                    left.checked_sub(1)
+                })
+                .is_ok()
+        {
+            return Err(format!("send of {key} failed"));
+        }
+        self.sent
+            .lock()
+            .unwrap()
+            .push((topic.to_string(), key.to_string()));
+        Ok(())
+    }
+
+    fn flush(&self, _timeout: Duration) -> Result<(), String> {
+        self.flushed.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+fn subscription_config() -> SubscriptionConfig {
+    SubscriptionConfig {
+        topic_prefix: "ingest".to_string(),
+        filters: vec![
+            FilterConfig {
+                name: "token".to_string(),
+                owners: vec![synthetic::TOKEN_PROGRAM_ID.to_string()],
+                mint_owners: vec![synthetic::TOKEN_2022_PROGRAM_ID.to_string()],
+                accounts: Vec::new(),
+            },
+            FilterConfig {
+                name: "pumpswap".to_string(),
+                owners: vec![synthetic::PUMPSWAP_PROGRAM_ID.to_string()],
+                mint_owners: Vec::new(),
+                accounts: Vec::new(),
+            },
+        ],
+        max_filters: 0,
+    }
+}
+
+fn temp_path(name: &str, extension: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "ingestor-{}-{}.{}",
+        name,
+        std::process::id(),
+        extension
+    ));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+/// Runs the worker against `endpoint` until `sink` has seen `expected` payloads.
+async fn run_until(endpoint: String, checkpoint: PathBuf, sink: &MemorySink, expected: usize) {
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let worker = YellowstoneWorker::new(
+        endpoint,
+        None,
+        "unused".to_string(),
+        subscription_config(),
+        SlotCheckpoint::load(Some(checkpoint)),
+        None,
+        Duration::from_secs(1),
+    );
+
+    let driver = async {
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(15);
+        while sink.len() < expected && tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        let _ = shutdown_tx.send(true);
+    };
+
+    tokio::join!(worker.run_with(sink, shutdown_rx), driver);
+}
+
+fn localhost() -> SocketAddr {
+    "127.0.0.1:0".parse().unwrap()
+}
+
+#[tokio::test]
+async fn routes_by_filter_and_resumes_from_checkpoint_after_disconnect() {
+    let config = MockGeyserConfig {
+        disconnects: vec![2],
+        ..Default::default()
+    };
+    let server = MockGeyser::new(synthetic::default_stream(100, 3), config)
+        .serve(localhost())
+        .await
+        .unwrap();
+    let path = temp_path("reconnect", "checkpoint");
+    let sink = MemorySink::default();
+
+    // 2 updates before the injected disconnect, then slots 100..=102 again (2 txs each).
+    run_until(server.endpoint(), path.clone(), &sink, 8).await;
+
+    let requests = server.requests();
+    assert_eq!(server.connections(), 2);
+    assert_eq!(requests[0].from_slot, None);
+    assert_eq!(requests[1].from_slot, Some(100));
+    assert!(requests[0].transactions.contains_key("token"));
+    assert!(requests[0].transactions.contains_key("pumpswap"));
+
+    let topics = sink.topics();
+    assert_eq!(topics.len(), 8);
+    assert_eq!(topics[0], "ingest.token");
+    assert_eq!(topics[1], "ingest.pumpswap");
+    assert!(!topics.iter().any(|topic| topic == "ingest.raw"));
+
+    assert!(sink.flushed.load(Ordering::SeqCst));
+    assert_eq!(std::fs::read_to_string(&path).unwrap().trim(), "102");
+
+    server.shutdown().await;
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn starts_from_saved_checkpoint() {
+    let server = MockGeyser::new(
+        synthetic::default_stream(100, 3),
+        MockGeyserConfig::default(),
+    )
+    .serve(localhost())
+    .await
+    .unwrap();
+    let path = temp_path("resume", "checkpoint");
+    std::fs::write(&path, "101\n").unwrap();
+    let sink = MemorySink::default();
+
+    run_until(server.endpoint(), path.clone(), &sink, 4).await;
+
+    let requests = server.requests();
+    assert_eq!(requests[0].from_slot, Some(101));
+    assert_eq!(sink.len(), 4);
+    assert!(
+        sink.sent
+            .lock()
+            .unwrap()
+            .iter()
+            .all(|(_, key)| !key.contains(":100:"))
+    );
+
+    server.shutdown().await;
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn resubscribes_from_last_published_slot_after_a_failed_send() {
+    let server = MockGeyser::new(
+        synthetic::default_stream(100, 3),
+        MockGeyserConfig::default(),
+    )
+    .serve(localhost())
+    .await
+    .unwrap();
+    let path = temp_path("failed_send", "checkpoint");
+    let sink = MemorySink {
+        failing_key: Some(":101:".to_string()),
+        failures_left: AtomicUsize::new(1),
+        ..Default::default()
+    };
+
+    // Slot 100 goes through, the first send for slot 101 fails, and the worker
+    // resubscribes from slot 100: 2 sends, then slots 100..=102 again.
+    run_until(server.endpoint(), path.clone(), &sink, 8).await;
+
+    let requests = server.requests();
+    assert_eq!(server.connections(), 2);
+    assert_eq!(requests[1].from_slot, Some(100));
+    assert_eq!(sink.len(), 8);
+    assert!(
+        sink.sent
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|(_, key)| key.contains(":101:"))
+    );
+    assert_eq!(std::fs::read_to_string(&path).unwrap().trim(), "102");
+
+    server.shutdown().await;
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn routes_mint_writes_to_their_filter_topic() {
+    let mint = Pubkey::new_from_array([6u8; 32]);
+    let token_account = Pubkey::new_from_array([7u8; 32]);
+    let mut updates = synthetic::default_stream(100, 2);
+    updates.insert(
+        2,
+        synthetic::account(100, mint, synthetic::TOKEN_2022_PROGRAM_ID, vec![0; 82]),
+    );
+    // Not a mint owner of any filter, so never sent.
+    updates.insert(
+        3,
+        synthetic::account(
+            100,
+            token_account,
+            synthetic::TOKEN_PROGRAM_ID,
+            vec![0; 165],
+        ),
+    );
+    let server = MockGeyser::new(updates, MockGeyserConfig::default())
+        .serve(localhost())
+        .await
+        .unwrap();
+    let path = temp_path("mints", "checkpoint");
+    let sink = MemorySink::default();
+
+    run_until(server.endpoint(), path.clone(), &sink, 5).await;
+
+    let requests = server.requests();
+    assert!(requests[0].accounts.contains_key("token.mints"));
+    assert!(requests[0].accounts.contains_key("token.extended_mints"));
+    assert_eq!(requests[0].accounts.len(), 2);
+
+    let sent = sink.sent.lock().unwrap().clone();
+    assert_eq!(sent.len(), 5);
+    assert_eq!(
+        sent[2],
+        ("ingest.token".to_string(), format!("account:{mint}:100:1"))
+    );
+    assert!(
+        !sent
+            .iter()
+            .any(|(_, key)| key.contains(&token_account.to_string()))
+    );
+
+    server.shutdown().await;
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn routes_merged_mint_writes_by_mint_owner() {
+    let mut config = subscription_config();
+    config.max_filters = 1;
+    config.filters[1].mint_owners = vec![synthetic::TOKEN_PROGRAM_ID.to_string()];
+
+    let request = create_subscription_request(&config, None);
+    assert!(request.accounts.contains_key("combined.mints"));
+
+    let mint_filter = ["combined.mints".to_string()];
+    assert_eq!(
+        config.topics_for_update(&mint_filter, &[synthetic::TOKEN_PROGRAM_ID.to_string()]),
+        ["ingest.pumpswap"]
+    );
+    assert_eq!(
+        config.topics_for_update(
+            &mint_filter,
+            &[synthetic::TOKEN_2022_PROGRAM_ID.to_string()]
+        ),
+        ["ingest.token"]
+    );
+    // Transactions are still routed by the programs they invoke.
+    assert_eq!(
+        config.topics_for_update(
+            &["combined".to_string()],
+            &[synthetic::TOKEN_PROGRAM_ID.to_string()]
+        ),
+        ["ingest.token"]
+    );
+}
+
+#[test]
+fn routes_account_writes_to_their_filter_topic() {
+    let global_config = Pubkey::new_from_array([8u8; 32]).to_string();
+    let mut config = subscription_config();
+    config.filters[1].accounts = vec![global_config.clone()];
+    let owner = [synthetic::PUMPSWAP_PROGRAM_ID.to_string()];
+
+    let request = create_subscription_request(&config, None);
+    assert_eq!(
+        request.accounts["pumpswap.accounts"].account,
+        [global_config.clone()]
+    );
+    assert_eq!(
+        config.topics_for_update(&["pumpswap.accounts".to_string()], &owner),
+        ["ingest.pumpswap"]
+    );
+
+    config.max_filters = 1;
+    let request = create_subscription_request(&config, None);
+    assert_eq!(
+        request.accounts["combined.accounts"].account,
+        [global_config]
+    );
+    assert_eq!(
+        config.topics_for_update(&["combined.accounts".to_string()], &owner),
+        ["ingest.pumpswap"]
+    );
+}
+
+#[tokio::test]
+async fn publishes_block_metas_to_every_filter_topic() {
+    let mut updates = synthetic::default_stream(100, 2);
+    updates.insert(2, synthetic::block_meta(100, 1_700_000_000));
+    let server = MockGeyser::new(updates, MockGeyserConfig::default())
+        .serve(localhost())
+        .await
+        .unwrap();
+    let path = temp_path("block_meta", "checkpoint");
+    let sink = MemorySink::default();
+
+    run_until(server.endpoint(), path.clone(), &sink, 6).await;
+
+    let requests = server.requests();
+    assert!(requests[0].blocks_meta.contains_key("blocks_meta"));
+
+    let block_meta_topics = sink
+        .sent
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, key)| key == "block_meta:100")
+        .map(|(topic, _)| topic.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(block_meta_topics, ["ingest.token", "ingest.pumpswap"]);
+    assert!(!sink.topics().iter().any(|topic| topic == "ingest.raw"));
+
+    server.shutdown().await;
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn record_mode_writes_replayable_file() {
+    let updates = synthetic::default_stream(200, 2);
+    let config = MockGeyserConfig {
+        close_when_done: true,
+        ..Default::default()
+    };
+    let server = MockGeyser::new(updates, config)
+        .serve(localhost())
+        .await
+        .unwrap();
+    let checkpoint = temp_path("record", "checkpoint");
+    let record_checkpoint = temp_path("record_resume", "checkpoint");
+    let record_path = temp_path("record", "bin.zst");
+    let worker = YellowstoneWorker::new(
+        server.endpoint(),
+        None,
+        "unused".to_string(),
+        subscription_config(),
+        SlotCheckpoint::load(Some(checkpoint.clone())),
+        Some(RecordConfig {
+            path: record_path.clone(),
+            zstd: true,
+            checkpoint: Some(record_checkpoint.clone()),
+        }),
+        Duration::from_secs(1),
+    );
+
+    // The checkpoint is written when the mock closes the stream after the last update.
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let driver = async {
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(15);
+        while !record_checkpoint.exists() && tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        let _ = shutdown_tx.send(true);
+    };
+    tokio::join!(worker.run_with(&Discard, shutdown_rx), driver);
+
+    let recorded = recording::read_updates(&record_path).unwrap();
+    assert_eq!(recorded.len(), 4);
+    assert!(recorded.iter().all(|update| !update.filters.is_empty()));
+    assert_eq!(
+        std::fs::read_to_string(&record_checkpoint).unwrap().trim(),
+        "201"
+    );
+    // Nothing was published, so the Kafka resume point is left alone.
+    assert!(!checkpoint.exists());
+
+    server.shutdown().await;
+    let _ = std::fs::remove_file(checkpoint);
+    let _ = std::fs::remove_file(record_checkpoint);
+    let _ = std::fs::remove_file(record_path);
+}
+
+#[test]
+fn recording_rejects_oversized_length_prefix() {
+    let path = temp_path("oversized", "bin");
+    // Varint length prefix of 2^32 - 1 with no payload behind it.
+    std::fs::write(&path, [0xff, 0xff, 0xff, 0xff, 0x0f]).unwrap();
+
+    let err = recording::read_updates(&path).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+    let _ = std::fs::remove_file(path);
+}
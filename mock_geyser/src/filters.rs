@@ -0,0 +1,139 @@
+use std::collections::HashSet;
+
+use solana_sdk::bs58;
+use yellowstone_grpc_proto::prelude::{
+    SubscribeRequest, SubscribeRequestFilterAccounts, SubscribeRequestFilterTransactions,
+    SubscribeUpdate, SubscribeUpdateAccount, SubscribeUpdateTransactionInfo,
+    subscribe_update::UpdateOneof,
+};
+
+/// Returns the names of the request filters an update matches, or `None` when the
+/// update should not be sent. Pings and pongs are never filtered.
+///
+/// Account `filters` (memcmp/datasize) and `accounts_data_slice` are not applied.
+pub fn matching_filters(
+    request: &SubscribeRequest,
+    update: &SubscribeUpdate,
+) -> Option<Vec<String>> {
+    let mut names = match update.update_oneof.as_ref()? {
+        UpdateOneof::Transaction(tx_update) => {
+            let tx = tx_update.transaction.as_ref()?;
+            request
+                .transactions
+                .iter()
+                .filter(|(_, filter)| transaction_matches(filter, tx))
+                .map(|(name, _)| name.clone())
+                .collect::<Vec<_>>()
+        }
+        UpdateOneof::Account(account_update) => request
+            .accounts
+            .iter()
+            .filter(|(_, filter)| account_matches(filter, account_update))
+            .map(|(name, _)| name.clone())
+            .collect(),
+        UpdateOneof::Slot(_) => request.slots.keys().cloned().collect(),
+        UpdateOneof::TransactionStatus(_) => request.transactions_status.keys().cloned().collect(),
+        UpdateOneof::Block(_) => request.blocks.keys().cloned().collect(),
+        UpdateOneof::BlockMeta(_) => request.blocks_meta.keys().cloned().collect(),
+        UpdateOneof::Entry(_) => request.entry.keys().cloned().collect(),
+        UpdateOneof::Ping(_) | UpdateOneof::Pong(_) => return Some(Vec::new()),
+    };
+
+    if names.is_empty() {
+        return None;
+    }
+
+    names.sort();
+    Some(names)
+}
+
+/// Slot an update belongs to, used to honour `from_slot`.
+pub fn update_slot(update: &SubscribeUpdate) -> Option<u64> {
+    match update.update_oneof.as_ref()? {
+        UpdateOneof::Account(account) => Some(account.slot),
+        UpdateOneof::Slot(slot) => Some(slot.slot),
+        UpdateOneof::Transaction(tx) => Some(tx.slot),
+        UpdateOneof::TransactionStatus(status) => Some(status.slot),
+        UpdateOneof::Block(block) => Some(block.slot),
+        UpdateOneof::BlockMeta(meta) => Some(meta.slot),
+        UpdateOneof::Entry(entry) => Some(entry.slot),
+        UpdateOneof::Ping(_) | UpdateOneof::Pong(_) => None,
+    }
+}
+
+fn transaction_matches(
+    filter: &SubscribeRequestFilterTransactions,
+    tx: &SubscribeUpdateTransactionInfo,
+) -> bool {
+    if filter.vote.is_some_and(|vote| vote != tx.is_vote) {
+        return false;
+    }
+
+    let failed = tx.meta.as_ref().is_some_and(|meta| meta.err.is_some());
+    if filter
+        .failed
+        .is_some_and(|want_failed| want_failed != failed)
+    {
+        return false;
+    }
+
+    if let Some(signature) = filter.signature.as_ref()
+        && *signature != bs58::encode(&tx.signature).into_string()
+    {
+        return false;
+    }
+
+    let keys = transaction_keys(tx);
+
+    if !filter.account_include.is_empty()
+        && !filter.account_include.iter().any(|key| keys.contains(key))
+    {
+        return false;
+    }
+
+    if filter.account_exclude.iter().any(|key| keys.contains(key)) {
+        return false;
+    }
+
+    filter.account_required.iter().all(|key| keys.contains(key))
+}
+
+fn account_matches(
+    filter: &SubscribeRequestFilterAccounts,
+    update: &SubscribeUpdateAccount,
+) -> bool {
+    let Some(account) = update.account.as_ref() else {
+        return false;
+    };
+
+    let pubkey = bs58::encode(&account.pubkey).into_string();
+    let owner = bs58::encode(&account.owner).into_string();
+
+    (filter.account.is_empty() || filter.account.contains(&pubkey))
+        && (filter.owner.is_empty() || filter.owner.contains(&owner))
+}
+
+/// Static account keys plus addresses loaded from lookup tables.
+fn transaction_keys(tx: &SubscribeUpdateTransactionInfo) -> HashSet<String> {
+    let mut keys = HashSet::new();
+
+    if let Some(message) = tx.transaction.as_ref().and_then(|t| t.message.as_ref()) {
+        keys.extend(
+            message
+                .account_keys
+                .iter()
+                .map(|key| bs58::encode(key).into_string()),
+        );
+    }
+
+    if let Some(meta) = tx.meta.as_ref() {
+        keys.extend(
+            meta.loaded_writable_addresses
+                .iter()
+                .chain(meta.loaded_readonly_addresses.iter())
+                .map(|key| bs58::encode(key).into_string()),
+        );
+    }
+
+    keys
+}
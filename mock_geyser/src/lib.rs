@@ -0,0 +1,12 @@
+//! In-process Yellowstone (Geyser) gRPC server for offline tests.
+//!
+//! Replays a list of `SubscribeUpdate`s to each subscriber, tagging them with the
+//! filter names from the client's `SubscribeRequest` the same way a real endpoint
+//! does, so routing can be exercised without a provider.
+
+pub mod filters;
+pub mod recording;
+pub mod server;
+pub mod synthetic;
+
+pub use server::{MockGeyser, MockGeyserConfig, MockGeyserHandle};
@@ -0,0 +1,63 @@
+use std::error::Error;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::Duration;
+
+use mock_geyser::{MockGeyser, MockGeyserConfig, recording, synthetic};
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    dotenv::dotenv().ok();
+
+    let addr: SocketAddr = std::env::var("MOCK_GEYSER_ADDR")
+        .unwrap_or_else(|_| "127.0.0.1:10000".to_string())
+        .parse()?;
+    let start_slot = env_u64("MOCK_GEYSER_START_SLOT").unwrap_or(1);
+    let slots = env_u64("MOCK_GEYSER_SLOTS").unwrap_or(100);
+    let ping_interval = env_u64("MOCK_GEYSER_PING_SECS").map(Duration::from_secs);
+    let disconnects = std::env::var("MOCK_GEYSER_DISCONNECTS")
+        .ok()
+        .map(|value| {
+            value
+                .split(',')
+                .filter_map(|count| count.trim().parse::<usize>().ok())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    let updates = match std::env::var("MOCK_GEYSER_REPLAY_PATH") {
+        Ok(path) => recording::read_updates(Path::new(&path))?,
+        Err(_) => synthetic::default_stream(start_slot, slots),
+    };
+
+    println!("Starting mock Yellowstone gRPC server...");
+    println!("   Address: {}", addr);
+    println!(
+        "   Updates: {} (slots {}..{})",
+        updates.len(),
+        start_slot,
+        start_slot + slots
+    );
+    println!("   Disconnects: {:?}", disconnects);
+
+    let config = MockGeyserConfig {
+        ping_interval,
+        disconnects,
+        close_when_done: false,
+    };
+    let handle = MockGeyser::new(updates, config).serve(addr).await?;
+    println!("Listening on {}", handle.endpoint());
+
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = handle.wait() => {}
+    }
+
+    Ok(())
+}
+
+fn env_u64(name: &str) -> Option<u64> {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse::<u64>().ok())
+}
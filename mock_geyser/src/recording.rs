@@ -0,0 +1,4 @@
+//! Recordings from the ingestor's record mode, loaded whole so they can be served
+//! from [`crate::MockGeyser`].
+
+pub use stream_common::recording::read_updates;
@@ -0,0 +1,340 @@
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::Stream;
+use tokio::net::TcpListener;
+use tokio::sync::{mpsc, oneshot};
+use tonic::transport::Server;
+use tonic::transport::server::TcpIncoming;
+use tonic::{Request, Response, Status, Streaming};
+use yellowstone_grpc_proto::geyser::geyser_server::{Geyser, GeyserServer};
+use yellowstone_grpc_proto::prelude::{
+    GetBlockHeightRequest, GetBlockHeightResponse, GetLatestBlockhashRequest,
+    GetLatestBlockhashResponse, GetSlotRequest, GetSlotResponse, GetVersionRequest,
+    GetVersionResponse, IsBlockhashValidRequest, IsBlockhashValidResponse, PingRequest,
+    PongResponse, SubscribeRequest, SubscribeUpdate, SubscribeUpdatePing, SubscribeUpdatePong,
+    subscribe_update::UpdateOneof,
+};
+
+use crate::filters;
+
+type UpdateStream = Pin<Box<dyn Stream<Item = Result<SubscribeUpdate, Status>> + Send>>;
+
+#[derive(Debug, Clone, Default)]
+pub struct MockGeyserConfig {
+    /// Send a server-side ping update at this interval.
+    pub ping_interval: Option<Duration>,
+    /// `disconnects[n]` is the number of updates sent on the n-th subscription before
+    /// the stream is terminated with `UNAVAILABLE`. Later subscriptions run to the end.
+    pub disconnects: Vec<usize>,
+    /// End the stream after the last update instead of idling like a live endpoint.
+    pub close_when_done: bool,
+}
+
+/// Geyser service that replays a fixed list of updates to every subscriber.
+#[derive(Clone)]
+pub struct MockGeyser {
+    updates: Arc<Vec<SubscribeUpdate>>,
+    config: MockGeyserConfig,
+    requests: Arc<Mutex<Vec<SubscribeRequest>>>,
+    connections: Arc<AtomicUsize>,
+}
+
+impl MockGeyser {
+    pub fn new(updates: Vec<SubscribeUpdate>, config: MockGeyserConfig) -> Self {
+        Self {
+            updates: Arc::new(updates),
+            config,
+            requests: Arc::new(Mutex::new(Vec::new())),
+            connections: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Binds `addr` (use port 0 for an ephemeral port) and serves in the background.
+    pub async fn serve(self, addr: SocketAddr) -> std::io::Result<MockGeyserHandle> {
+        let listener = TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+        let incoming = TcpIncoming::from_listener(listener, true, None)
+            .map_err(|err| std::io::Error::other(err.to_string()))?;
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        let requests = self.requests.clone();
+        let connections = self.connections.clone();
+
+        let task = tokio::spawn(async move {
+            let result = Server::builder()
+                .add_service(GeyserServer::new(self))
+                .serve_with_incoming_shutdown(incoming, async {
+                    let _ = shutdown_rx.await;
+                })
+                .await;
+            if let Err(err) = result {
+                eprintln!("Mock Geyser server failed: {}", err);
+            }
+        });
+
+        Ok(MockGeyserHandle {
+            addr,
+            requests,
+            connections,
+            shutdown: Some(shutdown_tx),
+            task,
+        })
+    }
+
+    async fn replay(
+        &self,
+        connection: usize,
+        request: Arc<Mutex<SubscribeRequest>>,
+        tx: mpsc::Sender<Result<SubscribeUpdate, Status>>,
+    ) {
+        let disconnect_after = self.config.disconnects.get(connection).copied();
+        let mut ping = self.config.ping_interval.map(tokio::time::interval);
+        let mut sent = 0usize;
+
+        for update in self.updates.iter() {
+            if disconnect_after.is_some_and(|limit| sent >= limit) {
+                let _ = tx
+                    .send(Err(Status::unavailable("mock: injected disconnect")))
+                    .await;
+                return;
+            }
+
+            if let Some(ping) = ping.as_mut()
+                && tick_ready(ping)
+                && tx.send(Ok(ping_update())).await.is_err()
+            {
+                return;
+            }
+
+            let Some(update) = filtered(&request, update) else {
+                continue;
+            };
+
+            if tx.send(Ok(update)).await.is_err() {
+                return;
+            }
+            sent += 1;
+        }
+
+        if disconnect_after.is_some_and(|limit| sent >= limit) {
+            let _ = tx
+                .send(Err(Status::unavailable("mock: injected disconnect")))
+                .await;
+            return;
+        }
+
+        if self.config.close_when_done {
+            return;
+        }
+
+        match ping {
+            Some(mut ping) => loop {
+                ping.tick().await;
+                if tx.send(Ok(ping_update())).await.is_err() {
+                    return;
+                }
+            },
+            None => tx.closed().await,
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl Geyser for MockGeyser {
+    type SubscribeStream = UpdateStream;
+
+    async fn subscribe(
+        &self,
+        request: Request<Streaming<SubscribeRequest>>,
+    ) -> Result<Response<Self::SubscribeStream>, Status> {
+        let mut inbound = request.into_inner();
+        let first = inbound
+            .message()
+            .await?
+            .ok_or_else(|| Status::invalid_argument("mock: expected a SubscribeRequest"))?;
+
+        let connection = self.connections.fetch_add(1, Ordering::SeqCst);
+        self.requests.lock().unwrap().push(first.clone());
+
+        let from_slot = first.from_slot;
+        let active = Arc::new(Mutex::new(first));
+        let (tx, rx) = mpsc::channel(128);
+
+        // Later requests on the same stream replace the filters or carry client pings.
+        let reader_active = active.clone();
+        let reader_requests = self.requests.clone();
+        let reader_tx = tx.clone();
+        tokio::spawn(async move {
+            while let Ok(Some(next)) = inbound.message().await {
+                reader_requests.lock().unwrap().push(next.clone());
+                if let Some(ping) = next.ping.as_ref() {
+                    let pong = SubscribeUpdate {
+                        filters: Vec::new(),
+                        created_at: None,
+                        update_oneof: Some(UpdateOneof::Pong(SubscribeUpdatePong { id: ping.id })),
+                    };
+                    if reader_tx.send(Ok(pong)).await.is_err() {
+                        return;
+                    }
+                    continue;
+                }
+                *reader_active.lock().unwrap() = next;
+            }
+        });
+
+        let service = self.clone();
+        tokio::spawn(async move {
+            let service = match from_slot {
+                Some(from_slot) => service.starting_at(from_slot),
+                None => service,
+            };
+            service.replay(connection, active, tx).await;
+        });
+
+        let stream = futures::stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|item| (item, rx))
+        });
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn ping(&self, request: Request<PingRequest>) -> Result<Response<PongResponse>, Status> {
+        Ok(Response::new(PongResponse {
+            count: request.into_inner().count,
+        }))
+    }
+
+    async fn get_latest_blockhash(
+        &self,
+        _request: Request<GetLatestBlockhashRequest>,
+    ) -> Result<Response<GetLatestBlockhashResponse>, Status> {
+        Ok(Response::new(GetLatestBlockhashResponse {
+            slot: self.last_slot(),
+            blockhash: solana_sdk::hash::Hash::default().to_string(),
+            last_valid_block_height: 0,
+        }))
+    }
+
+    async fn get_block_height(
+        &self,
+        _request: Request<GetBlockHeightRequest>,
+    ) -> Result<Response<GetBlockHeightResponse>, Status> {
+        Ok(Response::new(GetBlockHeightResponse {
+            block_height: self.last_slot(),
+        }))
+    }
+
+    async fn get_slot(
+        &self,
+        _request: Request<GetSlotRequest>,
+    ) -> Result<Response<GetSlotResponse>, Status> {
+        Ok(Response::new(GetSlotResponse {
+            slot: self.last_slot(),
+        }))
+    }
+
+    async fn is_blockhash_valid(
+        &self,
+        _request: Request<IsBlockhashValidRequest>,
+    ) -> Result<Response<IsBlockhashValidResponse>, Status> {
+        Ok(Response::new(IsBlockhashValidResponse {
+            slot: self.last_slot(),
+            valid: true,
+        }))
+    }
+
+    async fn get_version(
+        &self,
+        _request: Request<GetVersionRequest>,
+    ) -> Result<Response<GetVersionResponse>, Status> {
+        Ok(Response::new(GetVersionResponse {
+            version: format!("mock_geyser {}", env!("CARGO_PKG_VERSION")),
+        }))
+    }
+}
+
+impl MockGeyser {
+    fn starting_at(mut self, from_slot: u64) -> Self {
+        let updates = self
+            .updates
+            .iter()
+            .filter(|update| filters::update_slot(update).is_none_or(|slot| slot >= from_slot))
+            .cloned()
+            .collect();
+        self.updates = Arc::new(updates);
+        self
+    }
+
+    fn last_slot(&self) -> u64 {
+        self.updates
+            .iter()
+            .filter_map(filters::update_slot)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+pub struct MockGeyserHandle {
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<SubscribeRequest>>>,
+    connections: Arc<AtomicUsize>,
+    shutdown: Option<oneshot::Sender<()>>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl MockGeyserHandle {
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn endpoint(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Every `SubscribeRequest` received so far, across all connections.
+    pub fn requests(&self) -> Vec<SubscribeRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    pub fn connections(&self) -> usize {
+        self.connections.load(Ordering::SeqCst)
+    }
+
+    pub async fn shutdown(mut self) {
+        if let Some(tx) = self.shutdown.take() {
+            let _ = tx.send(());
+        }
+        let _ = self.task.await;
+    }
+
+    /// Waits for the server task; used by the binary to serve until killed.
+    pub async fn wait(self) {
+        let _ = self.task.await;
+    }
+}
+
+fn filtered(
+    request: &Mutex<SubscribeRequest>,
+    update: &SubscribeUpdate,
+) -> Option<SubscribeUpdate> {
+    let names = filters::matching_filters(&request.lock().unwrap(), update)?;
+    let mut update = update.clone();
+    update.filters = names;
+    Some(update)
+}
+
+fn ping_update() -> SubscribeUpdate {
+    SubscribeUpdate {
+        filters: Vec::new(),
+        created_at: None,
+        update_oneof: Some(UpdateOneof::Ping(SubscribeUpdatePing {})),
+    }
+}
+
+fn tick_ready(interval: &mut tokio::time::Interval) -> bool {
+    let waker = futures::task::noop_waker_ref();
+    let mut cx = std::task::Context::from_waker(waker);
+    interval.poll_tick(&mut cx).is_ready()
+}
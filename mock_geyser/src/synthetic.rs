@@ -0,0 +1,172 @@
+use solana_sdk::pubkey::Pubkey;
+use yellowstone_grpc_proto::prelude::{
+    CompiledInstruction, Message, MessageHeader, SubscribeUpdate, SubscribeUpdateAccount,
+    SubscribeUpdateAccountInfo, SubscribeUpdateBlockMeta, SubscribeUpdateSlot,
+    SubscribeUpdateTransaction, SubscribeUpdateTransactionInfo, Transaction, TransactionStatusMeta,
+    UnixTimestamp, subscribe_update::UpdateOneof,
+};
+
+pub const TOKEN_PROGRAM_ID: Pubkey =
+    solana_sdk::pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+pub const TOKEN_2022_PROGRAM_ID: Pubkey =
+    solana_sdk::pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+pub const PUMPSWAP_PROGRAM_ID: Pubkey =
+    solana_sdk::pubkey!("pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA");
+
+/// Deterministic 64-byte signature so fixtures are stable across runs.
+pub fn signature(slot: u64, index: u64) -> Vec<u8> {
+    let mut signature = vec![0u8; 64];
+    signature[..8].copy_from_slice(&slot.to_le_bytes());
+    signature[8..16].copy_from_slice(&index.to_le_bytes());
+    signature
+}
+
+/// A successful legacy transaction whose first key is the fee payer.
+pub fn transaction(
+    slot: u64,
+    index: u64,
+    account_keys: &[Pubkey],
+    instructions: Vec<CompiledInstruction>,
+) -> SubscribeUpdate {
+    let signature = signature(slot, index);
+    let message = Message {
+        header: Some(MessageHeader {
+            num_required_signatures: 1,
+            num_readonly_signed_accounts: 0,
+            num_readonly_unsigned_accounts: 0,
+        }),
+        account_keys: account_keys
+            .iter()
+            .map(|key| key.to_bytes().to_vec())
+            .collect(),
+        recent_blockhash: vec![0u8; 32],
+        instructions,
+        versioned: false,
+        address_table_lookups: Vec::new(),
+    };
+
+    SubscribeUpdate {
+        filters: Vec::new(),
+        created_at: None,
+        update_oneof: Some(UpdateOneof::Transaction(SubscribeUpdateTransaction {
+            transaction: Some(SubscribeUpdateTransactionInfo {
+                signature: signature.clone(),
+                is_vote: false,
+                transaction: Some(Transaction {
+                    signatures: vec![signature],
+                    message: Some(message),
+                }),
+                meta: Some(TransactionStatusMeta {
+                    fee: 5000,
+                    ..Default::default()
+                }),
+                index,
+            }),
+            slot,
+        })),
+    }
+}
+
+/// A transaction invoking `program_id` once with `data` over `accounts`.
+pub fn program_call(
+    slot: u64,
+    index: u64,
+    program_id: Pubkey,
+    accounts: &[Pubkey],
+    data: Vec<u8>,
+) -> SubscribeUpdate {
+    let payer = Pubkey::new_from_array([1u8; 32]);
+    let mut keys = vec![payer];
+    keys.extend_from_slice(accounts);
+    keys.push(program_id);
+
+    let instruction = CompiledInstruction {
+        program_id_index: (keys.len() - 1) as u32,
+        accounts: (1..=accounts.len() as u8).collect(),
+        data,
+    };
+
+    transaction(slot, index, &keys, vec![instruction])
+}
+
+/// A write of `data` to `pubkey`, owned by `owner`.
+pub fn account(slot: u64, pubkey: Pubkey, owner: Pubkey, data: Vec<u8>) -> SubscribeUpdate {
+    SubscribeUpdate {
+        filters: Vec::new(),
+        created_at: None,
+        update_oneof: Some(UpdateOneof::Account(SubscribeUpdateAccount {
+            account: Some(SubscribeUpdateAccountInfo {
+                pubkey: pubkey.to_bytes().to_vec(),
+                lamports: 1_461_600,
+                owner: owner.to_bytes().to_vec(),
+                data,
+                write_version: 1,
+                ..Default::default()
+            }),
+            slot,
+            is_startup: false,
+        })),
+    }
+}
+
+/// The block meta of `slot`, closing it at `block_time` (Unix seconds).
+pub fn block_meta(slot: u64, block_time: i64) -> SubscribeUpdate {
+    SubscribeUpdate {
+        filters: Vec::new(),
+        created_at: None,
+        update_oneof: Some(UpdateOneof::BlockMeta(SubscribeUpdateBlockMeta {
+            slot,
+            block_time: Some(UnixTimestamp {
+                timestamp: block_time,
+            }),
+            ..Default::default()
+        })),
+    }
+}
+
+pub fn slot(slot: u64) -> SubscribeUpdate {
+    SubscribeUpdate {
+        filters: Vec::new(),
+        created_at: None,
+        update_oneof: Some(UpdateOneof::Slot(SubscribeUpdateSlot {
+            slot,
+            parent: slot.checked_sub(1),
+            ..Default::default()
+        })),
+    }
+}
+
+/// One SPL token transfer and one PumpSwap buy per slot, for manual end-to-end runs.
+pub fn default_stream(start_slot: u64, slots: u64) -> Vec<SubscribeUpdate> {
+    let source = Pubkey::new_from_array([2u8; 32]);
+    let destination = Pubkey::new_from_array([3u8; 32]);
+    let owner = Pubkey::new_from_array([4u8; 32]);
+    let pool = Pubkey::new_from_array([5u8; 32]);
+
+    let mut transfer = vec![3u8];
+    transfer.extend_from_slice(&1_000u64.to_le_bytes());
+
+    let mut buy = vec![102, 6, 61, 18, 1, 218, 235, 234];
+    buy.extend_from_slice(&1_000u64.to_le_bytes());
+    buy.extend_from_slice(&1_000_000u64.to_le_bytes());
+
+    let mut updates = Vec::new();
+    for slot_number in start_slot..start_slot + slots {
+        updates.push(program_call(
+            slot_number,
+            0,
+            TOKEN_PROGRAM_ID,
+            &[source, destination, owner],
+            transfer.clone(),
+        ));
+        updates.push(program_call(
+            slot_number,
+            1,
+            PUMPSWAP_PROGRAM_ID,
+            &[pool, owner],
+            buy.clone(),
+        ));
+        updates.push(slot(slot_number));
+    }
+    updates
+}
@@ -0,0 +1,145 @@
+//! PumpSwap admin instructions that change the global config.
+
+use serde::{Deserialize, Serialize};
+
+use crate::pumpswap::{Reader, TransactionContext};
+
+const CREATE_CONFIG_DISCRIMINATOR: [u8; 8] = [201, 207, 243, 114, 75, 111, 47, 189];
+const UPDATE_FEE_CONFIG_DISCRIMINATOR: [u8; 8] = [104, 184, 103, 242, 88, 151, 107, 20];
+const UPDATE_ADMIN_DISCRIMINATOR: [u8; 8] = [161, 176, 40, 213, 60, 184, 179, 228];
+const DISABLE_DISCRIMINATOR: [u8; 8] = [185, 173, 187, 90, 216, 15, 238, 233];
+const PROTOCOL_FEE_RECIPIENTS: usize = 8;
+/// `sha256("account:GlobalConfig")[..8]`.
+const GLOBAL_CONFIG_ACCOUNT_DISCRIMINATOR: [u8; 8] = [149, 8, 156, 202, 160, 252, 176, 217];
+
+/// Fees charged on every swap from then on.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FeeConfig {
+    pub lp_fee_basis_points: u64,
+    pub protocol_fee_basis_points: u64,
+    pub protocol_fee_recipients: Vec<String>,
+    /// Absent on instructions sent before coin-creator fees were introduced.
+    pub coin_creator_fee_basis_points: Option<u64>,
+}
+
+impl FeeConfig {
+    fn decode(reader: &mut Reader) -> Option<Self> {
+        let lp_fee_basis_points = reader.u64()?;
+        let protocol_fee_basis_points = reader.u64()?;
+        let protocol_fee_recipients = (0..PROTOCOL_FEE_RECIPIENTS)
+            .map(|_| reader.pubkey())
+            .collect::<Option<_>>()?;
+        Some(Self {
+            lp_fee_basis_points,
+            protocol_fee_basis_points,
+            protocol_fee_recipients,
+            coin_creator_fee_basis_points: reader.u64(),
+        })
+    }
+
+    /// Decodes the fees held in the global config account, `None` for any other
+    /// account.
+    pub(crate) fn decode_account(data: &[u8]) -> Option<Self> {
+        let payload = data.strip_prefix(GLOBAL_CONFIG_ACCOUNT_DISCRIMINATOR.as_slice())?;
+        let mut reader = Reader(payload);
+        let _admin = reader.pubkey()?;
+        let lp_fee_basis_points = reader.u64()?;
+        let protocol_fee_basis_points = reader.u64()?;
+        let _disable_flags = reader.u8()?;
+        let protocol_fee_recipients = (0..PROTOCOL_FEE_RECIPIENTS)
+            .map(|_| reader.pubkey())
+            .collect::<Option<_>>()?;
+        Some(Self {
+            lp_fee_basis_points,
+            protocol_fee_basis_points,
+            protocol_fee_recipients,
+            coin_creator_fee_basis_points: reader.u64(),
+        })
+    }
+}
+
+/// What an admin instruction changed, tagged with the instruction name.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "instruction", rename_all = "snake_case")]
+pub enum ConfigChange {
+    CreateConfig(FeeConfig),
+    UpdateFeeConfig(FeeConfig),
+    UpdateAdmin {
+        new_admin: String,
+    },
+    /// Which instructions the program refuses from then on.
+    Disable {
+        disable_create_pool: bool,
+        disable_deposit: bool,
+        disable_withdraw: bool,
+        disable_buy: bool,
+        disable_sell: bool,
+    },
+}
+
+impl ConfigChange {
+    /// Decodes an admin instruction, `None` for any other instruction.
+    fn decode(data: &[u8], new_admin: Option<String>) -> Option<Self> {
+        let (discriminator, payload) = data.split_at_checked(8)?;
+        let mut reader = Reader(payload);
+        if discriminator == CREATE_CONFIG_DISCRIMINATOR {
+            FeeConfig::decode(&mut reader).map(ConfigChange::CreateConfig)
+        } else if discriminator == UPDATE_FEE_CONFIG_DISCRIMINATOR {
+            FeeConfig::decode(&mut reader).map(ConfigChange::UpdateFeeConfig)
+        } else if discriminator == UPDATE_ADMIN_DISCRIMINATOR {
+            Some(ConfigChange::UpdateAdmin {
+                new_admin: new_admin?,
+            })
+        } else if discriminator == DISABLE_DISCRIMINATOR {
+            Some(ConfigChange::Disable {
+                disable_create_pool: reader.bool()?,
+                disable_deposit: reader.bool()?,
+                disable_withdraw: reader.bool()?,
+                disable_buy: reader.bool()?,
+                disable_sell: reader.bool()?,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PumpSwapConfigUpdate {
+    #[serde(flatten)]
+    pub transaction: TransactionContext,
+    /// Top-level instruction the update belongs to.
+    pub instruction_index: u32,
+    /// Position among that instruction's inner instructions when PumpSwap was
+    /// reached through CPI.
+    pub inner_index: Option<u32>,
+    pub admin: String,
+    pub global_config: String,
+    #[serde(flatten)]
+    pub change: ConfigChange,
+}
+
+impl PumpSwapConfigUpdate {
+    /// Decodes an admin instruction. Every one takes the admin and the global
+    /// config first; `update_admin` names the new admin third.
+    pub(crate) fn decode(
+        data: &[u8],
+        accounts: &[u8],
+        account_keys: &[String],
+        transaction: &TransactionContext,
+        instruction_index: u32,
+        inner_index: Option<u32>,
+    ) -> Option<Self> {
+        let key = |position: usize| account_keys.get(*accounts.get(position)? as usize).cloned();
+        let change = ConfigChange::decode(data, key(2))?;
+
+        Some(Self {
+            transaction: transaction.clone(),
+            instruction_index,
+            inner_index,
+            admin: key(0)?,
+            global_config: key(1)?,
+            change,
+        })
+    }
+}
@@ -0,0 +1,397 @@
+//! OHLCV candles per pool, built from trades.
+//!
+//! Trades are bucketed by slot time: the block time when the slot's block meta has
+//! been seen, otherwise the cluster-clock timestamp in the swap's event. Kafka can
+//! deliver trades late and out of order, so a candle stays open until the newest
+//! slot time seen is `CANDLE_LATENESS_SECS` past its end, and open/close are the
+//! first and last trades in chain order rather than arrival order. Trades for a
+//! candle that has already been emitted go into a `correction` candle for the same
+//! period, and candles still open at shutdown are flushed as `partial`.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Serialize, Serializer};
+
+use crate::amount::FixedDecimal;
+use crate::pumpswap::PumpSwapTrade;
+
+const DEFAULT_INTERVALS: &str = "1s,1m,5m,1h,1d";
+const DEFAULT_LATENESS_SECS: i64 = 30;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CandleInterval {
+    OneSecond,
+    OneMinute,
+    FiveMinutes,
+    OneHour,
+    OneDay,
+}
+
+impl CandleInterval {
+    pub fn seconds(&self) -> i64 {
+        match self {
+            CandleInterval::OneSecond => 1,
+            CandleInterval::OneMinute => 60,
+            CandleInterval::FiveMinutes => 300,
+            CandleInterval::OneHour => 3_600,
+            CandleInterval::OneDay => 86_400,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CandleInterval::OneSecond => "1s",
+            CandleInterval::OneMinute => "1m",
+            CandleInterval::FiveMinutes => "5m",
+            CandleInterval::OneHour => "1h",
+            CandleInterval::OneDay => "1d",
+        }
+    }
+
+    /// Start of the candle containing `time`, in Unix seconds.
+    fn open_time(&self, time: i64) -> i64 {
+        time - time.rem_euclid(self.seconds())
+    }
+}
+
+impl FromStr for CandleInterval {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "1s" => Ok(CandleInterval::OneSecond),
+            "1m" => Ok(CandleInterval::OneMinute),
+            "5m" => Ok(CandleInterval::FiveMinutes),
+            "1h" => Ok(CandleInterval::OneHour),
+            "1d" => Ok(CandleInterval::OneDay),
+            other => Err(format!("unknown candle interval {other:?}")),
+        }
+    }
+}
+
+impl fmt::Display for CandleInterval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for CandleInterval {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+/// How far a candle can be relied on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CandleStatus {
+    /// Slot time passed its end plus the lateness.
+    Final,
+    /// Flushed at shutdown or the end of a replay before it was finished; trades
+    /// consumed after a restart go into another candle for the same period.
+    Partial,
+    /// Trades that arrived after the candle for the period was emitted, to be merged
+    /// into it.
+    Correction,
+}
+
+/// Where a trade sits in the chain: slot, transaction, instruction, inner
+/// instruction.
+type TradeOrder = (u64, u64, u32, Option<u32>);
+
+/// One pool's trades over one interval. Prices are execution prices, quote per
+/// base; volumes are in whole tokens, the quote side including fees. The USD
+/// figures cover only the trades that could be priced in USD, and are `None` when
+/// none could.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Candle {
+    pub pool: String,
+    pub base_mint: String,
+    pub quote_mint: String,
+    pub interval: CandleInterval,
+    /// Unix seconds, inclusive.
+    pub open_time: i64,
+    /// Unix seconds, exclusive.
+    pub close_time: i64,
+    pub status: CandleStatus,
+    pub open: FixedDecimal,
+    pub high: FixedDecimal,
+    pub low: FixedDecimal,
+    pub close: FixedDecimal,
+    pub base_volume: FixedDecimal,
+    pub quote_volume: FixedDecimal,
+    pub open_usd: Option<FixedDecimal>,
+    pub high_usd: Option<FixedDecimal>,
+    pub low_usd: Option<FixedDecimal>,
+    pub close_usd: Option<FixedDecimal>,
+    pub volume_usd: Option<FixedDecimal>,
+    /// As of the last trade priced in USD.
+    pub market_cap_usd: Option<FixedDecimal>,
+    pub trades: u64,
+    pub first_slot: u64,
+    pub last_slot: u64,
+    #[serde(skip)]
+    open_order: TradeOrder,
+    #[serde(skip)]
+    close_order: TradeOrder,
+    #[serde(skip)]
+    open_usd_order: Option<TradeOrder>,
+    #[serde(skip)]
+    close_usd_order: Option<TradeOrder>,
+}
+
+impl Candle {
+    fn new(
+        trade: &PumpSwapTrade,
+        interval: CandleInterval,
+        open_time: i64,
+        status: CandleStatus,
+        order: TradeOrder,
+        fill: &Fill,
+    ) -> Self {
+        Self {
+            pool: trade.pool.clone(),
+            base_mint: trade.base_mint.clone(),
+            quote_mint: trade.quote_mint.clone(),
+            interval,
+            open_time,
+            close_time: open_time + interval.seconds(),
+            status,
+            open: fill.price,
+            high: fill.price,
+            low: fill.price,
+            close: fill.price,
+            base_volume: fill.base,
+            quote_volume: fill.quote,
+            open_usd: fill.price_usd,
+            high_usd: fill.price_usd,
+            low_usd: fill.price_usd,
+            close_usd: fill.price_usd,
+            volume_usd: fill.volume_usd,
+            market_cap_usd: fill.market_cap_usd,
+            trades: 1,
+            first_slot: order.0,
+            last_slot: order.0,
+            open_order: order,
+            close_order: order,
+            open_usd_order: fill.price_usd.map(|_| order),
+            close_usd_order: fill.price_usd.map(|_| order),
+        }
+    }
+
+    fn add(&mut self, order: TradeOrder, fill: &Fill) {
+        if order < self.open_order {
+            self.open = fill.price;
+            self.open_order = order;
+        }
+        if order > self.close_order {
+            self.close = fill.price;
+            self.close_order = order;
+        }
+        if fill.price.value > self.high.value {
+            self.high = fill.price;
+        }
+        if fill.price.value < self.low.value {
+            self.low = fill.price;
+        }
+        self.base_volume.value += fill.base.value;
+        self.quote_volume.value += fill.quote.value;
+
+        if let Some(price_usd) = fill.price_usd {
+            if self.open_usd_order.is_none_or(|first| order < first) {
+                self.open_usd = Some(price_usd);
+                self.open_usd_order = Some(order);
+            }
+            if self.close_usd_order.is_none_or(|last| order > last) {
+                self.close_usd = Some(price_usd);
+                self.market_cap_usd = fill.market_cap_usd;
+                self.close_usd_order = Some(order);
+            }
+            if self
+                .high_usd
+                .is_none_or(|high| price_usd.value > high.value)
+            {
+                self.high_usd = Some(price_usd);
+            }
+            if self.low_usd.is_none_or(|low| price_usd.value < low.value) {
+                self.low_usd = Some(price_usd);
+            }
+        }
+        if let Some(volume_usd) = fill.volume_usd {
+            self.volume_usd = Some(match self.volume_usd {
+                Some(total) => FixedDecimal::new(total.value + volume_usd.value, total.decimals),
+                None => volume_usd,
+            });
+        }
+        self.trades += 1;
+        self.first_slot = self.first_slot.min(order.0);
+        self.last_slot = self.last_slot.max(order.0);
+    }
+}
+
+/// What a trade contributes to a candle.
+struct Fill {
+    price: FixedDecimal,
+    base: FixedDecimal,
+    quote: FixedDecimal,
+    price_usd: Option<FixedDecimal>,
+    volume_usd: Option<FixedDecimal>,
+    market_cap_usd: Option<FixedDecimal>,
+}
+
+impl Fill {
+    /// `None` for failed trades and trades whose amounts or reserves are unknown.
+    fn from_trade(trade: &PumpSwapTrade) -> Option<Self> {
+        if !trade.transaction.success {
+            return None;
+        }
+        // The pre-trade reserves carry the mints' decimals.
+        let base_decimals = trade.base_reserve?.decimals;
+        let quote_decimals = trade.quote_reserve?.decimals;
+        Some(Self {
+            price: trade.execution_price?,
+            base: FixedDecimal::new(trade.base_amount?, base_decimals),
+            quote: FixedDecimal::new(trade.quote_amount?, quote_decimals),
+            price_usd: trade.price_usd,
+            volume_usd: trade.volume_usd,
+            market_cap_usd: trade.market_cap_usd,
+        })
+    }
+}
+
+/// Open candles for every pool and interval, closed as slot time moves on.
+#[derive(Debug)]
+pub struct CandleAggregator {
+    intervals: Vec<CandleInterval>,
+    lateness: i64,
+    /// Keyed by close time first, so finished candles split off the front.
+    open: BTreeMap<(i64, CandleInterval, String), Candle>,
+    /// Late trades for candles already emitted, sent with the next finished ones.
+    corrections: BTreeMap<(i64, CandleInterval, String), Candle>,
+    /// Newest slot time seen.
+    latest: Option<i64>,
+}
+
+impl CandleAggregator {
+    pub fn new(intervals: Vec<CandleInterval>, lateness_secs: i64) -> Self {
+        Self {
+            intervals,
+            lateness: lateness_secs.max(0),
+            open: BTreeMap::new(),
+            corrections: BTreeMap::new(),
+            latest: None,
+        }
+    }
+
+    /// Intervals from `CANDLE_INTERVALS` (comma-separated, default all of `1s`,
+    /// `1m`, `5m`, `1h`, `1d`; `none` turns candles off) and the lateness from
+    /// `CANDLE_LATENESS_SECS`.
+    pub fn from_env() -> Option<Self> {
+        let intervals =
+            std::env::var("CANDLE_INTERVALS").unwrap_or_else(|_| DEFAULT_INTERVALS.to_string());
+        let mut parsed: Vec<CandleInterval> = intervals
+            .split(',')
+            .map(str::trim)
+            .filter(|value| !value.is_empty() && *value != "none")
+            .filter_map(|value| match value.parse() {
+                Ok(interval) => Some(interval),
+                Err(err) => {
+                    eprintln!("Ignoring {}", err);
+                    None
+                }
+            })
+            .collect();
+        parsed.sort();
+        parsed.dedup();
+        if parsed.is_empty() {
+            return None;
+        }
+
+        let lateness = std::env::var("CANDLE_LATENESS_SECS")
+            .ok()
+            .and_then(|value| value.parse::<i64>().ok())
+            .unwrap_or(DEFAULT_LATENESS_SECS);
+        Some(Self::new(parsed, lateness))
+    }
+
+    pub fn intervals(&self) -> &[CandleInterval] {
+        &self.intervals
+    }
+
+    /// Adds a trade to its candles and returns the candles that are now finished.
+    pub fn observe(&mut self, trade: &PumpSwapTrade) -> Vec<Candle> {
+        let Some(time) = trade
+            .transaction
+            .block_time
+            .or(trade.event.as_ref().map(|event| event.timestamp))
+        else {
+            return Vec::new();
+        };
+
+        if let Some(fill) = Fill::from_trade(trade) {
+            let order = (
+                trade.transaction.slot,
+                trade.transaction.transaction_index,
+                trade.instruction_index,
+                trade.inner_index,
+            );
+            for &interval in &self.intervals {
+                let open_time = interval.open_time(time);
+                let close_time = open_time + interval.seconds();
+                let (candles, status) = if self.is_closed(close_time) {
+                    (&mut self.corrections, CandleStatus::Correction)
+                } else {
+                    (&mut self.open, CandleStatus::Final)
+                };
+
+                candles
+                    .entry((close_time, interval, trade.pool.clone()))
+                    .and_modify(|candle| candle.add(order, &fill))
+                    .or_insert_with(|| {
+                        Candle::new(trade, interval, open_time, status, order, &fill)
+                    });
+            }
+        }
+
+        self.advance(time)
+    }
+
+    /// Moves slot time forward, e.g. from a block meta, and returns the candles
+    /// that are now finished, followed by any corrections.
+    pub fn advance(&mut self, time: i64) -> Vec<Candle> {
+        if self.latest.is_some_and(|latest| latest >= time) {
+            return Vec::new();
+        }
+        self.latest = Some(time);
+
+        // Everything ending at or before the cutoff is finished.
+        let cutoff = time - self.lateness;
+        let still_open =
+            self.open
+                .split_off(&(cutoff + 1, CandleInterval::OneSecond, String::new()));
+        std::mem::replace(&mut self.open, still_open)
+            .into_values()
+            .chain(std::mem::take(&mut self.corrections).into_values())
+            .collect()
+    }
+
+    /// Every open candle, finished or not, as `partial`, followed by any
+    /// corrections, e.g. at shutdown.
+    pub fn flush(&mut self) -> Vec<Candle> {
+        std::mem::take(&mut self.open)
+            .into_values()
+            .map(|mut candle| {
+                candle.status = CandleStatus::Partial;
+                candle
+            })
+            .chain(std::mem::take(&mut self.corrections).into_values())
+            .collect()
+    }
+
+    fn is_closed(&self, close_time: i64) -> bool {
+        self.latest
+            .is_some_and(|latest| close_time <= latest - self.lateness)
+    }
+}
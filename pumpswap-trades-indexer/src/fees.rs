@@ -0,0 +1,397 @@
+//! PumpSwap swap fees: what each trade paid to liquidity providers, the protocol
+//! and the coin creator, and daily totals per pool and per coin creator.
+//!
+//! A swap's event reports its fees exactly. Without one, the protocol and
+//! coin-creator fees are the quote transferred to their token accounts, and the LP
+//! fee, which never leaves the pool, is worked back from the quote into or out of
+//! the pool vault at the global config's LP rate. With `FEE_CONFIG_PATH` set the
+//! global configs seen are saved as JSON and reloaded on start, since a config is
+//! only sent again when it changes.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::admin::FeeConfig;
+use crate::amount::FixedDecimal;
+use crate::pumpswap::{PumpSwapTrade, SwapEvent, TradeSide};
+
+const BASIS_POINTS: u64 = 10_000;
+const SECONDS_PER_DAY: i64 = 86_400;
+const DEFAULT_LATENESS_SECS: i64 = 30;
+/// Minimum time between saves while updates keep arriving.
+const SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Fee rates by global config, from its account writes and admin instructions.
+#[derive(Debug, Default)]
+pub struct FeeConfigs {
+    path: Option<PathBuf>,
+    /// The newest config seen for each global config account, with its slot.
+    configs: HashMap<String, (u64, FeeConfig)>,
+    dirty: bool,
+    last_save: Option<Instant>,
+}
+
+/// A config as saved at `FEE_CONFIG_PATH`.
+#[derive(Serialize, Deserialize)]
+struct SavedFeeConfig {
+    global_config: String,
+    slot: u64,
+    #[serde(flatten)]
+    config: FeeConfig,
+}
+
+impl FeeConfigs {
+    /// Loads `FEE_CONFIG_PATH` if set; otherwise configs live in memory.
+    pub fn from_env() -> Self {
+        let path = std::env::var("FEE_CONFIG_PATH")
+            .ok()
+            .filter(|value| !value.trim().is_empty())
+            .map(PathBuf::from);
+        Self::load(path)
+    }
+
+    /// Reads saved configs. A missing file is empty; an unreadable one is reported
+    /// and ignored, since configs are learned again from the stream.
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let mut configs = Self {
+            path,
+            ..Self::default()
+        };
+        let Some(path) = configs.path.as_ref() else {
+            return configs;
+        };
+
+        match std::fs::read_to_string(path) {
+            Ok(contents) => match serde_json::from_str::<Vec<SavedFeeConfig>>(&contents) {
+                Ok(saved) => {
+                    configs.configs = saved
+                        .into_iter()
+                        .map(|saved| (saved.global_config, (saved.slot, saved.config)))
+                        .collect();
+                }
+                Err(err) => eprintln!("Ignoring fee configs {}: {}", path.display(), err),
+            },
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => eprintln!("Ignoring fee configs {}: {}", path.display(), err),
+        }
+        configs
+    }
+
+    pub fn len(&self) -> usize {
+        self.configs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.configs.is_empty()
+    }
+
+    pub fn get(&self, global_config: &str) -> Option<&FeeConfig> {
+        self.configs.get(global_config).map(|(_, config)| config)
+    }
+
+    /// Keeps `config` unless a newer one has already been seen.
+    pub fn observe(&mut self, global_config: String, slot: u64, config: FeeConfig) {
+        let newer = self
+            .configs
+            .get(&global_config)
+            .is_none_or(|(seen, _)| slot >= *seen);
+        if newer && self.configs.get(&global_config) != Some(&(slot, config.clone())) {
+            self.configs.insert(global_config, (slot, config));
+            self.dirty = true;
+        }
+    }
+
+    /// Saves if something changed and the last save was long enough ago.
+    pub fn save_if_due(&mut self) -> std::io::Result<()> {
+        if self
+            .last_save
+            .is_some_and(|last| last.elapsed() < SAVE_INTERVAL)
+        {
+            return Ok(());
+        }
+        self.save()
+    }
+
+    /// Writes the configs if they changed since the last save.
+    pub fn save(&mut self) -> std::io::Result<()> {
+        let Some(path) = self.path.as_ref() else {
+            return Ok(());
+        };
+        if !self.dirty {
+            return Ok(());
+        }
+
+        let mut saved: Vec<SavedFeeConfig> = self
+            .configs
+            .iter()
+            .map(|(global_config, (slot, config))| SavedFeeConfig {
+                global_config: global_config.clone(),
+                slot: *slot,
+                config: config.clone(),
+            })
+            .collect();
+        saved.sort_by(|a, b| a.global_config.cmp(&b.global_config));
+        let contents = serde_json::to_string(&saved)?;
+
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, contents)?;
+        std::fs::rename(&tmp_path, path)?;
+        self.dirty = false;
+        self.last_save = Some(Instant::now());
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeeSource {
+    /// Read from the swap's event.
+    Event,
+    /// Taken from the fee transfers, with the LP fee estimated from the global
+    /// config's rate.
+    Transfers,
+}
+
+/// The fees one trade paid, as raw quote amounts.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TradeFees {
+    pub source: FeeSource,
+    pub lp_fee_basis_points: Option<u64>,
+    pub protocol_fee_basis_points: Option<u64>,
+    pub coin_creator_fee_basis_points: Option<u64>,
+    /// Left in the pool for liquidity providers. Estimated fees can be a unit off
+    /// the program's rounding.
+    pub lp_fee: Option<u64>,
+    pub protocol_fee: Option<u64>,
+    pub coin_creator_fee: Option<u64>,
+    /// From the event, otherwise from the pool state.
+    pub coin_creator: Option<String>,
+}
+
+impl TradeFees {
+    pub(crate) fn from_event(event: &SwapEvent, pool_coin_creator: Option<String>) -> Self {
+        Self {
+            source: FeeSource::Event,
+            lp_fee_basis_points: Some(event.lp_fee_basis_points),
+            protocol_fee_basis_points: Some(event.protocol_fee_basis_points),
+            coin_creator_fee_basis_points: event.coin_creator_fee_basis_points,
+            lp_fee: Some(event.lp_fee),
+            protocol_fee: Some(event.protocol_fee),
+            coin_creator_fee: event.coin_creator_fee,
+            coin_creator: event.coin_creator.clone().or(pool_coin_creator),
+        }
+    }
+
+    /// Fees of a swap without an event. `pool_quote_amount` is the quote into
+    /// (buy) or out of (sell) the pool vault, fees to the protocol and creator
+    /// included on a sell.
+    pub(crate) fn from_transfers(
+        side: TradeSide,
+        config: Option<&FeeConfig>,
+        pool_quote_amount: Option<u64>,
+        protocol_fee: Option<u64>,
+        coin_creator_fee: Option<u64>,
+        coin_creator: Option<String>,
+    ) -> Self {
+        let lp_fee_basis_points = config.map(|config| config.lp_fee_basis_points);
+        let lp_fee = match (lp_fee_basis_points, pool_quote_amount) {
+            (Some(bps), Some(amount)) => lp_fee(side, bps, amount),
+            _ => None,
+        };
+
+        Self {
+            source: FeeSource::Transfers,
+            lp_fee_basis_points,
+            protocol_fee_basis_points: config.map(|config| config.protocol_fee_basis_points),
+            coin_creator_fee_basis_points: config
+                .and_then(|config| config.coin_creator_fee_basis_points),
+            lp_fee,
+            protocol_fee,
+            coin_creator_fee,
+            coin_creator,
+        }
+    }
+}
+
+/// The LP fee behind a pool-vault movement. The program charges `ceil(quote * bps
+/// / 10_000)` on the curve's quote amount, which a buy pays into the vault on top
+/// and a sell leaves behind in it.
+fn lp_fee(side: TradeSide, bps: u64, pool_quote_amount: u64) -> Option<u64> {
+    let amount = u128::from(pool_quote_amount);
+    let bps = u128::from(bps);
+    let fee = match side {
+        TradeSide::Buy => {
+            amount - amount * u128::from(BASIS_POINTS) / (u128::from(BASIS_POINTS) + bps)
+        }
+        TradeSide::Sell => {
+            let rest = u128::from(BASIS_POINTS)
+                .checked_sub(bps)
+                .filter(|rest| *rest > 0)?;
+            (amount * bps).div_ceil(rest)
+        }
+    };
+    u64::try_from(fee).ok()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeeScope {
+    Pool,
+    CoinCreator,
+}
+
+/// One UTC day of fees for a pool or a coin creator, in whole quote tokens. A
+/// creator with pools in several quote mints gets a summary per mint.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FeeSummary {
+    pub scope: FeeScope,
+    /// The pool or the coin creator.
+    pub address: String,
+    pub quote_mint: String,
+    /// Unix seconds, inclusive.
+    pub day_start: i64,
+    /// Unix seconds, exclusive.
+    pub day_end: i64,
+    pub lp_fee: FixedDecimal,
+    pub protocol_fee: FixedDecimal,
+    pub coin_creator_fee: FixedDecimal,
+    pub trades: u64,
+    /// Trades whose fees came from transfers rather than the event.
+    pub estimated_trades: u64,
+}
+
+impl FeeSummary {
+    fn add(&mut self, fees: &TradeFees) {
+        self.lp_fee.value += u128::from(fees.lp_fee.unwrap_or(0));
+        self.protocol_fee.value += u128::from(fees.protocol_fee.unwrap_or(0));
+        self.coin_creator_fee.value += u128::from(fees.coin_creator_fee.unwrap_or(0));
+        self.trades += 1;
+        if fees.source == FeeSource::Transfers {
+            self.estimated_trades += 1;
+        }
+    }
+}
+
+/// Daily fee totals for every pool and coin creator, closed as slot time moves on
+/// the same way candles are.
+#[derive(Debug)]
+pub struct FeeAggregator {
+    lateness: i64,
+    /// Keyed by day end first, so finished days split off the front.
+    open: BTreeMap<(i64, FeeScope, String, String), FeeSummary>,
+    /// Newest slot time seen.
+    latest: Option<i64>,
+}
+
+impl FeeAggregator {
+    pub fn new(lateness_secs: i64) -> Self {
+        Self {
+            lateness: lateness_secs.max(0),
+            open: BTreeMap::new(),
+            latest: None,
+        }
+    }
+
+    /// On unless `FEE_SUMMARIES` is `false`, `0` or `none`, with the lateness from
+    /// `FEE_LATENESS_SECS`.
+    pub fn from_env() -> Option<Self> {
+        let enabled = std::env::var("FEE_SUMMARIES")
+            .map(|value| !matches!(value.trim(), "false" | "0" | "none"))
+            .unwrap_or(true);
+        if !enabled {
+            return None;
+        }
+
+        let lateness = std::env::var("FEE_LATENESS_SECS")
+            .ok()
+            .and_then(|value| value.parse::<i64>().ok())
+            .unwrap_or(DEFAULT_LATENESS_SECS);
+        Some(Self::new(lateness))
+    }
+
+    /// Adds a successful trade's fees to its pool's and creator's day, and returns
+    /// the days that are now finished.
+    pub fn observe(&mut self, trade: &PumpSwapTrade) -> Vec<FeeSummary> {
+        let Some(time) = trade
+            .transaction
+            .block_time
+            .or(trade.event.as_ref().map(|event| event.timestamp))
+        else {
+            return Vec::new();
+        };
+
+        if trade.transaction.success {
+            self.add(trade, time);
+        }
+        self.advance(time)
+    }
+
+    fn add(&mut self, trade: &PumpSwapTrade, time: i64) {
+        // The pre-trade quote reserve carries the quote mint's decimals.
+        let Some(decimals) = trade.quote_reserve.map(|reserve| reserve.decimals) else {
+            return;
+        };
+        let day_start = time - time.rem_euclid(SECONDS_PER_DAY);
+        let day_end = day_start + SECONDS_PER_DAY;
+        if self
+            .latest
+            .is_some_and(|latest| day_end <= latest - self.lateness)
+        {
+            eprintln!(
+                "Dropping late fees of trade {} for pool {} on the day starting {}",
+                trade.transaction.signature, trade.pool, day_start
+            );
+            return;
+        }
+
+        let pool = Some((FeeScope::Pool, trade.pool.clone()));
+        let creator = trade
+            .fees
+            .coin_creator
+            .clone()
+            .map(|creator| (FeeScope::CoinCreator, creator));
+        let zero = FixedDecimal::new(0u64, decimals);
+        for (scope, address) in [pool, creator].into_iter().flatten() {
+            self.open
+                .entry((day_end, scope, address.clone(), trade.quote_mint.clone()))
+                .or_insert_with(|| FeeSummary {
+                    scope,
+                    address,
+                    quote_mint: trade.quote_mint.clone(),
+                    day_start,
+                    day_end,
+                    lp_fee: zero,
+                    protocol_fee: zero,
+                    coin_creator_fee: zero,
+                    trades: 0,
+                    estimated_trades: 0,
+                })
+                .add(&trade.fees);
+        }
+    }
+
+    /// Moves slot time forward, e.g. from a block meta, and returns the days that
+    /// are now finished.
+    pub fn advance(&mut self, time: i64) -> Vec<FeeSummary> {
+        if self.latest.is_some_and(|latest| latest >= time) {
+            return Vec::new();
+        }
+        self.latest = Some(time);
+
+        let cutoff = time - self.lateness;
+        let still_open =
+            self.open
+                .split_off(&(cutoff + 1, FeeScope::Pool, String::new(), String::new()));
+        std::mem::replace(&mut self.open, still_open)
+            .into_values()
+            .collect()
+    }
+
+    /// Every open day, finished or not, e.g. at shutdown.
+    pub fn flush(&mut self) -> Vec<FeeSummary> {
+        std::mem::take(&mut self.open).into_values().collect()
+    }
+}
@@ -0,0 +1,9 @@
+pub mod admin;
+pub mod candle;
+pub mod fees;
+pub mod liquidity;
+pub mod pool;
+pub mod pumpswap;
+pub mod usd;
+
+pub use stream_common::{amount, call_tree};
@@ -0,0 +1,266 @@
+//! PumpSwap liquidity instructions: `create_pool`, `deposit` and `withdraw`.
+
+use serde::Serialize;
+
+use crate::amount::FixedDecimal;
+use crate::pumpswap::{Reader, TransactionContext, EVENT_IX_TAG};
+
+const CREATE_POOL_DISCRIMINATOR: [u8; 8] = [233, 146, 209, 142, 207, 104, 64, 188];
+const DEPOSIT_DISCRIMINATOR: [u8; 8] = [242, 35, 198, 137, 82, 225, 242, 182];
+const WITHDRAW_DISCRIMINATOR: [u8; 8] = [183, 18, 70, 156, 148, 109, 161, 34];
+const CREATE_POOL_EVENT_DISCRIMINATOR: [u8; 8] = [177, 49, 12, 210, 160, 118, 167, 116];
+const DEPOSIT_EVENT_DISCRIMINATOR: [u8; 8] = [120, 248, 61, 83, 31, 142, 107, 144];
+const WITHDRAW_EVENT_DISCRIMINATOR: [u8; 8] = [22, 9, 133, 26, 160, 44, 71, 192];
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LiquidityAction {
+    CreatePool,
+    Deposit,
+    Withdraw,
+}
+
+impl LiquidityAction {
+    /// Whether liquidity goes into the pool rather than out of it.
+    pub fn adds(&self) -> bool {
+        *self != LiquidityAction::Withdraw
+    }
+}
+
+/// Instruction arguments, raw amounts as signed by the user.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum LiquidityArgs {
+    CreatePool {
+        index: u16,
+        base_amount_in: u64,
+        quote_amount_in: u64,
+        /// Absent on pools created before coin-creator fees were introduced.
+        coin_creator: Option<String>,
+    },
+    Deposit {
+        lp_token_amount_out: u64,
+        max_base_amount_in: u64,
+        max_quote_amount_in: u64,
+    },
+    Withdraw {
+        lp_token_amount_in: u64,
+        min_base_amount_out: u64,
+        min_quote_amount_out: u64,
+    },
+}
+
+impl LiquidityArgs {
+    /// Decodes a liquidity instruction, `None` for any other instruction.
+    pub(crate) fn decode(data: &[u8]) -> Option<Self> {
+        let (discriminator, payload) = data.split_at_checked(8)?;
+        let mut reader = Reader(payload);
+        if discriminator == CREATE_POOL_DISCRIMINATOR {
+            Some(LiquidityArgs::CreatePool {
+                index: reader.u16()?,
+                base_amount_in: reader.u64()?,
+                quote_amount_in: reader.u64()?,
+                coin_creator: reader.pubkey(),
+            })
+        } else if discriminator == DEPOSIT_DISCRIMINATOR {
+            Some(LiquidityArgs::Deposit {
+                lp_token_amount_out: reader.u64()?,
+                max_base_amount_in: reader.u64()?,
+                max_quote_amount_in: reader.u64()?,
+            })
+        } else if discriminator == WITHDRAW_DISCRIMINATOR {
+            Some(LiquidityArgs::Withdraw {
+                lp_token_amount_in: reader.u64()?,
+                min_base_amount_out: reader.u64()?,
+                min_quote_amount_out: reader.u64()?,
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn action(&self) -> LiquidityAction {
+        match self {
+            LiquidityArgs::CreatePool { .. } => LiquidityAction::CreatePool,
+            LiquidityArgs::Deposit { .. } => LiquidityAction::Deposit,
+            LiquidityArgs::Withdraw { .. } => LiquidityAction::Withdraw,
+        }
+    }
+}
+
+/// The named accounts of a liquidity instruction, other than the pool itself. All
+/// three share the layout; for `create_pool` the user is the pool creator.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct LiquidityAccounts {
+    pub global_config: String,
+    pub user: String,
+    pub base_mint: String,
+    pub quote_mint: String,
+    pub lp_mint: String,
+    pub user_base_token_account: String,
+    pub user_quote_token_account: String,
+    /// The user's LP token account, i.e. their position.
+    pub user_pool_token_account: String,
+    pub pool_base_token_account: String,
+    pub pool_quote_token_account: String,
+}
+
+impl LiquidityAccounts {
+    pub(crate) fn decode(accounts: &[u8], account_keys: &[String]) -> Option<Self> {
+        let key = |position: usize| account_keys.get(*accounts.get(position)? as usize).cloned();
+        // The rest are programs and the event authority.
+        Some(Self {
+            global_config: key(1)?,
+            user: key(2)?,
+            base_mint: key(3)?,
+            quote_mint: key(4)?,
+            lp_mint: key(5)?,
+            user_base_token_account: key(6)?,
+            user_quote_token_account: key(7)?,
+            user_pool_token_account: key(8)?,
+            pool_base_token_account: key(9)?,
+            pool_quote_token_account: key(10)?,
+        })
+    }
+}
+
+/// A `CreatePoolEvent`, `DepositEvent` or `WithdrawEvent`, reduced to what the
+/// three have in common. All amounts are raw.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct LiquidityEvent {
+    /// Unix seconds from the cluster clock.
+    pub timestamp: i64,
+    /// LP tokens minted to (create, deposit) or burned from (withdraw) the user.
+    pub lp_token_amount: u64,
+    /// Base put in (create, deposit) or taken out (withdraw).
+    pub base_amount: u64,
+    pub quote_amount: u64,
+    /// Pool vault balances before the instruction; zero for a new pool.
+    pub pool_base_token_reserves: u64,
+    pub pool_quote_token_reserves: u64,
+    /// LP supply after the instruction, including the locked minimum liquidity.
+    pub lp_mint_supply: u64,
+    pub pool: String,
+    pub user: String,
+}
+
+impl LiquidityEvent {
+    /// Decodes the data of an event CPI, returning which instruction emitted it.
+    pub(crate) fn decode(data: &[u8]) -> Option<(LiquidityAction, Self)> {
+        let data = data.strip_prefix(EVENT_IX_TAG.as_slice())?;
+        let (discriminator, payload) = data.split_at_checked(8)?;
+        let mut reader = Reader(payload);
+
+        if discriminator == CREATE_POOL_EVENT_DISCRIMINATOR {
+            let timestamp = reader.i64()?;
+            let _index = reader.u16()?;
+            let user = reader.pubkey()?;
+            // Mints and their decimals, already known from the accounts.
+            reader.pubkey()?;
+            reader.pubkey()?;
+            reader.u8()?;
+            reader.u8()?;
+            let base_amount = reader.u64()?;
+            let quote_amount = reader.u64()?;
+            let _pool_base_amount = reader.u64()?;
+            let _pool_quote_amount = reader.u64()?;
+            let _minimum_liquidity = reader.u64()?;
+            let lp_mint_supply = reader.u64()?;
+            let lp_token_amount = reader.u64()?;
+            let _pool_bump = reader.u8()?;
+            let pool = reader.pubkey()?;
+
+            let event = Self {
+                timestamp,
+                lp_token_amount,
+                base_amount,
+                quote_amount,
+                pool_base_token_reserves: 0,
+                pool_quote_token_reserves: 0,
+                lp_mint_supply,
+                pool,
+                user,
+            };
+            return Some((LiquidityAction::CreatePool, event));
+        }
+
+        let action = if discriminator == DEPOSIT_EVENT_DISCRIMINATOR {
+            LiquidityAction::Deposit
+        } else if discriminator == WITHDRAW_EVENT_DISCRIMINATOR {
+            LiquidityAction::Withdraw
+        } else {
+            return None;
+        };
+        // Deposit and withdraw share a layout; only the direction flips.
+        let timestamp = reader.i64()?;
+        let lp_token_amount = reader.u64()?;
+        let _base_limit = reader.u64()?;
+        let _quote_limit = reader.u64()?;
+        let _user_base_token_reserves = reader.u64()?;
+        let _user_quote_token_reserves = reader.u64()?;
+        let pool_base_token_reserves = reader.u64()?;
+        let pool_quote_token_reserves = reader.u64()?;
+        let base_amount = reader.u64()?;
+        let quote_amount = reader.u64()?;
+        let lp_mint_supply = reader.u64()?;
+        let pool = reader.pubkey()?;
+        let user = reader.pubkey()?;
+
+        let event = Self {
+            timestamp,
+            lp_token_amount,
+            base_amount,
+            quote_amount,
+            pool_base_token_reserves,
+            pool_quote_token_reserves,
+            lp_mint_supply,
+            pool,
+            user,
+        };
+        Some((action, event))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PumpSwapLiquidity {
+    pub action: LiquidityAction,
+    #[serde(flatten)]
+    pub transaction: TransactionContext,
+    /// Top-level instruction the liquidity change belongs to.
+    pub instruction_index: u32,
+    /// Position among that instruction's inner instructions when PumpSwap was
+    /// reached through CPI.
+    pub inner_index: Option<u32>,
+    /// Top-level program that invoked PumpSwap, `None` when called directly.
+    pub router: Option<String>,
+    pub pool: String,
+    /// From the pool state when it has been seen, otherwise from the instruction.
+    pub base_mint: String,
+    pub quote_mint: String,
+    /// Pool base-vault balance before the instruction; zero for a new pool.
+    pub base_reserve: Option<FixedDecimal>,
+    /// Pool quote-vault balance before the instruction; zero for a new pool.
+    pub quote_reserve: Option<FixedDecimal>,
+    /// Pool base-vault balance after the instruction.
+    pub post_base_reserve: Option<FixedDecimal>,
+    /// Pool quote-vault balance after the instruction.
+    pub post_quote_reserve: Option<FixedDecimal>,
+    /// Raw LP supply after the instruction, known only from the event.
+    pub lp_supply: Option<u64>,
+    #[serde(flatten)]
+    pub args: LiquidityArgs,
+    pub accounts: LiquidityAccounts,
+    /// Raw LP tokens minted to (create, deposit) or burned from (withdraw) the user.
+    pub lp_token_amount: Option<u64>,
+    /// Raw base contributed (create, deposit) or removed (withdraw).
+    pub base_amount: Option<u64>,
+    /// Raw quote contributed (create, deposit) or removed (withdraw).
+    pub quote_amount: Option<u64>,
+    /// The instruction's own event; when present the amounts come from it rather
+    /// than from the token transfers.
+    pub event: Option<LiquidityEvent>,
+    /// Where the reserve estimate or the token transfers disagree with the event:
+    /// any of `base_reserve`, `quote_reserve`, `lp_token_amount`, `base_amount`,
+    /// `quote_amount`.
+    pub mismatches: Vec<&'static str>,
+}
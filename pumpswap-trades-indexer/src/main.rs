@@ -1,15 +1,22 @@
 use std::error::Error;
+use std::io::Write as _;
+use std::path::Path;
+use std::sync::Arc;
 
 use base64::{engine::general_purpose, Engine as _};
 use futures::StreamExt;
 use prost::Message as _;
-use rdkafka::consumer::{Consumer, StreamConsumer};
+use rdkafka::consumer::{CommitMode, Consumer, StreamConsumer};
 use rdkafka::message::Message;
 use rdkafka::ClientConfig;
 use serde::Deserialize;
+use stream_common::recording::UpdateReader;
+use stream_common::shutdown;
 use yellowstone_grpc_proto::prelude::SubscribeUpdate;
 
-mod pumpswap;
+use pumpswap_indexing::{candle, fees, pool, pumpswap, usd};
+
+mod publish;
 
 #[derive(Debug, Deserialize)]
 struct KafkaPayload {
@@ -17,12 +24,19 @@ struct KafkaPayload {
 }
 
 fn build_consumer_config(brokers: &str, group_id: &str) -> Result<ClientConfig, Box<dyn Error>> {
-    let mut config = ClientConfig::new();
+    let mut config = build_client_config(brokers)?;
     config
-        .set("bootstrap.servers", brokers)
         .set("group.id", group_id)
         .set("enable.auto.commit", "true")
+        .set("enable.auto.offset.store", "false")
         .set("auto.offset.reset", "earliest");
+    Ok(config)
+}
+
+/// Brokers and security settings shared by the consumer and the candle producer.
+fn build_client_config(brokers: &str) -> Result<ClientConfig, Box<dyn Error>> {
+    let mut config = ClientConfig::new();
+    config.set("bootstrap.servers", brokers);
 
     let security_protocol = std::env::var("KAFKA_SECURITY_PROTOCOL").ok();
     let sasl_mechanism = std::env::var("KAFKA_SASL_MECHANISM").ok();
@@ -76,6 +90,10 @@ fn payload_from_message(message: &rdkafka::message::BorrowedMessage<'_>) -> Opti
 async fn main() -> Result<(), Box<dyn Error>> {
     dotenv::dotenv().ok();
 
+    if let Ok(path) = std::env::var("REPLAY_PATH") {
+        return replay_file(Path::new(&path));
+    }
+
     let brokers = std::env::var("KAFKA_BROKERS").unwrap_or_else(|_| "localhost:9092".to_string());
     let group_id =
         std::env::var("KAFKA_GROUP_ID").unwrap_or_else(|_| "indexer.pumpswap".to_string());
@@ -86,31 +104,43 @@ async fn main() -> Result<(), Box<dyn Error>> {
     println!("   Kafka group: {}", group_id);
     println!("   Kafka topic: {}", topic);
 
+    let shutdown_timeout = shutdown::timeout_from_env();
+    let mut shutdown = shutdown::listen_for_signals();
+
     let config = build_consumer_config(&brokers, &group_id)?;
-    let consumer: StreamConsumer = config.create()?;
+    let consumer: Arc<StreamConsumer> = Arc::new(config.create()?);
     consumer.subscribe(&[&topic])?;
 
+    let publisher = publish::CandlePublisher::from_env(&build_client_config(&brokers)?)?;
+    if let Some(publisher) = publisher.as_ref() {
+        println!("   Candle topic: {}", publisher.topic());
+    }
+
     println!("Subscribed. Waiting for messages...");
 
-    let mut processor = pumpswap::PumpSwapProcessor::new();
+    let mut processor = new_processor();
     let mut stream = consumer.stream();
-    while let Some(message) = stream.next().await {
+    loop {
+        let message = tokio::select! {
+            _ = shutdown::requested(&mut shutdown) => break,
+            message = stream.next() => match message {
+                Some(message) => message,
+                None => break,
+            },
+        };
+
         match message {
             Ok(msg) => {
-                let Some(payload) = payload_from_message(&msg) else {
-                    eprintln!("Skipping non-JSON Kafka payload");
-                    continue;
-                };
-
-                let update = match decode_update(&payload) {
-                    Ok(update) => update,
-                    Err(err) => {
-                        eprintln!("Failed to decode update: {}", err);
-                        continue;
-                    }
-                };
-
-                processor.handle_update(update);
+                let events = handle_message(&mut processor, &msg);
+                emit(&events);
+                if let Some(publisher) = publisher.as_ref() {
+                    publisher.publish(&events).await;
+                }
+                save_state(&mut processor, true);
+
+                if let Err(err) = consumer.store_offset_from_message(&msg) {
+                    eprintln!("Failed to store Kafka offset: {}", err);
+                }
             }
             Err(err) => {
                 eprintln!("Kafka error: {}", err);
@@ -118,5 +148,167 @@ async fn main() -> Result<(), Box<dyn Error>> {
         }
     }
 
+    drop(stream);
+    save_state(&mut processor, false);
+    let mut finished = processor.flush_candles();
+    finished.extend(processor.flush_fee_summaries());
+    emit(&finished);
+    if let Some(publisher) = publisher.as_ref() {
+        publisher.publish(&finished).await;
+        publisher.flush(shutdown_timeout);
+    }
+    drain(consumer, shutdown_timeout).await;
+
+    Ok(())
+}
+
+fn handle_message(
+    processor: &mut pumpswap::PumpSwapProcessor,
+    msg: &rdkafka::message::BorrowedMessage<'_>,
+) -> Vec<pumpswap::PumpSwapEvent> {
+    let Some(payload) = payload_from_message(msg) else {
+        eprintln!("Skipping non-JSON Kafka payload");
+        return Vec::new();
+    };
+
+    let update = match decode_update(&payload) {
+        Ok(update) => update,
+        Err(err) => {
+            eprintln!("Failed to decode update: {}", err);
+            return Vec::new();
+        }
+    };
+
+    processor.handle_update(update)
+}
+
+fn emit(events: &[pumpswap::PumpSwapEvent]) {
+    for event in events {
+        match serde_json::to_string(event) {
+            Ok(line) => println!("{line}"),
+            Err(err) => eprintln!("Failed to serialize event: {}", err),
+        }
+    }
+}
+
+/// Feeds a file recorded by the ingestor (`INGESTOR_MODE=record`) through the
+/// processor instead of consuming Kafka.
+fn replay_file(path: &Path) -> Result<(), Box<dyn Error>> {
+    println!("Replaying updates from {}", path.display());
+
+    let mut processor = new_processor();
+    let mut count = 0u64;
+    for update in UpdateReader::open(path)? {
+        match update {
+            Ok(update) => {
+                emit(&processor.handle_update(update));
+                count += 1;
+            }
+            Err(err) => {
+                eprintln!("Stopping replay after {} updates: {}", count, err);
+                break;
+            }
+        }
+    }
+
+    emit(&processor.flush_candles());
+    emit(&processor.flush_fee_summaries());
+    println!("Replayed {} updates", count);
+    save_state(&mut processor, false);
     Ok(())
 }
+
+/// A processor seeded with the pools saved at `POOL_REGISTRY_PATH` and the fee
+/// configs saved at `FEE_CONFIG_PATH`, if any, pricing in USD when `SOL_USD_POOL`
+/// is set (resuming from `USD_STATE_PATH`), totalling fees by day unless
+/// `FEE_SUMMARIES=false`, and aggregating candles unless `CANDLE_INTERVALS=none`.
+fn new_processor() -> pumpswap::PumpSwapProcessor {
+    let pools = pool::PoolRegistry::from_env();
+    if !pools.is_empty() {
+        println!("Loaded {} pools from the registry", pools.len());
+    }
+    let fee_configs = fees::FeeConfigs::from_env();
+    if !fee_configs.is_empty() {
+        println!("Loaded {} fee configs", fee_configs.len());
+    }
+    let mut processor = pumpswap::PumpSwapProcessor::new()
+        .with_pool_registry(pools)
+        .with_fee_configs(fee_configs);
+
+    if let Some(usd) = usd::UsdPricer::from_env() {
+        println!("   SOL/USD reference pool: {}", usd.reference_pool());
+        processor = processor.with_usd_pricer(usd);
+    }
+    if let Some(fee_summaries) = fees::FeeAggregator::from_env() {
+        println!("   Daily fee summaries: on");
+        processor = processor.with_fee_summaries(fee_summaries);
+    }
+
+    match candle::CandleAggregator::from_env() {
+        Some(candles) => {
+            let intervals: Vec<_> = candles
+                .intervals()
+                .iter()
+                .map(|interval| interval.as_str())
+                .collect();
+            println!("   Candle intervals: {}", intervals.join(", "));
+            processor.with_candles(candles)
+        }
+        None => processor,
+    }
+}
+
+/// Saves the pool registry, fee configs and USD pricing state where paths are
+/// set; with `when_due` only what was not saved recently.
+fn save_state(processor: &mut pumpswap::PumpSwapProcessor, when_due: bool) {
+    let pools = processor.pools_mut();
+    let saved = if when_due {
+        pools.save_if_due()
+    } else {
+        pools.save()
+    };
+    if let Err(err) = saved {
+        eprintln!("Failed to save pool registry: {}", err);
+    }
+
+    let fee_configs = processor.fee_configs_mut();
+    let saved = if when_due {
+        fee_configs.save_if_due()
+    } else {
+        fee_configs.save()
+    };
+    if let Err(err) = saved {
+        eprintln!("Failed to save fee configs: {}", err);
+    }
+
+    if let Some(usd) = processor.usd_pricer_mut() {
+        let saved = if when_due {
+            usd.save_if_due()
+        } else {
+            usd.save()
+        };
+        if let Err(err) = saved {
+            eprintln!("Failed to save USD state: {}", err);
+        }
+    }
+}
+
+/// Commits stored offsets and flushes stdout, giving up after `timeout`.
+async fn drain(consumer: Arc<StreamConsumer>, timeout: std::time::Duration) {
+    println!("Committing offsets (timeout: {}s)...", timeout.as_secs());
+
+    let commit = tokio::task::spawn_blocking(move || {
+        let result = consumer.commit_consumer_state(CommitMode::Sync);
+        let _ = std::io::stdout().flush();
+        result
+    });
+
+    match tokio::time::timeout(timeout, commit).await {
+        Ok(Ok(Ok(()))) => println!("Offsets committed"),
+        Ok(Ok(Err(err))) => eprintln!("Offset commit failed: {}", err),
+        Ok(Err(err)) => eprintln!("Offset commit task failed: {}", err),
+        Err(_) => eprintln!("Offset commit timed out"),
+    }
+
+    println!("PumpSwap indexer stopped");
+}
@@ -0,0 +1,203 @@
+//! PumpSwap pool state and the registry of pools seen so far.
+//!
+//! Pools are learned from their account updates and, for pools whose account has
+//! not been seen, from the `create_pool` instruction that made them. With
+//! `POOL_REGISTRY_PATH` set the registry is saved as JSON and reloaded on start,
+//! so a restart does not forget pools created before the committed Kafka offset.
+
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use solana_sdk::bs58;
+
+const POOL_DISCRIMINATOR: [u8; 8] = [241, 154, 109, 4, 17, 177, 109, 188];
+/// Discriminator through `lp_supply`; `coin_creator` was appended later.
+const POOL_MIN_LEN: usize = 211;
+const POOL_LEN: usize = 243;
+/// Minimum time between saves while updates keep arriving.
+const SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PoolSource {
+    /// Decoded from the pool account.
+    Account,
+    /// Backfilled from a `create_pool` instruction; `pool_bump` and `lp_supply`
+    /// stay unknown until the account is seen.
+    CreatePool,
+}
+
+/// A decoded `Pool` account.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PoolState {
+    pub address: String,
+    /// Slot of the update this state was read from.
+    pub slot: u64,
+    pub source: PoolSource,
+    pub pool_bump: Option<u8>,
+    pub index: u16,
+    pub creator: String,
+    pub base_mint: String,
+    pub quote_mint: String,
+    pub lp_mint: String,
+    pub pool_base_token_account: String,
+    pub pool_quote_token_account: String,
+    pub lp_supply: Option<u64>,
+    /// Absent from pools created before coin-creator fees were introduced.
+    pub coin_creator: Option<String>,
+}
+
+impl PoolState {
+    /// Decodes the account data, `None` if it is not a pool.
+    ///
+    /// Layout after the discriminator: `pool_bump: u8`, `index: u16`, `creator`,
+    /// `base_mint`, `quote_mint`, `lp_mint`, `pool_base_token_account`,
+    /// `pool_quote_token_account` (32-byte keys), `lp_supply: u64`, `coin_creator`.
+    pub fn decode(address: String, slot: u64, data: &[u8]) -> Option<Self> {
+        if data.len() < POOL_MIN_LEN || data[..8] != POOL_DISCRIMINATOR {
+            return None;
+        }
+        let key = |offset: usize| bs58::encode(&data[offset..offset + 32]).into_string();
+
+        Some(Self {
+            address,
+            slot,
+            source: PoolSource::Account,
+            pool_bump: Some(data[8]),
+            index: u16::from_le_bytes([data[9], data[10]]),
+            creator: key(11),
+            base_mint: key(43),
+            quote_mint: key(75),
+            lp_mint: key(107),
+            pool_base_token_account: key(139),
+            pool_quote_token_account: key(171),
+            lp_supply: Some(u64::from_le_bytes(data[203..211].try_into().ok()?)),
+            coin_creator: (data.len() >= POOL_LEN).then(|| key(211)),
+        })
+    }
+}
+
+/// Pools by address.
+#[derive(Debug, Default)]
+pub struct PoolRegistry {
+    path: Option<PathBuf>,
+    pools: HashMap<String, PoolState>,
+    /// Every pool's base mint, whose supply gives market caps.
+    base_mints: HashSet<String>,
+    dirty: bool,
+    last_save: Option<Instant>,
+}
+
+impl PoolRegistry {
+    /// Loads `POOL_REGISTRY_PATH` if set; otherwise the registry lives in memory.
+    pub fn from_env() -> Self {
+        let path = std::env::var("POOL_REGISTRY_PATH")
+            .ok()
+            .filter(|value| !value.trim().is_empty())
+            .map(PathBuf::from);
+        Self::load(path)
+    }
+
+    /// Reads a saved registry. A missing file is an empty registry; an unreadable
+    /// one is reported and ignored, since pools are learned again from the stream.
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let mut registry = Self {
+            path,
+            ..Self::default()
+        };
+        let Some(path) = registry.path.as_ref() else {
+            return registry;
+        };
+
+        match std::fs::read_to_string(path) {
+            Ok(contents) => match serde_json::from_str::<Vec<PoolState>>(&contents) {
+                Ok(pools) => {
+                    for pool in pools {
+                        registry.insert(pool);
+                    }
+                }
+                Err(err) => eprintln!("Ignoring pool registry {}: {}", path.display(), err),
+            },
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => eprintln!("Ignoring pool registry {}: {}", path.display(), err),
+        }
+        registry
+    }
+
+    pub fn get(&self, address: &str) -> Option<&PoolState> {
+        self.pools.get(address)
+    }
+
+    /// Whether `mint` is the base mint of a known pool.
+    pub fn has_base_mint(&self, mint: &str) -> bool {
+        self.base_mints.contains(mint)
+    }
+
+    pub fn len(&self) -> usize {
+        self.pools.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pools.is_empty()
+    }
+
+    /// Records a decoded account, unless a newer account update is already known.
+    /// Backfilled state is always replaced.
+    pub fn observe_account(&mut self, state: PoolState) {
+        let replace = match self.pools.get(&state.address) {
+            Some(known) => known.source == PoolSource::CreatePool || state.slot >= known.slot,
+            None => true,
+        };
+        if replace && self.pools.get(&state.address) != Some(&state) {
+            self.insert(state);
+            self.dirty = true;
+        }
+    }
+
+    /// Records a pool seen being created, if nothing is known about it yet.
+    pub fn backfill(&mut self, state: PoolState) {
+        if !self.pools.contains_key(&state.address) {
+            self.insert(state);
+            self.dirty = true;
+        }
+    }
+
+    fn insert(&mut self, state: PoolState) {
+        self.base_mints.insert(state.base_mint.clone());
+        self.pools.insert(state.address.clone(), state);
+    }
+
+    /// Saves if something changed and the last save was long enough ago.
+    pub fn save_if_due(&mut self) -> std::io::Result<()> {
+        if self
+            .last_save
+            .is_some_and(|last| last.elapsed() < SAVE_INTERVAL)
+        {
+            return Ok(());
+        }
+        self.save()
+    }
+
+    /// Writes the registry if it changed since the last save.
+    pub fn save(&mut self) -> std::io::Result<()> {
+        let Some(path) = self.path.as_ref() else {
+            return Ok(());
+        };
+        if !self.dirty {
+            return Ok(());
+        }
+
+        let mut pools: Vec<&PoolState> = self.pools.values().collect();
+        pools.sort_by(|a, b| a.address.cmp(&b.address));
+        let contents = serde_json::to_string(&pools)?;
+
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, contents)?;
+        std::fs::rename(&tmp_path, path)?;
+        self.dirty = false;
+        self.last_save = Some(Instant::now());
+        Ok(())
+    }
+}
@@ -0,0 +1,66 @@
+use std::time::Duration;
+
+use rdkafka::error::KafkaError;
+use rdkafka::producer::{FutureProducer, FutureRecord, Producer};
+use rdkafka::util::Timeout;
+use rdkafka::ClientConfig;
+
+use pumpswap_indexing::pumpswap::PumpSwapEvent;
+
+/// Publishes finished candles to `CANDLE_TOPIC`, keyed by pool so each pool's
+/// candles stay in order within a partition.
+pub struct CandlePublisher {
+    producer: FutureProducer,
+    topic: String,
+}
+
+impl CandlePublisher {
+    /// `None` when `CANDLE_TOPIC` is unset.
+    pub fn from_env(config: &ClientConfig) -> Result<Option<Self>, KafkaError> {
+        let Some(topic) = std::env::var("CANDLE_TOPIC")
+            .ok()
+            .filter(|value| !value.trim().is_empty())
+        else {
+            return Ok(None);
+        };
+
+        let producer: FutureProducer = config.clone().set("message.timeout.ms", "5000").create()?;
+        Ok(Some(Self { producer, topic }))
+    }
+
+    pub fn topic(&self) -> &str {
+        &self.topic
+    }
+
+    pub async fn publish(&self, events: &[PumpSwapEvent]) {
+        for event in events {
+            let PumpSwapEvent::Candle(candle) = event else {
+                continue;
+            };
+            let payload = match serde_json::to_string(event) {
+                Ok(payload) => payload,
+                Err(err) => {
+                    eprintln!("Failed to serialize candle: {}", err);
+                    continue;
+                }
+            };
+
+            let record = FutureRecord::to(&self.topic)
+                .payload(payload.as_str())
+                .key(candle.pool.as_str());
+            if let Err((err, _)) = self
+                .producer
+                .send(record, Timeout::After(Duration::from_secs(5)))
+                .await
+            {
+                eprintln!("Failed to publish candle to {}: {}", self.topic, err);
+            }
+        }
+    }
+
+    pub fn flush(&self, timeout: Duration) {
+        if let Err(err) = self.producer.flush(Timeout::After(timeout)) {
+            eprintln!("Failed to flush candles: {}", err);
+        }
+    }
+}
@@ -1,36 +1,532 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
+use serde::Serialize;
 use solana_sdk::bs58;
+use solana_sdk::transaction::TransactionError;
 use yellowstone_grpc_proto::prelude::{
-    subscribe_update::UpdateOneof, CompiledInstruction, Message, SubscribeUpdate,
-    SubscribeUpdateAccount, SubscribeUpdateTransaction, TransactionStatusMeta,
+    subscribe_update::UpdateOneof, Message, SubscribeUpdate, SubscribeUpdateAccount,
+    SubscribeUpdateTransaction, TokenBalance, TransactionStatusMeta,
 };
 
+use crate::admin::{ConfigChange, FeeConfig, PumpSwapConfigUpdate};
+use crate::amount::FixedDecimal;
+use crate::call_tree::CallTree;
+use crate::candle::{Candle, CandleAggregator};
+use crate::fees::{FeeAggregator, FeeConfigs, FeeSummary, TradeFees};
+use crate::liquidity::{
+    LiquidityAccounts, LiquidityAction, LiquidityArgs, LiquidityEvent, PumpSwapLiquidity,
+};
+use crate::pool::{PoolRegistry, PoolSource, PoolState};
+use crate::usd::UsdPricer;
+
 const PUMPSWAP_PROGRAM_ID: &str = "pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA";
-const POOL_DISCRIMINATOR: [u8; 8] = [241, 154, 109, 4, 17, 177, 109, 188];
 const BUY_DISCRIMINATOR: [u8; 8] = [102, 6, 61, 18, 1, 218, 235, 234];
 const SELL_DISCRIMINATOR: [u8; 8] = [51, 230, 133, 164, 1, 127, 131, 173];
+/// Prefix of an Anchor `emit_cpi!` instruction: `sha256("anchor:event")[..8]`, as
+/// a little-endian `u64`.
+pub(crate) const EVENT_IX_TAG: [u8; 8] = [228, 69, 165, 46, 81, 203, 154, 29];
+const BUY_EVENT_DISCRIMINATOR: [u8; 8] = [103, 244, 82, 31, 44, 245, 119, 119];
+const SELL_EVENT_DISCRIMINATOR: [u8; 8] = [62, 47, 55, 10, 165, 3, 220, 42];
+const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+const TOKEN_2022_PROGRAM_ID: &str = "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb";
+const TRANSFER: u8 = 3;
+const MINT_TO: u8 = 7;
+const BURN: u8 = 8;
+const TRANSFER_CHECKED: u8 = 12;
+const MINT_TO_CHECKED: u8 = 14;
+const BURN_CHECKED: u8 = 15;
+/// Fractional digits kept for prices; memecoin prices are often below 1e-8 SOL.
+pub const PRICE_DECIMALS: u8 = 18;
+/// Slots of block times kept behind the newest block meta.
+const BLOCK_TIME_SLOTS: u64 = 512;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TradeSide {
+    Buy,
+    Sell,
+}
+
+/// Instruction arguments, raw amounts as signed by the user.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum SwapArgs {
+    Buy {
+        base_amount_out: u64,
+        max_quote_amount_in: u64,
+    },
+    Sell {
+        base_amount_in: u64,
+        min_quote_amount_out: u64,
+    },
+}
+
+impl SwapArgs {
+    pub fn side(&self) -> TradeSide {
+        match self {
+            SwapArgs::Buy { .. } => TradeSide::Buy,
+            SwapArgs::Sell { .. } => TradeSide::Sell,
+        }
+    }
+}
+
+/// The named accounts of a buy or sell (both use the same layout), other than the
+/// pool itself.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SwapAccounts {
+    pub user: String,
+    pub global_config: String,
+    pub base_mint: String,
+    pub quote_mint: String,
+    pub user_base_token_account: String,
+    pub user_quote_token_account: String,
+    pub pool_base_token_account: String,
+    pub pool_quote_token_account: String,
+    pub protocol_fee_recipient: String,
+    pub protocol_fee_recipient_token_account: String,
+    /// Absent on swaps sent before coin-creator fees were introduced.
+    pub coin_creator_vault_ata: Option<String>,
+    pub coin_creator_vault_authority: Option<String>,
+}
+
+impl SwapAccounts {
+    fn decode(accounts: &[u8], account_keys: &[String]) -> Option<Self> {
+        let key = |position: usize| account_keys.get(*accounts.get(position)? as usize).cloned();
+        // 11-16 are the token, system and associated token programs, the event
+        // authority and PumpSwap itself.
+        Some(Self {
+            user: key(1)?,
+            global_config: key(2)?,
+            base_mint: key(3)?,
+            quote_mint: key(4)?,
+            user_base_token_account: key(5)?,
+            user_quote_token_account: key(6)?,
+            pool_base_token_account: key(7)?,
+            pool_quote_token_account: key(8)?,
+            protocol_fee_recipient: key(9)?,
+            protocol_fee_recipient_token_account: key(10)?,
+            coin_creator_vault_ata: key(17),
+            coin_creator_vault_authority: key(18),
+        })
+    }
+}
+
+/// A `BuyEvent` or `SellEvent`: the amounts PumpSwap actually settled, emitted by a
+/// CPI to itself. All amounts are raw.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SwapEvent {
+    /// Unix seconds from the cluster clock.
+    pub timestamp: i64,
+    pub base_amount: u64,
+    /// Quote priced by the curve, before fees.
+    pub quote_amount: u64,
+    /// Quote into (buy, plus the LP fee) or out of (sell, minus the LP fee) the pool
+    /// vault.
+    pub pool_quote_amount: u64,
+    /// Quote the user paid (buy) or received (sell), all fees included.
+    pub user_quote_amount: u64,
+    pub user_base_token_reserves: u64,
+    pub user_quote_token_reserves: u64,
+    /// Pool vault balances before the swap.
+    pub pool_base_token_reserves: u64,
+    pub pool_quote_token_reserves: u64,
+    pub lp_fee_basis_points: u64,
+    pub lp_fee: u64,
+    pub protocol_fee_basis_points: u64,
+    pub protocol_fee: u64,
+    pub pool: String,
+    pub user: String,
+    /// Absent from events emitted before coin-creator fees were introduced.
+    pub coin_creator: Option<String>,
+    pub coin_creator_fee_basis_points: Option<u64>,
+    pub coin_creator_fee: Option<u64>,
+}
+
+impl SwapEvent {
+    /// Decodes the data of an event CPI, returning which side emitted it.
+    fn decode(data: &[u8]) -> Option<(TradeSide, Self)> {
+        let data = data.strip_prefix(EVENT_IX_TAG.as_slice())?;
+        let (discriminator, payload) = data.split_at_checked(8)?;
+        let side = if discriminator == BUY_EVENT_DISCRIMINATOR {
+            TradeSide::Buy
+        } else if discriminator == SELL_EVENT_DISCRIMINATOR {
+            TradeSide::Sell
+        } else {
+            return None;
+        };
+
+        // Both events share a layout; only the meaning of the quote fields flips.
+        let mut reader = Reader(payload);
+        let timestamp = reader.i64()?;
+        let base_amount = reader.u64()?;
+        let _quote_limit = reader.u64()?;
+        let user_base_token_reserves = reader.u64()?;
+        let user_quote_token_reserves = reader.u64()?;
+        let pool_base_token_reserves = reader.u64()?;
+        let pool_quote_token_reserves = reader.u64()?;
+        let quote_amount = reader.u64()?;
+        let lp_fee_basis_points = reader.u64()?;
+        let lp_fee = reader.u64()?;
+        let protocol_fee_basis_points = reader.u64()?;
+        let protocol_fee = reader.u64()?;
+        let pool_quote_amount = reader.u64()?;
+        let user_quote_amount = reader.u64()?;
+        let pool = reader.pubkey()?;
+        let user = reader.pubkey()?;
+        // User token accounts and fee recipient, already known from the instruction.
+        for _ in 0..4 {
+            reader.pubkey()?;
+        }
+
+        let event = Self {
+            timestamp,
+            base_amount,
+            quote_amount,
+            pool_quote_amount,
+            user_quote_amount,
+            user_base_token_reserves,
+            user_quote_token_reserves,
+            pool_base_token_reserves,
+            pool_quote_token_reserves,
+            lp_fee_basis_points,
+            lp_fee,
+            protocol_fee_basis_points,
+            protocol_fee,
+            pool,
+            user,
+            coin_creator: reader.pubkey(),
+            coin_creator_fee_basis_points: reader.u64(),
+            coin_creator_fee: reader.u64(),
+        };
+        Some((side, event))
+    }
+}
+
+/// Reads Borsh fields (little-endian, fixed width) in order.
+pub(crate) struct Reader<'a>(pub(crate) &'a [u8]);
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let (head, rest) = self.0.split_at_checked(N)?;
+        self.0 = rest;
+        head.try_into().ok()
+    }
+
+    pub(crate) fn bool(&mut self) -> Option<bool> {
+        self.u8().map(|byte| byte != 0)
+    }
+
+    pub(crate) fn u8(&mut self) -> Option<u8> {
+        self.take().map(u8::from_le_bytes)
+    }
+
+    pub(crate) fn u16(&mut self) -> Option<u16> {
+        self.take().map(u16::from_le_bytes)
+    }
+
+    pub(crate) fn u64(&mut self) -> Option<u64> {
+        self.take().map(u64::from_le_bytes)
+    }
+
+    pub(crate) fn i64(&mut self) -> Option<i64> {
+        self.take().map(i64::from_le_bytes)
+    }
+
+    pub(crate) fn pubkey(&mut self) -> Option<String> {
+        self.take::<32>().map(|key| bs58::encode(key).into_string())
+    }
+}
+
+/// The transaction an event belongs to.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TransactionContext {
+    pub slot: u64,
+    pub signature: String,
+    /// Unix seconds, known only once the slot's block meta has been seen.
+    pub block_time: Option<i64>,
+    /// Position of the transaction within its slot.
+    pub transaction_index: u64,
+    pub fee_payer: Option<String>,
+    pub success: bool,
+    /// The `TransactionError`, e.g. `InstructionError(1, Custom(6001))`.
+    pub error: Option<String>,
+    pub compute_units_consumed: Option<u64>,
+    /// Lamports paid by the fee payer.
+    pub transaction_fee: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PumpSwapTrade {
+    pub side: TradeSide,
+    #[serde(flatten)]
+    pub transaction: TransactionContext,
+    /// Top-level instruction the swap belongs to.
+    pub instruction_index: u32,
+    /// Position among that instruction's inner instructions when PumpSwap was
+    /// reached through CPI.
+    pub inner_index: Option<u32>,
+    /// Top-level program that invoked PumpSwap (an aggregator or bot), `None` when
+    /// the swap was called directly.
+    pub router: Option<String>,
+    pub pool: String,
+    /// From the pool state when it has been seen, otherwise from the instruction.
+    pub base_mint: String,
+    /// Usually WSOL, but pools can quote in any mint.
+    pub quote_mint: String,
+    /// Pool base-vault balance before the trade.
+    pub base_reserve: Option<FixedDecimal>,
+    /// Pool quote-vault balance before the trade.
+    pub quote_reserve: Option<FixedDecimal>,
+    /// Quote per base from the pool reserves before the trade, to `PRICE_DECIMALS`
+    /// places; `None` when a reserve is missing or the base reserve is empty.
+    pub price: Option<FixedDecimal>,
+    /// Pool base-vault balance after the trade.
+    pub post_base_reserve: Option<FixedDecimal>,
+    /// Pool quote-vault balance after the trade.
+    pub post_quote_reserve: Option<FixedDecimal>,
+    /// Spot price the trade left the pool at.
+    pub post_price: Option<FixedDecimal>,
+    /// Quote paid (buy, fees included) or received (sell) per base traded.
+    pub execution_price: Option<FixedDecimal>,
+    /// USD per SOL from the reference pool when the trade was seen; `None` without
+    /// `SOL_USD_POOL` or before the reference pool has traded.
+    pub sol_usd: Option<FixedDecimal>,
+    /// `execution_price` in USD, for pools quoted in WSOL or the reference
+    /// stablecoin.
+    pub price_usd: Option<FixedDecimal>,
+    /// The quote side of the trade, fees included, in USD.
+    pub volume_usd: Option<FixedDecimal>,
+    /// `price_usd` times the base mint's supply, once its mint account has been
+    /// seen.
+    pub market_cap_usd: Option<FixedDecimal>,
+    #[serde(flatten)]
+    pub args: SwapArgs,
+    pub accounts: SwapAccounts,
+    /// Raw base amount actually received (buy) or paid in (sell), from the swap's
+    /// token transfers.
+    pub base_amount: Option<u64>,
+    /// Raw quote amount actually paid, fees included (buy), or received (sell).
+    pub quote_amount: Option<u64>,
+    /// How much worse the fill was than the pre-trade reserve price, in basis
+    /// points (price impact plus fees).
+    pub slippage_bps: Option<i64>,
+    pub fees: TradeFees,
+    /// The swap's own event; when present `base_amount` and `quote_amount` come
+    /// from it rather than from the token transfers.
+    pub event: Option<SwapEvent>,
+    /// Where the reserve estimate or the token transfers disagree with the event:
+    /// any of `base_reserve`, `quote_reserve`, `base_amount`, `quote_amount`,
+    /// `protocol_fee`, `coin_creator_fee`; or where the global config's rates do:
+    /// `lp_fee_basis_points`, `protocol_fee_basis_points`,
+    /// `coin_creator_fee_basis_points`.
+    pub mismatches: Vec<&'static str>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PumpSwapEvent {
+    Trade(PumpSwapTrade),
+    Liquidity(PumpSwapLiquidity),
+    Config(PumpSwapConfigUpdate),
+    /// A finished candle, when candles are enabled.
+    Candle(Candle),
+    /// A finished day of fees for a pool or coin creator, when fee summaries are
+    /// enabled.
+    Fees(FeeSummary),
+}
+
+/// A PumpSwap instruction, called directly or reached through CPI.
+struct PumpSwapInstruction<'a> {
+    accounts: &'a [u8],
+    data: &'a [u8],
+    instruction_index: u32,
+    inner_index: Option<u32>,
+    router: Option<String>,
+    /// Token transfers the instruction made directly.
+    transfers: Vec<TokenTransfer>,
+    /// Swap events the instruction emitted.
+    events: Vec<(TradeSide, SwapEvent)>,
+    /// Liquidity events the instruction emitted.
+    liquidity_events: Vec<(LiquidityAction, LiquidityEvent)>,
+}
 
 pub struct PumpSwapProcessor {
-    known_pools: HashMap<String, (String, String)>,
+    pools: PoolRegistry,
+    /// Block time by slot, from block meta updates.
+    block_times: BTreeMap<u64, i64>,
+    candles: Option<CandleAggregator>,
+    usd: Option<UsdPricer>,
+    fee_configs: FeeConfigs,
+    fee_summaries: Option<FeeAggregator>,
+}
+
+impl Default for PumpSwapProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl PumpSwapProcessor {
     pub fn new() -> Self {
         Self {
-            known_pools: HashMap::new(),
+            pools: PoolRegistry::default(),
+            block_times: BTreeMap::new(),
+            candles: None,
+            usd: None,
+            fee_configs: FeeConfigs::default(),
+            fee_summaries: None,
         }
     }
 
-    pub fn handle_update(&mut self, update: SubscribeUpdate) {
+    /// Starts from a registry loaded at startup instead of an empty one.
+    pub fn with_pool_registry(mut self, pools: PoolRegistry) -> Self {
+        self.pools = pools;
+        self
+    }
+
+    /// Starts from the fee configs loaded at startup instead of none.
+    pub fn with_fee_configs(mut self, fee_configs: FeeConfigs) -> Self {
+        self.fee_configs = fee_configs;
+        self
+    }
+
+    /// Aggregates trades into candles, emitted after the trades that finish them.
+    pub fn with_candles(mut self, candles: CandleAggregator) -> Self {
+        self.candles = Some(candles);
+        self
+    }
+
+    /// Prices trades, and the candles built from them, in USD.
+    pub fn with_usd_pricer(mut self, usd: UsdPricer) -> Self {
+        self.usd = Some(usd);
+        self
+    }
+
+    /// Totals fees per pool and coin creator by day, emitted after the trades
+    /// that finish the day.
+    pub fn with_fee_summaries(mut self, fee_summaries: FeeAggregator) -> Self {
+        self.fee_summaries = Some(fee_summaries);
+        self
+    }
+
+    /// Emits every open candle, finished or not; for shutdown and the end of a
+    /// replay.
+    pub fn flush_candles(&mut self) -> Vec<PumpSwapEvent> {
+        self.candles
+            .as_mut()
+            .map(|candles| candles.flush())
+            .unwrap_or_default()
+            .into_iter()
+            .map(PumpSwapEvent::Candle)
+            .collect()
+    }
+
+    /// Emits every open day of fees, finished or not; for shutdown and the end of
+    /// a replay.
+    pub fn flush_fee_summaries(&mut self) -> Vec<PumpSwapEvent> {
+        self.fee_summaries
+            .as_mut()
+            .map(|fee_summaries| fee_summaries.flush())
+            .unwrap_or_default()
+            .into_iter()
+            .map(PumpSwapEvent::Fees)
+            .collect()
+    }
+
+    pub fn fee_configs(&self) -> &FeeConfigs {
+        &self.fee_configs
+    }
+
+    pub fn fee_configs_mut(&mut self) -> &mut FeeConfigs {
+        &mut self.fee_configs
+    }
+
+    pub fn pools(&self) -> &PoolRegistry {
+        &self.pools
+    }
+
+    pub fn pools_mut(&mut self) -> &mut PoolRegistry {
+        &mut self.pools
+    }
+
+    pub fn usd_pricer(&self) -> Option<&UsdPricer> {
+        self.usd.as_ref()
+    }
+
+    pub fn usd_pricer_mut(&mut self) -> Option<&mut UsdPricer> {
+        self.usd.as_mut()
+    }
+
+    pub fn handle_update(&mut self, update: SubscribeUpdate) -> Vec<PumpSwapEvent> {
         match update.update_oneof {
             Some(UpdateOneof::Account(account_update)) => {
                 self.handle_account_update(account_update);
+                Vec::new()
             }
             Some(UpdateOneof::Transaction(tx_update)) => {
-                self.handle_transaction_update(tx_update);
+                let mut events = self.handle_transaction_update(tx_update);
+                // In execution order, so a trade is priced with the reference as
+                // the transaction left it at that point.
+                if let Some(usd) = self.usd.as_mut() {
+                    for event in &mut events {
+                        usd.observe(event);
+                    }
+                }
+                let mut finished = Vec::new();
+                for event in &events {
+                    let PumpSwapEvent::Trade(trade) = event else {
+                        continue;
+                    };
+                    if let Some(candles) = self.candles.as_mut() {
+                        finished.extend(
+                            candles
+                                .observe(trade)
+                                .into_iter()
+                                .map(PumpSwapEvent::Candle),
+                        );
+                    }
+                    if let Some(fee_summaries) = self.fee_summaries.as_mut() {
+                        finished.extend(
+                            fee_summaries
+                                .observe(trade)
+                                .into_iter()
+                                .map(PumpSwapEvent::Fees),
+                        );
+                    }
+                }
+                events.extend(finished);
+                events
             }
-            _ => {}
+            Some(UpdateOneof::BlockMeta(block_meta)) => {
+                let mut events = Vec::new();
+                if let Some(block_time) = block_meta.block_time {
+                    self.block_times
+                        .insert(block_meta.slot, block_time.timestamp);
+                    // Block times also move candles and fee days on when no trades
+                    // arrive.
+                    if let Some(candles) = self.candles.as_mut() {
+                        events.extend(
+                            candles
+                                .advance(block_time.timestamp)
+                                .into_iter()
+                                .map(PumpSwapEvent::Candle),
+                        );
+                    }
+                    if let Some(fee_summaries) = self.fee_summaries.as_mut() {
+                        events.extend(
+                            fee_summaries
+                                .advance(block_time.timestamp)
+                                .into_iter()
+                                .map(PumpSwapEvent::Fees),
+                        );
+                    }
+                }
+                // Only recent slots can still have transactions arriving.
+                let oldest = block_meta.slot.saturating_sub(BLOCK_TIME_SLOTS);
+                self.block_times = self.block_times.split_off(&oldest);
+                events
+            }
+            _ => Vec::new(),
         }
     }
 
@@ -38,174 +534,908 @@ impl PumpSwapProcessor {
         if let Some(account) = &account_update.account {
             let owner = bs58::encode(&account.owner).into_string();
             let account_address = bs58::encode(&account.pubkey).into_string();
-
-            if owner == PUMPSWAP_PROGRAM_ID {
-                if account.data.len() >= 107 {
-                    let discriminator = &account.data[0..8];
-                    if discriminator == &POOL_DISCRIMINATOR {
-                        let base_mint = bs58::encode(&account.data[43..75]).into_string();
-                        let quote_mint = bs58::encode(&account.data[75..107]).into_string();
-                        self.known_pools
-                            .insert(account_address, (base_mint, quote_mint));
+            if owner == TOKEN_PROGRAM_ID || owner == TOKEN_2022_PROGRAM_ID {
+                // Mint supplies are only needed for market caps; the subscription
+                // carries every mint write of the token programs.
+                if let Some(usd) = self.usd.as_mut() {
+                    if self.pools.has_base_mint(&account_address)
+                        || usd.wants_supply(&account_address)
+                    {
+                        usd.observe_mint(account_address, account_update.slot, &account.data);
                     }
                 }
+                return;
+            }
+            if owner != PUMPSWAP_PROGRAM_ID {
+                return;
+            }
+
+            if let Some(config) = FeeConfig::decode_account(&account.data) {
+                self.fee_configs
+                    .observe(account_address, account_update.slot, config);
+            } else if let Some(state) =
+                PoolState::decode(account_address, account_update.slot, &account.data)
+            {
+                self.pools.observe_account(state);
             }
         }
     }
 
-    fn handle_transaction_update(&mut self, tx_update: SubscribeUpdateTransaction) {
-        if let Some(tx) = &tx_update.transaction {
-            if let Some(meta) = &tx.meta {
-                if let Some(message) = &tx.transaction {
-                    if let Some(tx_message) = &message.message {
-                        for instruction in &tx_message.instructions {
-                            let program_id_index = instruction.program_id_index as usize;
-                            if program_id_index < tx_message.account_keys.len() {
-                                let program_id_bytes = &tx_message.account_keys[program_id_index];
-                                let program_id = bs58::encode(program_id_bytes).into_string();
-
-                                if program_id == PUMPSWAP_PROGRAM_ID {
-                                    self.parse_pumpswap_instruction(
-                                        instruction,
-                                        tx_message,
-                                        meta,
-                                        tx_update.slot,
-                                    );
-                                }
-                            }
-                        }
-                    }
+    fn handle_transaction_update(
+        &mut self,
+        tx_update: SubscribeUpdateTransaction,
+    ) -> Vec<PumpSwapEvent> {
+        let mut events = Vec::new();
+        let Some(tx) = &tx_update.transaction else {
+            return events;
+        };
+        let Some(meta) = &tx.meta else {
+            return events;
+        };
+        let Some(tx_message) = tx.transaction.as_ref().and_then(|t| t.message.as_ref()) else {
+            return events;
+        };
+
+        let account_keys = resolve_account_keys(tx_message, meta);
+        let transaction = TransactionContext {
+            slot: tx_update.slot,
+            signature: bs58::encode(&tx.signature).into_string(),
+            block_time: self.block_times.get(&tx_update.slot).copied(),
+            transaction_index: tx.index,
+            fee_payer: account_keys.first().cloned(),
+            success: meta.err.is_none(),
+            error: meta.err.as_ref().map(|err| transaction_error(&err.err)),
+            compute_units_consumed: meta.compute_units_consumed,
+            transaction_fee: meta.fee,
+        };
+
+        let mut reserves = ReserveTracker::new(
+            &account_keys,
+            &meta.pre_token_balances,
+            &meta.post_token_balances,
+        );
+        // Swaps routed through aggregators are CPIs, so each top-level instruction's
+        // inner instructions are searched too, in execution order.
+        for (idx, instruction) in tx_message.instructions.iter().enumerate() {
+            let inner_instructions: Vec<_> = meta
+                .inner_instructions
+                .iter()
+                .filter(|inner| inner.index == idx as u32)
+                .flat_map(|inner| inner.instructions.iter())
+                .collect();
+            // Program id indexes past the account keys leave their nodes unresolved.
+            let program_id =
+                |program_id_index: u32| account_keys.get(program_id_index as usize).cloned();
+            let tree = CallTree::build(
+                program_id(instruction.program_id_index),
+                inner_instructions
+                    .iter()
+                    .map(|inner| (program_id(inner.program_id_index), inner.stack_height)),
+            );
+
+            let top_level = (
+                None,
+                instruction.program_id_index,
+                &instruction.accounts,
+                &instruction.data,
+            );
+            let inner = inner_instructions
+                .iter()
+                .enumerate()
+                .map(|(inner_idx, inner)| {
+                    (
+                        Some(inner_idx as u32),
+                        inner.program_id_index,
+                        &inner.accounts,
+                        &inner.data,
+                    )
+                });
+            for (inner_index, program_id_index, accounts, data) in
+                std::iter::once(top_level).chain(inner)
+            {
+                let is_pumpswap = account_keys
+                    .get(program_id_index as usize)
+                    .is_some_and(|program_id| program_id == PUMPSWAP_PROGRAM_ID);
+                if !is_pumpswap {
+                    continue;
                 }
+                let children: Vec<_> = tree
+                    .children(inner_index)
+                    .into_iter()
+                    .filter_map(|child| inner_instructions.get(child.inner_index? as usize))
+                    .collect();
+                let transfers = children
+                    .iter()
+                    .filter_map(|inner| {
+                        TokenTransfer::decode(
+                            &account_keys,
+                            inner.program_id_index,
+                            &inner.accounts,
+                            &inner.data,
+                        )
+                    })
+                    .collect();
+                // Event CPIs go from PumpSwap to itself.
+                let self_calls: Vec<_> = children
+                    .iter()
+                    .filter(|inner| inner.program_id_index == program_id_index)
+                    .collect();
+                let swap_events = self_calls
+                    .iter()
+                    .filter_map(|inner| SwapEvent::decode(&inner.data))
+                    .collect();
+                let liquidity_events = self_calls
+                    .iter()
+                    .filter_map(|inner| LiquidityEvent::decode(&inner.data))
+                    .collect();
+                let call = PumpSwapInstruction {
+                    accounts,
+                    data,
+                    instruction_index: idx as u32,
+                    inner_index,
+                    router: tree.invoked_by(inner_index).into_iter().flatten().next(),
+                    transfers,
+                    events: swap_events,
+                    liquidity_events,
+                };
+                events.extend(self.parse_pumpswap_instruction(
+                    &call,
+                    &account_keys,
+                    &mut reserves,
+                    &transaction,
+                ));
             }
         }
+
+        events
     }
 
     fn parse_pumpswap_instruction(
         &mut self,
-        instruction: &CompiledInstruction,
-        message: &Message,
-        meta: &TransactionStatusMeta,
-        slot: u64,
-    ) {
-        let data = &instruction.data;
-
-        if data.len() < 8 {
-            return;
+        instruction: &PumpSwapInstruction,
+        account_keys: &[String],
+        reserves: &mut ReserveTracker,
+        transaction: &TransactionContext,
+    ) -> Option<PumpSwapEvent> {
+        let data = instruction.data;
+        if let Some(args) = LiquidityArgs::decode(data) {
+            return self
+                .extract_liquidity(instruction, account_keys, reserves, args, transaction)
+                .map(PumpSwapEvent::Liquidity);
+        }
+        if let Some(update) = PumpSwapConfigUpdate::decode(
+            data,
+            instruction.accounts,
+            account_keys,
+            transaction,
+            instruction.instruction_index,
+            instruction.inner_index,
+        ) {
+            match &update.change {
+                // Failed admin instructions changed nothing.
+                ConfigChange::CreateConfig(fees) | ConfigChange::UpdateFeeConfig(fees)
+                    if transaction.success =>
+                {
+                    self.fee_configs.observe(
+                        update.global_config.clone(),
+                        transaction.slot,
+                        fees.clone(),
+                    );
+                }
+                _ => {}
+            }
+            return Some(PumpSwapEvent::Config(update));
         }
 
-        let discriminator = &data[0..8];
+        let discriminator = data.get(0..8)?;
+        // Both take `(u64, u64)`: buy `base_amount_out, max_quote_amount_in`, sell
+        // `base_amount_in, min_quote_amount_out`.
+        let base_amount = u64::from_le_bytes(data.get(8..16)?.try_into().ok()?);
+        let quote_limit = u64::from_le_bytes(data.get(16..24)?.try_into().ok()?);
 
-        if discriminator == &BUY_DISCRIMINATOR {
-            println!("PUMPSWAP BUY INSTRUCTION DETECTED (slot: {})", slot);
-            self.extract_pumpswap_price_from_reserves(instruction, message, meta, "buy", slot);
-        } else if discriminator == &SELL_DISCRIMINATOR {
-            println!("PUMPSWAP SELL INSTRUCTION DETECTED (slot: {})", slot);
-            self.extract_pumpswap_price_from_reserves(instruction, message, meta, "sell", slot);
-        }
+        let args = if discriminator == BUY_DISCRIMINATOR {
+            SwapArgs::Buy {
+                base_amount_out: base_amount,
+                max_quote_amount_in: quote_limit,
+            }
+        } else if discriminator == SELL_DISCRIMINATOR {
+            SwapArgs::Sell {
+                base_amount_in: base_amount,
+                min_quote_amount_out: quote_limit,
+            }
+        } else {
+            return None;
+        };
+        let accounts = SwapAccounts::decode(instruction.accounts, account_keys)?;
+
+        self.extract_pumpswap_price_from_reserves(
+            instruction,
+            account_keys,
+            reserves,
+            args,
+            accounts,
+            transaction,
+        )
+        .map(PumpSwapEvent::Trade)
     }
 
     fn extract_pumpswap_price_from_reserves(
         &mut self,
-        instruction: &CompiledInstruction,
-        message: &Message,
-        meta: &TransactionStatusMeta,
-        instruction_type: &str,
-        slot: u64,
-    ) {
-        if instruction.accounts.is_empty() {
-            return;
-        }
-
-        let pool_account_index = instruction.accounts[0] as usize;
-        if pool_account_index >= message.account_keys.len() {
-            return;
-        }
+        instruction: &PumpSwapInstruction,
+        account_keys: &[String],
+        reserves: &mut ReserveTracker,
+        args: SwapArgs,
+        accounts: SwapAccounts,
+        transaction: &TransactionContext,
+    ) -> Option<PumpSwapTrade> {
+        let pool_account_index = *instruction.accounts.first()? as usize;
+        let pool_address = account_keys.get(pool_account_index)?.clone();
 
-        let pool_address_bytes = &message.account_keys[pool_account_index];
-        let pool_address = bs58::encode(pool_address_bytes).into_string();
+        // The pool state decides which side is base; the instruction's mint accounts
+        // are the fallback for pools not seen yet.
+        let (base_mint, quote_mint) = match self.pools.get(&pool_address) {
+            Some(pool) => (pool.base_mint.clone(), pool.quote_mint.clone()),
+            None => (accounts.base_mint.clone(), accounts.quote_mint.clone()),
+        };
 
-        println!(
-            "Processing PumpSwap {} for pool: {} (slot: {})",
-            instruction_type, pool_address, slot
+        let estimate = reserves.current(
+            &pool_address,
+            Vault::new(&accounts.pool_base_token_account, &base_mint),
+            Vault::new(&accounts.pool_quote_token_account, &quote_mint),
         );
 
-        let (base_mint, _quote_mint) =
-            if let Some((base, quote)) = self.known_pools.get(&pool_address) {
-                (base.clone(), quote.clone())
-            } else {
-                ("".to_string(), "".to_string())
-            };
-
-        let mut token_reserve: f64 = 0.0;
-        let mut sol_reserve: f64 = 0.0;
-        let mut detected_token_mint = String::new();
-
-        for balance in &meta.pre_token_balances {
-            if balance.owner == pool_address {
-                let mint = &balance.mint;
-                let amount = balance
-                    .ui_token_amount
-                    .as_ref()
-                    .map(|a| a.ui_amount)
-                    .unwrap_or(0.0);
-
-                if mint == "So11111111111111111111111111111111111111112" {
-                    sol_reserve = amount;
-                } else {
-                    token_reserve = amount;
-                    detected_token_mint = mint.clone();
+        let side = args.side();
+        let transferred = fill(side, &accounts, &instruction.transfers);
+        let event = instruction
+            .events
+            .iter()
+            .find(|(event_side, event)| *event_side == side && event.pool == pool_address)
+            .map(|(_, event)| event.clone());
+        let config = self.fee_configs.get(&accounts.global_config);
+        let mismatches = event
+            .as_ref()
+            .map(|event| {
+                let mut mismatches = cross_check(event, &transferred, &estimate);
+                if let Some(config) = config {
+                    mismatches.extend(fee_rate_mismatches(event, config));
                 }
-
-                println!(
-                    "  - {} reserve: {} (mint: {})",
-                    if mint == "So11111111111111111111111111111111111111112" {
-                        "SOL"
-                    } else {
-                        "Token"
-                    },
-                    amount,
-                    mint
+                mismatches
+            })
+            .unwrap_or_default();
+        let pool_coin_creator = self
+            .pools
+            .get(&pool_address)
+            .and_then(|pool| pool.coin_creator.clone());
+        // The event is authoritative for both the amounts and the reserves it saw.
+        let (pre, fill, fees) = match &event {
+            Some(event) => (
+                estimate.with_event(event),
+                Fill {
+                    base_amount: Some(event.base_amount),
+                    quote_amount: Some(event.user_quote_amount),
+                    pool_quote_amount: Some(event.pool_quote_amount),
+                    protocol_fee: Some(event.protocol_fee),
+                    coin_creator_fee: event.coin_creator_fee,
+                },
+                TradeFees::from_event(event, pool_coin_creator),
+            ),
+            None => {
+                let fees = TradeFees::from_transfers(
+                    side,
+                    config,
+                    transferred.pool_quote_amount,
+                    transferred.protocol_fee,
+                    transferred.coin_creator_fee,
+                    pool_coin_creator,
                 );
+                (estimate, transferred, fees)
+            }
+        };
+        let post = pre.after(side, &fill);
+        reserves.update(&pool_address, post);
+
+        let slippage_bps = match (fill.base_amount, fill.quote_amount, pre.base, pre.quote) {
+            (Some(base), Some(quote), Some(base_reserve), Some(quote_reserve)) => {
+                slippage_bps(side, base, quote, base_reserve.value, quote_reserve.value)
             }
+            _ => None,
+        };
+        let execution_price = match (fill.base_amount, fill.quote_amount, pre.base, pre.quote) {
+            (Some(base), Some(quote), Some(base_reserve), Some(quote_reserve)) => price(
+                FixedDecimal::new(quote, quote_reserve.decimals),
+                FixedDecimal::new(base, base_reserve.decimals),
+            ),
+            _ => None,
+        };
+
+        Some(PumpSwapTrade {
+            side,
+            transaction: transaction.clone(),
+            instruction_index: instruction.instruction_index,
+            inner_index: instruction.inner_index,
+            router: instruction.router.clone(),
+            pool: pool_address,
+            base_mint,
+            quote_mint,
+            base_reserve: pre.base,
+            quote_reserve: pre.quote,
+            price: pre.price(),
+            post_base_reserve: post.base,
+            post_quote_reserve: post.quote,
+            post_price: post.price(),
+            execution_price,
+            sol_usd: None,
+            price_usd: None,
+            volume_usd: None,
+            market_cap_usd: None,
+            args,
+            accounts,
+            base_amount: fill.base_amount,
+            quote_amount: fill.quote_amount,
+            slippage_bps,
+            fees,
+            event,
+            mismatches,
+        })
+    }
+
+    /// A `create_pool`, `deposit` or `withdraw`. Like a swap, the reserves it saw
+    /// and left are tracked so later swaps on the pool in the same transaction see
+    /// them.
+    fn extract_liquidity(
+        &mut self,
+        instruction: &PumpSwapInstruction,
+        account_keys: &[String],
+        reserves: &mut ReserveTracker,
+        args: LiquidityArgs,
+        transaction: &TransactionContext,
+    ) -> Option<PumpSwapLiquidity> {
+        let pool_account_index = *instruction.accounts.first()? as usize;
+        let pool_address = account_keys.get(pool_account_index)?.clone();
+        let accounts = LiquidityAccounts::decode(instruction.accounts, account_keys)?;
+        let action = args.action();
+        // A failed create_pool made no pool.
+        if action == LiquidityAction::CreatePool && transaction.success {
+            self.pools.backfill(created_pool(
+                &pool_address,
+                &accounts,
+                &args,
+                transaction.slot,
+            ));
         }
 
-        let final_token_mint = if !base_mint.is_empty()
-            && base_mint != "So11111111111111111111111111111111111111112"
-        {
-            base_mint
+        let (base_mint, quote_mint) = match self.pools.get(&pool_address) {
+            Some(pool) => (pool.base_mint.clone(), pool.quote_mint.clone()),
+            None => (accounts.base_mint.clone(), accounts.quote_mint.clone()),
+        };
+        let base_vault = Vault::new(&accounts.pool_base_token_account, &base_mint);
+        let quote_vault = Vault::new(&accounts.pool_quote_token_account, &quote_mint);
+        // A new pool's vaults are created by the instruction itself.
+        let estimate = match action {
+            LiquidityAction::CreatePool => reserves.created(base_vault, quote_vault),
+            _ => reserves.current(&pool_address, base_vault, quote_vault),
+        };
+
+        let user_base = Some(&accounts.user_base_token_account);
+        let user_quote = Some(&accounts.user_quote_token_account);
+        let user_lp = Some(&accounts.user_pool_token_account);
+        let pool_base = Some(&accounts.pool_base_token_account);
+        let pool_quote = Some(&accounts.pool_quote_token_account);
+        let lp_mint = Some(&accounts.lp_mint);
+        let transfers = &instruction.transfers;
+        // LP tokens are minted to the user when adding and burned when removing.
+        let (lp_token_amount, base_amount, quote_amount) = if action.adds() {
+            (
+                moved(transfers, lp_mint, user_lp),
+                moved(transfers, user_base, pool_base),
+                moved(transfers, user_quote, pool_quote),
+            )
         } else {
-            detected_token_mint
+            (
+                moved(transfers, user_lp, lp_mint),
+                moved(transfers, pool_base, user_base),
+                moved(transfers, pool_quote, user_quote),
+            )
         };
 
-        if token_reserve > 0.0 && sol_reserve > 0.0 {
-            let price_per_token = sol_reserve / token_reserve;
-            let tokens_per_sol = token_reserve / sol_reserve;
+        let event = instruction
+            .liquidity_events
+            .iter()
+            .find(|(event_action, event)| *event_action == action && event.pool == pool_address)
+            .map(|(_, event)| event.clone());
+        let mismatches = event
+            .as_ref()
+            .map(|event| {
+                mismatched([
+                    (
+                        "base_reserve",
+                        estimate.base.map(|reserve| reserve.value),
+                        event.pool_base_token_reserves,
+                    ),
+                    (
+                        "quote_reserve",
+                        estimate.quote.map(|reserve| reserve.value),
+                        event.pool_quote_token_reserves,
+                    ),
+                    (
+                        "lp_token_amount",
+                        lp_token_amount.map(u128::from),
+                        event.lp_token_amount,
+                    ),
+                    (
+                        "base_amount",
+                        base_amount.map(u128::from),
+                        event.base_amount,
+                    ),
+                    (
+                        "quote_amount",
+                        quote_amount.map(u128::from),
+                        event.quote_amount,
+                    ),
+                ])
+            })
+            .unwrap_or_default();
+        let (pre, lp_token_amount, base_amount, quote_amount) = match &event {
+            Some(event) => (
+                estimate.with_pool_reserves(
+                    event.pool_base_token_reserves,
+                    event.pool_quote_token_reserves,
+                ),
+                Some(event.lp_token_amount),
+                Some(event.base_amount),
+                Some(event.quote_amount),
+            ),
+            None => (estimate, lp_token_amount, base_amount, quote_amount),
+        };
+        let post = Reserves {
+            base: shift(pre.base, base_amount, action.adds()),
+            quote: shift(pre.quote, quote_amount, action.adds()),
+        };
+        reserves.update(&pool_address, post);
 
-            println!("CURRENT PRICE: {:.9} SOL per token", price_per_token);
-            println!("CURRENT PRICE: {:.2} tokens per SOL", tokens_per_sol);
-            println!("RESERVES: {} tokens, {} SOL", token_reserve, sol_reserve);
+        Some(PumpSwapLiquidity {
+            action,
+            transaction: transaction.clone(),
+            instruction_index: instruction.instruction_index,
+            inner_index: instruction.inner_index,
+            router: instruction.router.clone(),
+            pool: pool_address,
+            base_mint,
+            quote_mint,
+            base_reserve: pre.base,
+            quote_reserve: pre.quote,
+            post_base_reserve: post.base,
+            post_quote_reserve: post.quote,
+            lp_supply: event.as_ref().map(|event| event.lp_mint_supply),
+            args,
+            accounts,
+            lp_token_amount,
+            base_amount,
+            quote_amount,
+            event,
+            mismatches,
+        })
+    }
+}
 
-            if final_token_mint != "So11111111111111111111111111111111111111112"
-                && !final_token_mint.is_empty()
-            {
-                println!(
-                    "Price update: {} = {:.9} SOL per token",
-                    final_token_mint, price_per_token
-                );
-                println!("   Pool: {}", pool_address);
-                println!("   Slot: {}", slot);
-                println!("   Timestamp: {:?}", std::time::SystemTime::now());
-            } else {
-                println!("Invalid token mint: {}", final_token_mint);
-            }
-        } else {
-            println!("Insufficient reserve data for price calculation");
-            println!(
-                "   Token Reserve: {}, SOL Reserve: {}",
-                token_reserve, sol_reserve
-            );
+/// The pool a `create_pool` instruction made, as far as the instruction tells.
+fn created_pool(
+    address: &str,
+    accounts: &LiquidityAccounts,
+    args: &LiquidityArgs,
+    slot: u64,
+) -> PoolState {
+    let (index, coin_creator) = match args {
+        LiquidityArgs::CreatePool {
+            index,
+            coin_creator,
+            ..
+        } => (*index, coin_creator.clone()),
+        _ => (0, None),
+    };
+
+    PoolState {
+        address: address.to_string(),
+        slot,
+        source: PoolSource::CreatePool,
+        pool_bump: None,
+        index,
+        creator: accounts.user.clone(),
+        base_mint: accounts.base_mint.clone(),
+        quote_mint: accounts.quote_mint.clone(),
+        lp_mint: accounts.lp_mint.clone(),
+        pool_base_token_account: accounts.pool_base_token_account.clone(),
+        pool_quote_token_account: accounts.pool_quote_token_account.clone(),
+        lp_supply: None,
+        coin_creator,
+    }
+}
+
+/// Raw amounts a swap moved, from its event or its own token transfers. `None` when
+/// nothing matching was seen, e.g. inner instructions missing from the update.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Fill {
+    /// Base received (buy) or paid in (sell).
+    base_amount: Option<u64>,
+    /// Quote paid including the protocol and coin-creator fees (buy), or received
+    /// (sell).
+    quote_amount: Option<u64>,
+    /// Quote into (buy) or out of (sell) the pool's quote vault.
+    pool_quote_amount: Option<u64>,
+    /// Quote to the protocol fee recipient's token account.
+    protocol_fee: Option<u64>,
+    /// Quote to the coin creator's vault.
+    coin_creator_fee: Option<u64>,
+}
+
+/// Total of the transfers from `source` to `destination`, either of which may be
+/// any account; `None` when there are none.
+fn moved(
+    transfers: &[TokenTransfer],
+    source: Option<&String>,
+    destination: Option<&String>,
+) -> Option<u64> {
+    transfers
+        .iter()
+        .filter(|transfer| {
+            source.is_none_or(|source| &transfer.source == source)
+                && destination.is_none_or(|destination| &transfer.destination == destination)
+        })
+        .map(|transfer| transfer.amount)
+        .reduce(u64::saturating_add)
+}
+
+fn fill(side: TradeSide, accounts: &SwapAccounts, transfers: &[TokenTransfer]) -> Fill {
+    let user_base = Some(&accounts.user_base_token_account);
+    let user_quote = Some(&accounts.user_quote_token_account);
+    let pool_base = Some(&accounts.pool_base_token_account);
+    let pool_quote = Some(&accounts.pool_quote_token_account);
+    let protocol = Some(&accounts.protocol_fee_recipient_token_account);
+    // Swaps from before coin-creator fees have no creator vault to pay.
+    let creator = |payer| {
+        let vault = accounts.coin_creator_vault_ata.as_ref()?;
+        moved(transfers, payer, Some(vault))
+    };
+
+    match side {
+        // The user pays the fees on top, so the quote side covers every transfer out
+        // of their account.
+        TradeSide::Buy => Fill {
+            base_amount: moved(transfers, pool_base, user_base),
+            quote_amount: moved(transfers, user_quote, None),
+            pool_quote_amount: moved(transfers, user_quote, pool_quote),
+            protocol_fee: moved(transfers, user_quote, protocol),
+            coin_creator_fee: creator(user_quote),
+        },
+        // The fees are paid out of the pool's quote vault.
+        TradeSide::Sell => Fill {
+            base_amount: moved(transfers, user_base, pool_base),
+            quote_amount: moved(transfers, pool_quote, user_quote),
+            pool_quote_amount: moved(transfers, pool_quote, None),
+            protocol_fee: moved(transfers, pool_quote, protocol),
+            coin_creator_fee: creator(pool_quote),
+        },
+    }
+}
+
+/// Compares the reserve estimate and the transferred amounts with what the event
+/// reports; values that could not be estimated are not compared.
+fn cross_check(event: &SwapEvent, transferred: &Fill, estimate: &Reserves) -> Vec<&'static str> {
+    let mut mismatches = mismatched([
+        (
+            "base_reserve",
+            estimate.base.map(|reserve| reserve.value),
+            event.pool_base_token_reserves,
+        ),
+        (
+            "quote_reserve",
+            estimate.quote.map(|reserve| reserve.value),
+            event.pool_quote_token_reserves,
+        ),
+        (
+            "base_amount",
+            transferred.base_amount.map(u128::from),
+            event.base_amount,
+        ),
+        (
+            "quote_amount",
+            transferred.quote_amount.map(u128::from),
+            event.user_quote_amount,
+        ),
+        (
+            "protocol_fee",
+            transferred.protocol_fee.map(u128::from),
+            event.protocol_fee,
+        ),
+    ]);
+    if let Some(coin_creator_fee) = event.coin_creator_fee {
+        mismatches.extend(mismatched([(
+            "coin_creator_fee",
+            transferred.coin_creator_fee.map(u128::from),
+            coin_creator_fee,
+        )]));
+    }
+    mismatches
+}
+
+/// Compares the rates the event charged with the global config's.
+fn fee_rate_mismatches(event: &SwapEvent, config: &FeeConfig) -> Vec<&'static str> {
+    let mut mismatches = mismatched([
+        (
+            "lp_fee_basis_points",
+            Some(u128::from(config.lp_fee_basis_points)),
+            event.lp_fee_basis_points,
+        ),
+        (
+            "protocol_fee_basis_points",
+            Some(u128::from(config.protocol_fee_basis_points)),
+            event.protocol_fee_basis_points,
+        ),
+    ]);
+    if let Some(coin_creator_fee_basis_points) = event.coin_creator_fee_basis_points {
+        mismatches.extend(mismatched([(
+            "coin_creator_fee_basis_points",
+            config.coin_creator_fee_basis_points.map(u128::from),
+            coin_creator_fee_basis_points,
+        )]));
+    }
+    mismatches
+}
+
+/// Names of the `(name, estimate, reported)` checks whose estimate is known and
+/// differs from the reported value.
+fn mismatched<const N: usize>(checks: [(&'static str, Option<u128>, u64); N]) -> Vec<&'static str> {
+    checks
+        .into_iter()
+        .filter(|(_, estimate, reported)| {
+            estimate.is_some_and(|value| value != u128::from(*reported))
+        })
+        .map(|(name, _, _)| name)
+        .collect()
+}
+
+/// A pool's reserves at one point in a transaction.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Reserves {
+    base: Option<FixedDecimal>,
+    quote: Option<FixedDecimal>,
+}
+
+impl Reserves {
+    /// Quote per base, to `PRICE_DECIMALS` places.
+    fn price(&self) -> Option<FixedDecimal> {
+        price(self.quote?, self.base?)
+    }
+
+    /// The reserves reported by the swap's event, keeping the known decimals.
+    fn with_event(&self, event: &SwapEvent) -> Self {
+        self.with_pool_reserves(
+            event.pool_base_token_reserves,
+            event.pool_quote_token_reserves,
+        )
+    }
+
+    /// Reserves reported by an event, keeping the known decimals.
+    fn with_pool_reserves(&self, base: u64, quote: u64) -> Self {
+        Self {
+            base: self
+                .base
+                .map(|reserve| FixedDecimal::new(base, reserve.decimals)),
+            quote: self
+                .quote
+                .map(|reserve| FixedDecimal::new(quote, reserve.decimals)),
+        }
+    }
+
+    /// The reserves once `fill` has settled; a side whose amount is unknown
+    /// becomes unknown.
+    fn after(&self, side: TradeSide, fill: &Fill) -> Self {
+        let buy = side == TradeSide::Buy;
+        Self {
+            base: shift(self.base, fill.base_amount, !buy),
+            quote: shift(self.quote, fill.pool_quote_amount, buy),
+        }
+    }
+}
+
+/// A reserve after `amount` was added to or taken from it; unknown when either is.
+fn shift(reserve: Option<FixedDecimal>, amount: Option<u64>, added: bool) -> Option<FixedDecimal> {
+    let reserve = reserve?;
+    let amount = u128::from(amount?);
+    let value = if added {
+        reserve.value.checked_add(amount)?
+    } else {
+        reserve.value.checked_sub(amount)?
+    };
+    Some(FixedDecimal::new(value, reserve.decimals))
+}
+
+/// A pool token account and the mint it should hold.
+#[derive(Clone, Copy)]
+struct Vault<'a> {
+    address: &'a str,
+    mint: &'a str,
+}
+
+impl<'a> Vault<'a> {
+    fn new(address: &'a str, mint: &'a str) -> Self {
+        Self { address, mint }
+    }
+}
+
+/// Pool reserves as a transaction's swaps settle, starting from its pre-token
+/// balances, so several swaps on one pool each see the reserves left by the last.
+struct ReserveTracker<'a> {
+    account_keys: &'a [String],
+    pre_token_balances: &'a [TokenBalance],
+    /// Only for the decimals of vaults created by the transaction.
+    post_token_balances: &'a [TokenBalance],
+    pools: HashMap<String, Reserves>,
+}
+
+impl<'a> ReserveTracker<'a> {
+    fn new(
+        account_keys: &'a [String],
+        pre_token_balances: &'a [TokenBalance],
+        post_token_balances: &'a [TokenBalance],
+    ) -> Self {
+        Self {
+            account_keys,
+            pre_token_balances,
+            post_token_balances,
+            pools: HashMap::new(),
+        }
+    }
+
+    /// The pool's reserves, read on first use from the pre-token balances of its
+    /// vaults. A vault holding some other mint than expected is not trusted.
+    fn current(&self, pool: &str, base: Vault, quote: Vault) -> Reserves {
+        if let Some(reserves) = self.pools.get(pool) {
+            return *reserves;
+        }
+
+        Reserves {
+            base: self.vault_balance(self.pre_token_balances, base),
+            quote: self.vault_balance(self.pre_token_balances, quote),
+        }
+    }
+
+    /// Empty reserves for vaults the transaction creates, with the decimals of
+    /// their post-token balances.
+    fn created(&self, base: Vault, quote: Vault) -> Reserves {
+        let empty = |vault| {
+            self.vault_balance(self.post_token_balances, vault)
+                .map(|balance| FixedDecimal::new(0u64, balance.decimals))
+        };
+        Reserves {
+            base: empty(base),
+            quote: empty(quote),
+        }
+    }
+
+    fn vault_balance(&self, balances: &[TokenBalance], vault: Vault) -> Option<FixedDecimal> {
+        let balance = balances.iter().find(|balance| {
+            self.account_keys
+                .get(balance.account_index as usize)
+                .is_some_and(|account| account == vault.address)
+        })?;
+        if balance.mint != vault.mint {
+            return None;
         }
+        let amount = balance.ui_token_amount.as_ref()?;
+        FixedDecimal::from_raw_str(&amount.amount, amount.decimals as u8)
+    }
 
-        println!("------------------------");
+    fn update(&mut self, pool: &str, reserves: Reserves) {
+        self.pools.insert(pool.to_string(), reserves);
     }
 }
+
+/// How much worse the fill was than the pre-trade spot price, in basis points:
+/// price impact plus fees. Raw reserves are used, so decimals cancel out.
+fn slippage_bps(
+    side: TradeSide,
+    base_amount: u64,
+    quote_amount: u64,
+    base_reserve: u128,
+    quote_reserve: u128,
+) -> Option<i64> {
+    let expected = u128::from(base_amount)
+        .checked_mul(quote_reserve)?
+        .checked_div(base_reserve)?;
+    if expected == 0 {
+        return None;
+    }
+    let expected = i128::try_from(expected).ok()?;
+    let actual = i128::from(quote_amount);
+    let worse_by = match side {
+        TradeSide::Buy => actual - expected,
+        TradeSide::Sell => expected - actual,
+    };
+    i64::try_from(worse_by.checked_mul(10_000)? / expected).ok()
+}
+
+/// A token-program `Transfer` or `TransferChecked`. A `MintTo` counts as a transfer
+/// out of the mint account and a `Burn` as one into it, so LP tokens can be
+/// followed the same way.
+#[derive(Clone, Debug, PartialEq)]
+struct TokenTransfer {
+    source: String,
+    destination: String,
+    amount: u64,
+}
+
+impl TokenTransfer {
+    fn decode(
+        account_keys: &[String],
+        program_id_index: u32,
+        accounts: &[u8],
+        data: &[u8],
+    ) -> Option<Self> {
+        let program_id = account_keys.get(program_id_index as usize)?;
+        if program_id != TOKEN_PROGRAM_ID && program_id != TOKEN_2022_PROGRAM_ID {
+            return None;
+        }
+        let key = |position: usize| account_keys.get(*accounts.get(position)? as usize).cloned();
+        let amount = u64::from_le_bytes(data.get(1..9)?.try_into().ok()?);
+
+        match *data.first()? {
+            // Transfer: source, destination, authority
+            TRANSFER => Some(Self {
+                source: key(0)?,
+                destination: key(1)?,
+                amount,
+            }),
+            // TransferChecked: source, mint, destination, authority
+            TRANSFER_CHECKED => Some(Self {
+                source: key(0)?,
+                destination: key(2)?,
+                amount,
+            }),
+            // MintTo(Checked): mint, destination, authority
+            MINT_TO | MINT_TO_CHECKED => Some(Self {
+                source: key(0)?,
+                destination: key(1)?,
+                amount,
+            }),
+            // Burn(Checked): source, mint, owner
+            BURN | BURN_CHECKED => Some(Self {
+                source: key(0)?,
+                destination: key(1)?,
+                amount,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// `quote / base` in whole units: `quote_raw * 10^base_decimals / (base_raw * 10^quote_decimals)`.
+pub(crate) fn price(quote: FixedDecimal, base: FixedDecimal) -> Option<FixedDecimal> {
+    let numerator = quote
+        .value
+        .checked_mul(10u128.checked_pow(u32::from(base.decimals))?)?;
+    let denominator = base
+        .value
+        .checked_mul(10u128.checked_pow(u32::from(quote.decimals))?)?;
+    FixedDecimal::ratio(numerator, denominator, PRICE_DECIMALS)
+}
+
+/// Renders the bincode-encoded `TransactionError` carried in the protobuf, falling
+/// back to hex if it does not decode.
+fn transaction_error(err: &[u8]) -> String {
+    match bincode::deserialize::<TransactionError>(err) {
+        Ok(err) => format!("{err:?}"),
+        Err(_) => err.iter().map(|byte| format!("{byte:02x}")).collect(),
+    }
+}
+
+/// Static keys followed by lookup-table addresses, matching how v0 instructions index accounts.
+fn resolve_account_keys(message: &Message, meta: &TransactionStatusMeta) -> Vec<String> {
+    message
+        .account_keys
+        .iter()
+        .chain(meta.loaded_writable_addresses.iter())
+        .chain(meta.loaded_readonly_addresses.iter())
+        .map(|key| bs58::encode(key).into_string())
+        .collect()
+}
@@ -0,0 +1,354 @@
+//! USD pricing from a SOL/USD reference pool.
+//!
+//! `SOL_USD_POOL` names a PumpSwap pool pairing WSOL with a stablecoin, e.g.
+//! SOL/USDC. Its reserves after every successful trade or liquidity change on it
+//! give the SOL/USD price, and its other mint is taken to be worth exactly $1.
+//! Trades quoted in WSOL or in that stablecoin are then priced in USD; market cap
+//! additionally needs the base mint's supply, read from mint account writes. With
+//! `USD_STATE_PATH` set the reference price and supplies are saved as JSON and
+//! reloaded on start, since neither is sent again until it changes.
+
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::amount::FixedDecimal;
+use crate::liquidity::PumpSwapLiquidity;
+use crate::pumpswap::{price, PumpSwapEvent, PumpSwapTrade, PRICE_DECIMALS};
+
+pub const WSOL_MINT: &str = "So11111111111111111111111111111111111111112";
+/// Fractional digits kept for USD volumes and market caps.
+pub const USD_DECIMALS: u8 = 6;
+/// Size of an SPL Token mint; Token-2022 mints with extensions are longer and
+/// carry their account type after the 165 bytes of a token account.
+const MINT_LEN: usize = 82;
+const ACCOUNT_TYPE_OFFSET: usize = 165;
+const ACCOUNT_TYPE_MINT: u8 = 1;
+/// Minimum time between saves while updates keep arriving.
+const SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Where an update sits in the chain: slot, transaction, instruction, inner
+/// instruction.
+type ChainOrder = (u64, u64, u32, Option<u32>);
+
+#[derive(Debug)]
+pub struct UsdPricer {
+    reference_pool: String,
+    /// The reference pool's non-SOL mint, known once the pool has been seen.
+    stable_mint: Option<String>,
+    /// USD per SOL, to `PRICE_DECIMALS` places.
+    sol_usd: Option<FixedDecimal>,
+    /// Where `sol_usd` was last taken from, so updates arriving late do not
+    /// override newer ones.
+    updated_at: Option<ChainOrder>,
+    /// Supply by mint, with the slot it was written at.
+    supplies: HashMap<String, (u64, FixedDecimal)>,
+    /// Base mints of the trades seen, whose supplies are wanted.
+    traded_mints: HashSet<String>,
+    path: Option<PathBuf>,
+    dirty: bool,
+    last_save: Option<Instant>,
+}
+
+/// What is saved at `USD_STATE_PATH`.
+#[derive(Serialize, Deserialize)]
+struct SavedState {
+    reference_pool: String,
+    stable_mint: Option<String>,
+    sol_usd: Option<FixedDecimal>,
+    updated_at: Option<ChainOrder>,
+    supplies: Vec<SavedSupply>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedSupply {
+    mint: String,
+    slot: u64,
+    supply: FixedDecimal,
+}
+
+impl UsdPricer {
+    pub fn new(reference_pool: impl Into<String>) -> Self {
+        Self {
+            reference_pool: reference_pool.into(),
+            stable_mint: None,
+            sol_usd: None,
+            updated_at: None,
+            supplies: HashMap::new(),
+            traded_mints: HashSet::new(),
+            path: None,
+            dirty: false,
+            last_save: None,
+        }
+    }
+
+    /// A pricer for the pool at `SOL_USD_POOL`, loaded from `USD_STATE_PATH` if
+    /// set; `None` when the pool is unset, which leaves every USD field empty. A
+    /// pool that does not pair WSOL never sets a reference price.
+    pub fn from_env() -> Option<Self> {
+        let reference_pool = std::env::var("SOL_USD_POOL")
+            .ok()
+            .map(|pool| pool.trim().to_string())
+            .filter(|pool| !pool.is_empty())?;
+        let path = std::env::var("USD_STATE_PATH")
+            .ok()
+            .filter(|value| !value.trim().is_empty())
+            .map(PathBuf::from);
+        Some(Self::load(reference_pool, path))
+    }
+
+    /// Reads saved state. A missing file starts empty; an unreadable one is
+    /// reported and ignored. A reference price saved for another pool is dropped.
+    pub fn load(reference_pool: impl Into<String>, path: Option<PathBuf>) -> Self {
+        let mut pricer = Self {
+            path,
+            ..Self::new(reference_pool)
+        };
+        let Some(path) = pricer.path.as_ref() else {
+            return pricer;
+        };
+
+        let saved = match std::fs::read_to_string(path) {
+            Ok(contents) => match serde_json::from_str::<SavedState>(&contents) {
+                Ok(saved) => saved,
+                Err(err) => {
+                    eprintln!("Ignoring USD state {}: {}", path.display(), err);
+                    return pricer;
+                }
+            },
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return pricer,
+            Err(err) => {
+                eprintln!("Ignoring USD state {}: {}", path.display(), err);
+                return pricer;
+            }
+        };
+
+        if saved.reference_pool == pricer.reference_pool {
+            pricer.stable_mint = saved.stable_mint;
+            pricer.sol_usd = saved.sol_usd;
+            pricer.updated_at = saved.updated_at;
+        }
+        pricer.supplies = saved
+            .supplies
+            .into_iter()
+            .map(|saved| (saved.mint, (saved.slot, saved.supply)))
+            .collect();
+        pricer
+    }
+
+    pub fn reference_pool(&self) -> &str {
+        &self.reference_pool
+    }
+
+    /// USD per SOL from the newest reference-pool reserves seen.
+    pub fn sol_usd(&self) -> Option<FixedDecimal> {
+        self.sol_usd
+    }
+
+    /// Supply of `mint` in whole tokens, when its account has been seen.
+    pub fn supply(&self, mint: &str) -> Option<FixedDecimal> {
+        self.supplies.get(mint).map(|(_, supply)| *supply)
+    }
+
+    /// Whether `mint` has been traded as a base mint or has a supply already, so
+    /// its writes are worth recording.
+    pub fn wants_supply(&self, mint: &str) -> bool {
+        self.traded_mints.contains(mint) || self.supplies.contains_key(mint)
+    }
+
+    /// Records the supply from a Token or Token-2022 account write; anything but a
+    /// mint is ignored. Supplies are never pruned, so callers pass only the mints
+    /// they need market caps for.
+    pub fn observe_mint(&mut self, address: String, slot: u64, data: &[u8]) {
+        let is_mint =
+            data.len() == MINT_LEN || data.get(ACCOUNT_TYPE_OFFSET) == Some(&ACCOUNT_TYPE_MINT);
+        if !is_mint {
+            return;
+        }
+        let Some(supply) = data.get(36..44).and_then(|bytes| bytes.try_into().ok()) else {
+            return;
+        };
+        let supply = FixedDecimal::new(u64::from_le_bytes(supply), data[44]);
+
+        let newer = self
+            .supplies
+            .get(&address)
+            .is_none_or(|(seen, _)| slot >= *seen);
+        if newer && self.supplies.get(&address) != Some(&(slot, supply)) {
+            self.supplies.insert(address, (slot, supply));
+            self.dirty = true;
+        }
+    }
+
+    /// Saves if something changed and the last save was long enough ago.
+    pub fn save_if_due(&mut self) -> std::io::Result<()> {
+        if self
+            .last_save
+            .is_some_and(|last| last.elapsed() < SAVE_INTERVAL)
+        {
+            return Ok(());
+        }
+        self.save()
+    }
+
+    /// Writes the state if it changed since the last save.
+    pub fn save(&mut self) -> std::io::Result<()> {
+        let Some(path) = self.path.as_ref() else {
+            return Ok(());
+        };
+        if !self.dirty {
+            return Ok(());
+        }
+
+        let mut supplies: Vec<SavedSupply> = self
+            .supplies
+            .iter()
+            .map(|(mint, (slot, supply))| SavedSupply {
+                mint: mint.clone(),
+                slot: *slot,
+                supply: *supply,
+            })
+            .collect();
+        supplies.sort_by(|a, b| a.mint.cmp(&b.mint));
+        let contents = serde_json::to_string(&SavedState {
+            reference_pool: self.reference_pool.clone(),
+            stable_mint: self.stable_mint.clone(),
+            sol_usd: self.sol_usd,
+            updated_at: self.updated_at,
+            supplies,
+        })?;
+
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, contents)?;
+        std::fs::rename(&tmp_path, path)?;
+        self.dirty = false;
+        self.last_save = Some(Instant::now());
+        Ok(())
+    }
+
+    /// Updates the reference from a trade or liquidity change and fills in a
+    /// trade's USD fields; other events pass through.
+    pub fn observe(&mut self, event: &mut PumpSwapEvent) {
+        match event {
+            PumpSwapEvent::Trade(trade) => {
+                if !self.traded_mints.contains(&trade.base_mint) {
+                    self.traded_mints.insert(trade.base_mint.clone());
+                }
+                self.observe_trade(trade);
+                self.price(trade);
+            }
+            PumpSwapEvent::Liquidity(liquidity) => self.observe_liquidity(liquidity),
+            _ => {}
+        }
+    }
+
+    fn observe_trade(&mut self, trade: &PumpSwapTrade) {
+        if !trade.transaction.success || trade.pool != self.reference_pool {
+            return;
+        }
+        let order = (
+            trade.transaction.slot,
+            trade.transaction.transaction_index,
+            trade.instruction_index,
+            trade.inner_index,
+        );
+        self.update_reference(
+            order,
+            (&trade.base_mint, trade.post_base_reserve),
+            (&trade.quote_mint, trade.post_quote_reserve),
+        );
+    }
+
+    fn observe_liquidity(&mut self, liquidity: &PumpSwapLiquidity) {
+        if !liquidity.transaction.success || liquidity.pool != self.reference_pool {
+            return;
+        }
+        let order = (
+            liquidity.transaction.slot,
+            liquidity.transaction.transaction_index,
+            liquidity.instruction_index,
+            liquidity.inner_index,
+        );
+        self.update_reference(
+            order,
+            (&liquidity.base_mint, liquidity.post_base_reserve),
+            (&liquidity.quote_mint, liquidity.post_quote_reserve),
+        );
+    }
+
+    /// Takes SOL/USD from the reference pool's reserves, whichever side WSOL is on.
+    fn update_reference(
+        &mut self,
+        order: ChainOrder,
+        base: (&String, Option<FixedDecimal>),
+        quote: (&String, Option<FixedDecimal>),
+    ) {
+        let (sol, stable) = if base.0 == WSOL_MINT {
+            (base, quote)
+        } else if quote.0 == WSOL_MINT {
+            (quote, base)
+        } else {
+            return;
+        };
+        if self.stable_mint.as_ref() != Some(stable.0) {
+            self.stable_mint = Some(stable.0.clone());
+            self.dirty = true;
+        }
+
+        if self.updated_at.is_some_and(|newest| order < newest) {
+            return;
+        }
+        let sol_usd = match (stable.1, sol.1) {
+            (Some(stable_reserve), Some(sol_reserve)) => price(stable_reserve, sol_reserve),
+            _ => None,
+        };
+        if sol_usd.is_some() {
+            self.sol_usd = sol_usd;
+            self.updated_at = Some(order);
+            self.dirty = true;
+        }
+    }
+
+    /// USD per whole quote token: the SOL/USD reference for WSOL, $1 for the
+    /// reference stablecoin, unknown for anything else.
+    fn quote_usd(&self, quote_mint: &str) -> Option<FixedDecimal> {
+        if quote_mint == WSOL_MINT {
+            self.sol_usd
+        } else if self.stable_mint.as_deref() == Some(quote_mint) {
+            Some(FixedDecimal::new(1u64, 0))
+        } else {
+            None
+        }
+    }
+
+    fn price(&self, trade: &mut PumpSwapTrade) {
+        trade.sol_usd = self.sol_usd;
+        let Some(quote_usd) = self.quote_usd(&trade.quote_mint) else {
+            return;
+        };
+
+        trade.price_usd = trade
+            .execution_price
+            .and_then(|price| multiply(price, quote_usd, PRICE_DECIMALS));
+        trade.volume_usd = match (trade.quote_amount, trade.quote_reserve) {
+            (Some(amount), Some(reserve)) => multiply(
+                FixedDecimal::new(amount, reserve.decimals),
+                quote_usd,
+                USD_DECIMALS,
+            ),
+            _ => None,
+        };
+        trade.market_cap_usd = match (trade.price_usd, self.supply(&trade.base_mint)) {
+            (Some(price_usd), Some(supply)) => multiply(price_usd, supply, USD_DECIMALS),
+            _ => None,
+        };
+    }
+}
+
+/// `a * b` truncated to `decimals` places; `None` on overflow.
+fn multiply(a: FixedDecimal, b: FixedDecimal, decimals: u8) -> Option<FixedDecimal> {
+    let scale = 10u128.checked_pow(u32::from(a.decimals) + u32::from(b.decimals))?;
+    FixedDecimal::ratio(a.value.checked_mul(b.value)?, scale, decimals)
+}
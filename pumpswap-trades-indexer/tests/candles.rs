@@ -0,0 +1,150 @@
+mod common;
+
+use pumpswap_indexing::candle::{Candle, CandleAggregator, CandleInterval, CandleStatus};
+use pumpswap_indexing::pumpswap::{PumpSwapEvent, PumpSwapProcessor, PumpSwapTrade};
+
+const LATENESS_SECS: i64 = 30;
+
+fn trades(fixture: &str) -> Vec<PumpSwapTrade> {
+    let events = common::run(&mut PumpSwapProcessor::new(), fixture);
+    common::trades(&events).into_iter().cloned().collect()
+}
+
+/// The same trade `seconds` later, in a later slot.
+fn later(trade: &PumpSwapTrade, seconds: i64) -> PumpSwapTrade {
+    let mut trade = trade.clone();
+    trade.transaction.slot += seconds as u64 * 2;
+    if let Some(event) = trade.event.as_mut() {
+        event.timestamp += seconds;
+    }
+    trade
+}
+
+fn of_interval(candles: Vec<Candle>, interval: CandleInterval) -> Vec<Candle> {
+    candles
+        .into_iter()
+        .filter(|candle| candle.interval == interval)
+        .collect()
+}
+
+#[test]
+fn aggregates_trades_in_chain_order() {
+    let trades = trades("pumpswap_double_buy");
+    assert_eq!(trades.len(), 2);
+    let (first, second) = (&trades[0], &trades[1]);
+
+    let mut in_order = CandleAggregator::new(vec![CandleInterval::OneMinute], LATENESS_SECS);
+    assert!(in_order.observe(first).is_empty());
+    assert!(in_order.observe(second).is_empty());
+    let mut reversed = CandleAggregator::new(vec![CandleInterval::OneMinute], LATENESS_SECS);
+    assert!(reversed.observe(second).is_empty());
+    assert!(reversed.observe(first).is_empty());
+
+    let candles = in_order.flush();
+    assert_eq!(candles, reversed.flush());
+    assert_eq!(candles.len(), 1);
+    let candle = &candles[0];
+    assert_eq!(candle.status, CandleStatus::Partial);
+    assert_eq!(candle.pool, first.pool);
+    assert_eq!(candle.open, first.execution_price.unwrap());
+    assert_eq!(candle.close, second.execution_price.unwrap());
+    assert_eq!(candle.low, candle.open);
+    assert_eq!(candle.high, candle.close);
+    assert_eq!(
+        candle.base_volume.value,
+        u128::from(first.base_amount.unwrap() + second.base_amount.unwrap())
+    );
+    assert_eq!(
+        candle.quote_volume.value,
+        u128::from(first.quote_amount.unwrap() + second.quote_amount.unwrap())
+    );
+    assert_eq!(candle.trades, 2);
+    assert_eq!(candle.close_time - candle.open_time, 60);
+}
+
+#[test]
+fn closes_candles_once_slot_time_passes_the_lateness() {
+    let trades = trades("pumpswap_buy");
+    let trade = &trades[0];
+    let mut candles = CandleAggregator::new(
+        vec![CandleInterval::OneSecond, CandleInterval::OneHour],
+        LATENESS_SECS,
+    );
+    candles.observe(trade);
+
+    // Still within the lateness of the first second: nothing is finished.
+    assert!(candles.observe(&later(trade, LATENESS_SECS)).is_empty());
+    let finished = candles.observe(&later(trade, LATENESS_SECS + 1));
+    assert_eq!(finished.len(), 1);
+    assert_eq!(finished[0].interval, CandleInterval::OneSecond);
+    assert_eq!(finished[0].status, CandleStatus::Final);
+    assert_eq!(finished[0].trades, 1);
+
+    // A trade for the finished second becomes a correction, sent with the next
+    // finished candles; the open hour still takes it.
+    assert!(candles.observe(trade).is_empty());
+    let flushed = candles.flush();
+    let second = of_interval(flushed.clone(), CandleInterval::OneSecond);
+    assert_eq!(second.len(), 1);
+    assert_eq!(second[0].status, CandleStatus::Correction);
+    assert_eq!(second[0].open_time, finished[0].open_time);
+    assert_eq!(second[0].trades, 1);
+    let hour = of_interval(flushed, CandleInterval::OneHour);
+    assert_eq!(hour.len(), 1);
+    assert_eq!(hour[0].status, CandleStatus::Partial);
+    assert_eq!(hour[0].trades, 4);
+}
+
+#[test]
+fn sends_corrections_with_the_next_finished_candles() {
+    let trades = trades("pumpswap_buy");
+    let trade = &trades[0];
+    let mut candles = CandleAggregator::new(vec![CandleInterval::OneSecond], LATENESS_SECS);
+    candles.observe(trade);
+    assert_eq!(candles.observe(&later(trade, LATENESS_SECS + 1)).len(), 1);
+
+    // Two late trades for the emitted second collect into one correction.
+    assert!(candles.observe(trade).is_empty());
+    assert!(candles.observe(trade).is_empty());
+    let finished = candles.observe(&later(trade, 2 * LATENESS_SECS + 2));
+    assert_eq!(finished.len(), 2);
+    assert_eq!(finished[0].status, CandleStatus::Final);
+    assert_eq!(finished[1].status, CandleStatus::Correction);
+    assert_eq!(finished[1].trades, 2);
+    assert_eq!(finished[1].first_slot, trade.transaction.slot);
+    assert!(candles
+        .flush()
+        .iter()
+        .all(|candle| candle.status == CandleStatus::Partial));
+}
+
+#[test]
+fn block_metas_close_candles_without_trades() {
+    let mut processor = PumpSwapProcessor::new().with_candles(CandleAggregator::new(
+        vec![CandleInterval::OneMinute],
+        LATENESS_SECS,
+    ));
+    let mut events = Vec::new();
+    for update in common::load_fixture("pumpswap_buy") {
+        events.extend(processor.handle_update(update));
+    }
+    let trade = match &events[..] {
+        [PumpSwapEvent::Trade(trade)] => trade.clone(),
+        other => panic!("expected one trade, got {other:?}"),
+    };
+
+    let timestamp = trade.event.as_ref().unwrap().timestamp;
+    let meta = common::BlockMetaFixture {
+        slot: trade.transaction.slot + 500,
+        block_time: timestamp + 60 + LATENESS_SECS,
+    };
+    let finished = processor.handle_update(meta.into_update(common::FILTER_NAME));
+    match &finished[..] {
+        [PumpSwapEvent::Candle(candle)] => {
+            assert_eq!(candle.pool, trade.pool);
+            assert_eq!(candle.first_slot, trade.transaction.slot);
+        }
+        other => panic!("expected one candle, got {other:?}"),
+    }
+    assert!(processor.flush_candles().is_empty());
+}
@@ -0,0 +1,51 @@
+//! Golden-output harness shared by the integration tests: fixtures are read from
+//! `tests/fixtures` and compared with `tests/golden` by `stream_common::fixtures`.
+
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+
+use pumpswap_indexing::pumpswap::{PumpSwapEvent, PumpSwapProcessor, PumpSwapTrade};
+use serde::Serialize;
+use stream_common::fixtures;
+use yellowstone_grpc_proto::prelude::SubscribeUpdate;
+
+pub use stream_common::fixtures::BlockMetaFixture;
+
+/// Filter name the ingestor routes this indexer's updates under.
+pub const FILTER_NAME: &str = "pumpswap";
+
+fn tests_dir(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join(name)
+}
+
+/// Loads `tests/fixtures/<name>.json`, `.bin` or `.bin.zst` as a list of updates.
+pub fn load_fixture(name: &str) -> Vec<SubscribeUpdate> {
+    fixtures::load_fixture(&tests_dir("fixtures"), name, FILTER_NAME)
+}
+
+/// Compares `events` with `tests/golden/<name>.json`, or rewrites it when
+/// `UPDATE_GOLDEN` is set.
+pub fn assert_golden<E: Serialize>(name: &str, events: &[E]) {
+    fixtures::assert_golden(&tests_dir("golden"), name, events);
+}
+
+/// Feeds a fixture through `processor` and returns every event it produced.
+pub fn run(processor: &mut PumpSwapProcessor, fixture: &str) -> Vec<PumpSwapEvent> {
+    load_fixture(fixture)
+        .into_iter()
+        .flat_map(|update| processor.handle_update(update))
+        .collect()
+}
+
+pub fn trades(events: &[PumpSwapEvent]) -> Vec<&PumpSwapTrade> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            PumpSwapEvent::Trade(trade) => Some(trade),
+            _ => None,
+        })
+        .collect()
+}
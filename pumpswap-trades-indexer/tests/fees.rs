@@ -0,0 +1,116 @@
+mod common;
+
+use std::path::PathBuf;
+
+use pumpswap_indexing::fees::{FeeAggregator, FeeConfigs, FeeScope, FeeSource, FeeSummary};
+use pumpswap_indexing::pumpswap::{PumpSwapEvent, PumpSwapProcessor};
+
+const POOL_A: &str = "FDBPpiwYnoURjzbpdy3eCut6sJcEkr2VYuoLYbuyZyzJ";
+const CREATOR_A: &str = "HaAcz1C6ZxkD5xU2TFRNGwmky15UvfyNnKT2FguU36bZ";
+const GLOBAL_CONFIG: &str = "7D3uTA7xd3yfgAcS6RHCSd4tnTXRnGgbVSvjpcmiQrAF";
+
+#[test]
+fn estimates_fees_from_transfers_and_the_global_config() {
+    let mut processor = PumpSwapProcessor::new();
+    let events = common::run(&mut processor, "pumpswap_fees");
+    assert!(processor.fee_configs().get(GLOBAL_CONFIG).is_some());
+
+    let trades = common::trades(&events);
+    let (legacy, evented) = (trades[0], trades[2]);
+    assert_eq!(legacy.fees.source, FeeSource::Transfers);
+    assert_eq!(evented.fees.source, FeeSource::Event);
+    // The same buy, with and without its event.
+    assert_eq!(legacy.fees.lp_fee, evented.fees.lp_fee);
+    assert_eq!(legacy.fees.protocol_fee, evented.fees.protocol_fee);
+    assert_eq!(legacy.fees.coin_creator_fee, evented.fees.coin_creator_fee);
+    // Without an event the creator comes from the pool account.
+    assert_eq!(legacy.fees.coin_creator.as_deref(), Some(CREATOR_A));
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("pumpswap-{}-{}.json", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+#[test]
+fn saves_and_reloads_fee_configs() {
+    let path = temp_path("fee-configs");
+    let mut processor =
+        PumpSwapProcessor::new().with_fee_configs(FeeConfigs::load(Some(path.clone())));
+    let expected = common::run(&mut processor, "pumpswap_fees");
+    processor.fee_configs_mut().save().unwrap();
+
+    let reloaded = FeeConfigs::load(Some(path.clone()));
+    assert_eq!(reloaded.len(), 1);
+    assert_eq!(
+        reloaded.get(GLOBAL_CONFIG),
+        processor.fee_configs().get(GLOBAL_CONFIG)
+    );
+
+    // After a restart the config account is not sent again, yet the trade
+    // without an event still gets its LP fee.
+    let mut restarted = PumpSwapProcessor::new().with_fee_configs(reloaded);
+    let mut updates = common::load_fixture("pumpswap_fees");
+    updates.remove(0);
+    let events: Vec<_> = updates
+        .into_iter()
+        .flat_map(|update| restarted.handle_update(update))
+        .collect();
+    assert_eq!(
+        common::trades(&events)[0].fees,
+        common::trades(&expected)[0].fees
+    );
+
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn flags_rates_that_differ_from_the_global_config() {
+    let mut processor = PumpSwapProcessor::new();
+    // Only `create_config`, which set a 25 bps LP fee; the buy was charged 20.
+    let create_config = common::load_fixture("pumpswap_config").remove(0);
+    processor.handle_update(create_config);
+
+    let events = common::run(&mut processor, "pumpswap_buy");
+    assert_eq!(
+        common::trades(&events)[0].mismatches,
+        vec!["lp_fee_basis_points"]
+    );
+}
+
+#[test]
+fn totals_fees_per_pool_and_creator_by_day() {
+    let mut processor = PumpSwapProcessor::new().with_fee_summaries(FeeAggregator::new(30));
+    common::run(&mut processor, "pumpswap_fees");
+
+    let summaries: Vec<FeeSummary> = processor
+        .flush_fee_summaries()
+        .into_iter()
+        .filter_map(|event| match event {
+            PumpSwapEvent::Fees(summary) => Some(summary),
+            _ => None,
+        })
+        .collect();
+    // Pools A and B, and A's creator; B's legacy sell names no creator.
+    assert_eq!(summaries.len(), 3);
+
+    let summary = |scope: FeeScope, address: &str| {
+        summaries
+            .iter()
+            .find(|summary| summary.scope == scope && summary.address == address)
+            .unwrap_or_else(|| panic!("no {scope:?} summary for {address}"))
+    };
+    let pool = summary(FeeScope::Pool, POOL_A);
+    assert_eq!(pool.trades, 3);
+    assert_eq!(pool.estimated_trades, 1);
+    assert_eq!(pool.lp_fee.to_string(), "0.000602009");
+    assert_eq!(pool.protocol_fee.to_string(), "0.000150504");
+    assert_eq!(pool.coin_creator_fee.to_string(), "0.000150504");
+    assert_eq!(pool.day_end - pool.day_start, 86_400);
+    assert_eq!(pool.day_start % 86_400, 0);
+
+    let creator = summary(FeeScope::CoinCreator, CREATOR_A);
+    assert_eq!(creator.trades, pool.trades);
+    assert_eq!(creator.coin_creator_fee, pool.coin_creator_fee);
+}
@@ -0,0 +1,32 @@
+mod common;
+
+use pumpswap_indexing::pumpswap::PumpSwapProcessor;
+
+fn run(name: &str) {
+    let events = common::run(&mut PumpSwapProcessor::new(), name);
+    common::assert_golden(name, &events);
+}
+
+macro_rules! golden {
+    ($($name:ident),* $(,)?) => {
+        $(
+            #[test]
+            fn $name() {
+                run(stringify!($name));
+            }
+        )*
+    };
+}
+
+golden!(
+    pumpswap_buy,
+    pumpswap_config,
+    pumpswap_create_pool,
+    pumpswap_double_buy,
+    pumpswap_fees,
+    pumpswap_liquidity,
+    pumpswap_routed_buy,
+    pumpswap_sell,
+    pumpswap_sol_usd,
+    pumpswap_usdc_pool,
+);
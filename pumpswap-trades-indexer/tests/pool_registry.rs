@@ -0,0 +1,79 @@
+mod common;
+
+use std::path::PathBuf;
+
+use pumpswap_indexing::pool::{PoolRegistry, PoolSource};
+use pumpswap_indexing::pumpswap::PumpSwapProcessor;
+
+const CREATED_POOL: &str = "FCHHZQfA9JSG5hoh5sHhVrvL91jF4S5oJvBSCwpF1QN3";
+const ACCOUNT_POOL: &str = "49p4fXdmWXojsMmxG271ywRCQUAhpNNJaENcGmMmRznt";
+
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("pumpswap-{}-{}.json", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+#[test]
+fn backfills_pools_from_create_pool() {
+    let mut processor = PumpSwapProcessor::new();
+    common::run(&mut processor, "pumpswap_create_pool");
+
+    let pool = processor
+        .pools()
+        .get(CREATED_POOL)
+        .expect("pool backfilled");
+    assert_eq!(pool.source, PoolSource::CreatePool);
+    assert_eq!(pool.index, 0);
+    assert_eq!(pool.pool_bump, None);
+    assert_eq!(pool.lp_supply, None);
+    assert_eq!(pool.coin_creator.as_deref(), Some(pool.creator.as_str()));
+}
+
+#[test]
+fn decodes_pool_accounts_and_keeps_the_newest() {
+    let mut processor = PumpSwapProcessor::new();
+    common::run(&mut processor, "pumpswap_usdc_pool");
+
+    let pool = processor
+        .pools()
+        .get(ACCOUNT_POOL)
+        .expect("pool decoded")
+        .clone();
+    assert_eq!(pool.source, PoolSource::Account);
+    assert_eq!(pool.pool_bump, Some(254));
+    assert_eq!(pool.lp_supply, Some(4_472_135_954));
+    assert_eq!(
+        pool.quote_mint,
+        "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"
+    );
+
+    let mut stale = pool.clone();
+    stale.slot -= 1;
+    stale.lp_supply = Some(1);
+    processor.pools_mut().observe_account(stale);
+    let mut backfilled = pool.clone();
+    backfilled.source = PoolSource::CreatePool;
+    backfilled.lp_supply = None;
+    processor.pools_mut().backfill(backfilled);
+
+    assert_eq!(processor.pools().get(ACCOUNT_POOL), Some(&pool));
+}
+
+#[test]
+fn saves_and_reloads_the_registry() {
+    let path = temp_path("pools");
+    let mut processor =
+        PumpSwapProcessor::new().with_pool_registry(PoolRegistry::load(Some(path.clone())));
+    common::run(&mut processor, "pumpswap_create_pool");
+    common::run(&mut processor, "pumpswap_usdc_pool");
+    processor.pools_mut().save().unwrap();
+
+    let reloaded = PoolRegistry::load(Some(path.clone()));
+    assert_eq!(reloaded.len(), 2);
+    for address in [CREATED_POOL, ACCOUNT_POOL] {
+        assert_eq!(reloaded.get(address), processor.pools().get(address));
+    }
+
+    let _ = std::fs::remove_file(path);
+}
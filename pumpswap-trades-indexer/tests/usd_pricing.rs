@@ -0,0 +1,141 @@
+mod common;
+
+use std::path::PathBuf;
+
+use pumpswap_indexing::candle::{CandleAggregator, CandleInterval};
+use pumpswap_indexing::pumpswap::{PumpSwapEvent, PumpSwapProcessor, PumpSwapTrade};
+use pumpswap_indexing::usd::UsdPricer;
+
+/// WSOL/USDC pool the fixture's first trade is on.
+const REFERENCE_POOL: &str = "2rLnEzxejTsmjALJDk4nztP6uPWBHKkSyDZyeLWfyHCG";
+const MEMECOIN_POOL: &str = "FDBPpiwYnoURjzbpdy3eCut6sJcEkr2VYuoLYbuyZyzJ";
+const MEMECOIN_MINT: &str = "5kJCfm13XnH3oUZEVuqQ2V9QXZF7sveC7YvzPWfR9yE9";
+
+fn trade<'a>(events: &'a [PumpSwapEvent], pool: &str) -> &'a PumpSwapTrade {
+    events
+        .iter()
+        .find_map(|event| match event {
+            PumpSwapEvent::Trade(trade) if trade.pool == pool => Some(trade),
+            _ => None,
+        })
+        .expect("trade on pool")
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("pumpswap-{}-{}.json", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+fn text<T: ToString>(value: Option<T>) -> Option<String> {
+    value.map(|value| value.to_string())
+}
+
+#[test]
+fn prices_sol_quoted_trades_from_the_reference_pool() {
+    let mut processor = PumpSwapProcessor::new().with_usd_pricer(UsdPricer::new(REFERENCE_POOL));
+    // Trading the memecoin first makes its supply wanted before the mint write.
+    common::run(&mut processor, "pumpswap_buy");
+    let events = common::run(&mut processor, "pumpswap_sol_usd");
+
+    // The reference trade is quoted in the stablecoin itself.
+    let reference = trade(&events, REFERENCE_POOL);
+    assert_eq!(
+        text(reference.sol_usd).as_deref(),
+        Some("150.300751203203203203")
+    );
+    assert_eq!(reference.price_usd, reference.execution_price);
+    assert_eq!(text(reference.volume_usd).as_deref(), Some("150.600604"));
+    assert_eq!(reference.market_cap_usd, None);
+
+    let memecoin = trade(&events, MEMECOIN_POOL);
+    assert_eq!(memecoin.sol_usd, reference.sol_usd);
+    assert_eq!(
+        text(memecoin.price_usd).as_deref(),
+        Some("0.000015105376397876")
+    );
+    assert_eq!(text(memecoin.volume_usd).as_deref(), Some("15.105376"));
+    // One billion tokens, from the mint account write.
+    assert_eq!(
+        text(memecoin.market_cap_usd).as_deref(),
+        Some("15105.376397")
+    );
+}
+
+#[test]
+fn leaves_usd_fields_empty_without_a_reference() {
+    let mut processor = PumpSwapProcessor::new().with_usd_pricer(UsdPricer::new(REFERENCE_POOL));
+    let events = common::run(&mut processor, "pumpswap_buy");
+
+    let memecoin = trade(&events, MEMECOIN_POOL);
+    assert_eq!(memecoin.sol_usd, None);
+    assert_eq!(memecoin.price_usd, None);
+    assert_eq!(memecoin.volume_usd, None);
+    assert_eq!(memecoin.market_cap_usd, None);
+}
+
+#[test]
+fn carries_usd_figures_into_candles() {
+    let mut processor = PumpSwapProcessor::new()
+        .with_usd_pricer(UsdPricer::new(REFERENCE_POOL))
+        .with_candles(CandleAggregator::new(vec![CandleInterval::OneHour], 30));
+    let events = common::run(&mut processor, "pumpswap_sol_usd");
+    let memecoin = trade(&events, MEMECOIN_POOL).clone();
+
+    let candles = processor.flush_candles();
+    let candle = candles
+        .iter()
+        .find_map(|event| match event {
+            PumpSwapEvent::Candle(candle) if candle.pool == MEMECOIN_POOL => Some(candle),
+            _ => None,
+        })
+        .expect("candle for the memecoin pool");
+    assert_eq!(candle.open_usd, memecoin.price_usd);
+    assert_eq!(candle.high_usd, memecoin.price_usd);
+    assert_eq!(candle.low_usd, memecoin.price_usd);
+    assert_eq!(candle.close_usd, memecoin.price_usd);
+    assert_eq!(candle.volume_usd, memecoin.volume_usd);
+    assert_eq!(candle.market_cap_usd, memecoin.market_cap_usd);
+}
+
+#[test]
+fn ignores_mint_writes_until_the_mint_is_traded_or_pooled() {
+    let mut processor = PumpSwapProcessor::new().with_usd_pricer(UsdPricer::new(REFERENCE_POOL));
+    let events = common::run(&mut processor, "pumpswap_sol_usd");
+
+    let memecoin = trade(&events, MEMECOIN_POOL);
+    assert!(memecoin.price_usd.is_some());
+    assert_eq!(memecoin.market_cap_usd, None);
+    let usd = processor.usd_pricer().expect("pricer");
+    assert_eq!(usd.supply(MEMECOIN_MINT), None);
+    assert!(usd.wants_supply(MEMECOIN_MINT));
+}
+
+#[test]
+fn saves_and_reloads_the_reference_and_supplies() {
+    let path = temp_path("usd");
+    let mut processor = PumpSwapProcessor::new()
+        .with_usd_pricer(UsdPricer::load(REFERENCE_POOL, Some(path.clone())));
+    common::run(&mut processor, "pumpswap_buy");
+    let events = common::run(&mut processor, "pumpswap_sol_usd");
+    let sol_usd = trade(&events, REFERENCE_POOL).sol_usd;
+    processor.usd_pricer_mut().unwrap().save().unwrap();
+
+    // Without a reference trade after the restart, the saved price is used.
+    let mut restarted = PumpSwapProcessor::new()
+        .with_usd_pricer(UsdPricer::load(REFERENCE_POOL, Some(path.clone())));
+    let events = common::run(&mut restarted, "pumpswap_buy");
+    let memecoin = trade(&events, MEMECOIN_POOL);
+    assert_eq!(memecoin.sol_usd, sol_usd);
+    assert!(memecoin.market_cap_usd.is_some());
+
+    // A reference price saved for another pool is dropped; supplies are kept.
+    let other = UsdPricer::load(MEMECOIN_POOL, Some(path.clone()));
+    assert_eq!(other.sol_usd(), None);
+    assert_eq!(
+        text(other.supply(MEMECOIN_MINT)).as_deref(),
+        Some("1000000000.000000")
+    );
+
+    let _ = std::fs::remove_file(path);
+}
@@ -0,0 +1,90 @@
+use std::fmt;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// An exact fixed-point number: `value / 10^decimals`.
+///
+/// Token amounts are carried as the raw integer from the chain plus the mint's
+/// decimals, so nothing is lost to `f64` rounding. `Display` (and `Serialize`)
+/// render every fractional digit, e.g. `1500000` with 6 decimals is `1.500000`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FixedDecimal {
+    pub value: u128,
+    pub decimals: u8,
+}
+
+impl FixedDecimal {
+    pub fn new(value: impl Into<u128>, decimals: u8) -> Self {
+        Self {
+            value: value.into(),
+            decimals,
+        }
+    }
+
+    /// Parses a raw integer amount string such as `UiTokenAmount.amount`.
+    pub fn from_raw_str(amount: &str, decimals: u8) -> Option<Self> {
+        amount
+            .parse::<u128>()
+            .ok()
+            .map(|value| Self::new(value, decimals))
+    }
+
+    /// Parses the `Display` form back, keeping as many decimals as it has digits
+    /// after the point.
+    pub fn parse(text: &str) -> Option<Self> {
+        let (integer, fraction) = text.split_once('.').unwrap_or((text, ""));
+        if integer.is_empty() || !text.bytes().all(|b| b.is_ascii_digit() || b == b'.') {
+            return None;
+        }
+        let decimals = u8::try_from(fraction.len()).ok()?;
+        Self::from_raw_str(&format!("{integer}{fraction}"), decimals)
+    }
+
+    /// `numerator / denominator` truncated to `decimals` fractional digits, by long
+    /// division so intermediate values never exceed `denominator * 10`.
+    pub fn ratio(numerator: u128, denominator: u128, decimals: u8) -> Option<Self> {
+        if denominator == 0 {
+            return None;
+        }
+
+        let mut value = numerator / denominator;
+        let mut remainder = numerator % denominator;
+        for _ in 0..decimals {
+            remainder = remainder.checked_mul(10)?;
+            value = value
+                .checked_mul(10)?
+                .checked_add(remainder / denominator)?;
+            remainder %= denominator;
+        }
+
+        Some(Self::new(value, decimals))
+    }
+}
+
+impl fmt::Display for FixedDecimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = self.value.to_string();
+        let decimals = usize::from(self.decimals);
+        if decimals == 0 {
+            return f.write_str(&digits);
+        }
+
+        let digits = format!("{digits:0>width$}", width = decimals + 1);
+        let (integer, fraction) = digits.split_at(digits.len() - decimals);
+        write!(f, "{integer}.{fraction}")
+    }
+}
+
+impl Serialize for FixedDecimal {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for FixedDecimal {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        Self::parse(&text)
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid decimal {text:?}")))
+    }
+}
@@ -0,0 +1,107 @@
+//! CPI nesting of one top-level instruction, rebuilt from `stack_height`.
+//!
+//! Inner instructions are recorded flat, in execution order, with the invocation
+//! depth of each: the top-level instruction is height 1 and a CPI it makes is 2. An
+//! instruction's caller is the closest earlier instruction one level shallower.
+//! Nodes carry the program id in whatever form the caller keys programs by, e.g. a
+//! `Pubkey` or a base58 string.
+
+/// Stack height of a top-level instruction.
+pub const TOP_LEVEL_STACK_HEIGHT: u32 = 1;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CallNode<K> {
+    pub program_id: K,
+    /// `None` for the top-level instruction.
+    pub inner_index: Option<u32>,
+    pub stack_height: u32,
+    /// Index into [`CallTree::nodes`] of the invoking instruction.
+    pub parent: Option<usize>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CallTree<K> {
+    nodes: Vec<CallNode<K>>,
+}
+
+impl<K> CallTree<K> {
+    /// Builds the tree for a top-level instruction from its inner instructions'
+    /// `(program_id, stack_height)`. Transactions recorded before `stack_height`
+    /// existed have none; those inner instructions are treated as direct CPIs.
+    pub fn build(
+        program_id: K,
+        inner_instructions: impl IntoIterator<Item = (K, Option<u32>)>,
+    ) -> Self {
+        let mut nodes = vec![CallNode {
+            program_id,
+            inner_index: None,
+            stack_height: TOP_LEVEL_STACK_HEIGHT,
+            parent: None,
+        }];
+        // Nodes on the path from the root to the last instruction seen.
+        let mut path = vec![0];
+
+        for (inner_index, (program_id, stack_height)) in inner_instructions.into_iter().enumerate()
+        {
+            let stack_height = stack_height
+                .unwrap_or(TOP_LEVEL_STACK_HEIGHT + 1)
+                .max(TOP_LEVEL_STACK_HEIGHT + 1);
+            while let Some(&last) = path.last() {
+                if last == 0 || nodes[last].stack_height < stack_height {
+                    break;
+                }
+                path.pop();
+            }
+            nodes.push(CallNode {
+                program_id,
+                inner_index: Some(inner_index as u32),
+                stack_height,
+                parent: path.last().copied(),
+            });
+            path.push(nodes.len() - 1);
+        }
+
+        Self { nodes }
+    }
+
+    /// The top-level instruction followed by its inner instructions, in execution
+    /// order.
+    pub fn nodes(&self) -> &[CallNode<K>] {
+        &self.nodes
+    }
+
+    /// The node for an inner instruction, or the root for `None`.
+    pub fn node(&self, inner_index: Option<u32>) -> Option<&CallNode<K>> {
+        self.nodes.get(Self::position(inner_index))
+    }
+
+    /// Instructions invoked directly by an instruction (the root for `None`), in
+    /// execution order.
+    pub fn children(&self, inner_index: Option<u32>) -> Vec<&CallNode<K>> {
+        let parent = Self::position(inner_index);
+        self.nodes
+            .iter()
+            .filter(|node| node.parent == Some(parent))
+            .collect()
+    }
+
+    /// Position in [`Self::nodes`]: the root comes first.
+    fn position(inner_index: Option<u32>) -> usize {
+        inner_index.map_or(0, |inner_index| inner_index as usize + 1)
+    }
+}
+
+impl<K: Clone> CallTree<K> {
+    /// Programs that led to an instruction, outermost first; empty for the
+    /// top-level instruction.
+    pub fn invoked_by(&self, inner_index: Option<u32>) -> Vec<K> {
+        let mut callers = Vec::new();
+        let mut parent = self.node(inner_index).and_then(|node| node.parent);
+        while let Some(index) = parent {
+            callers.push(self.nodes[index].program_id.clone());
+            parent = self.nodes[index].parent;
+        }
+        callers.reverse();
+        callers
+    }
+}
@@ -0,0 +1,364 @@
+//! Golden-output fixtures for the indexers' integration tests.
+//!
+//! A fixture is either hand-written (`<name>.json`: one [`TransactionFixture`], or
+//! a list of transactions, account writes and block metas) or a recording from the
+//! ingestor's record mode (`<name>.bin` / `.bin.zst`). The events an indexer
+//! produces for it are compared with a golden `<name>.json`. Run with
+//! `UPDATE_GOLDEN=1` to rewrite the expected files after an intentional output
+//! change.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use yellowstone_grpc_proto::prelude::{
+    CompiledInstruction, InnerInstruction, InnerInstructions, Message, MessageHeader,
+    SubscribeUpdate, SubscribeUpdateAccount, SubscribeUpdateAccountInfo, SubscribeUpdateBlockMeta,
+    SubscribeUpdateTransaction, SubscribeUpdateTransactionInfo, TokenBalance, Transaction,
+    TransactionError, TransactionStatusMeta, UiTokenAmount, UnixTimestamp,
+    subscribe_update::UpdateOneof,
+};
+
+use crate::recording::UpdateReader;
+
+#[derive(Debug, Deserialize)]
+pub struct TransactionFixture {
+    pub slot: u64,
+    #[serde(default)]
+    pub index: u64,
+    /// Base58 transaction signature.
+    pub signature: String,
+    #[serde(default)]
+    pub versioned: bool,
+    pub account_keys: Vec<String>,
+    #[serde(default)]
+    pub loaded_writable_addresses: Vec<String>,
+    #[serde(default)]
+    pub loaded_readonly_addresses: Vec<String>,
+    pub instructions: Vec<InstructionFixture>,
+    #[serde(default)]
+    pub inner_instructions: Vec<InnerInstructionsFixture>,
+    #[serde(default)]
+    pub pre_token_balances: Vec<TokenBalanceFixture>,
+    #[serde(default)]
+    pub post_token_balances: Vec<TokenBalanceFixture>,
+    #[serde(default)]
+    pub pre_balances: Vec<u64>,
+    #[serde(default)]
+    pub post_balances: Vec<u64>,
+    #[serde(default)]
+    pub fee: u64,
+    #[serde(default)]
+    pub compute_units_consumed: Option<u64>,
+    /// Hex of the bincode-encoded `TransactionError`, as carried in the protobuf.
+    #[serde(default)]
+    pub err: Option<String>,
+    #[serde(default)]
+    pub log_messages: Vec<String>,
+}
+
+/// An account write, e.g. a mint or pool whose state the processor caches.
+#[derive(Debug, Deserialize)]
+pub struct AccountFixture {
+    pub slot: u64,
+    pub pubkey: String,
+    pub owner: String,
+    #[serde(default)]
+    pub lamports: u64,
+    /// Hex-encoded account data.
+    pub data: String,
+}
+
+/// A block meta update, carrying the slot's block time.
+#[derive(Debug, Deserialize)]
+pub struct BlockMetaFixture {
+    pub slot: u64,
+    pub block_time: i64,
+}
+
+/// One update in a JSON fixture: `{"account": {...}}`, `{"block_meta": {...}}` or a
+/// transaction.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum FixtureEntry {
+    Account { account: AccountFixture },
+    BlockMeta { block_meta: BlockMetaFixture },
+    Transaction(Box<TransactionFixture>),
+}
+
+/// A JSON fixture file: a single transaction or a list of entries applied in order.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum Fixture {
+    Many(Vec<FixtureEntry>),
+    One(Box<TransactionFixture>),
+}
+
+impl Fixture {
+    /// The fixture's updates, tagged with the ingestor filter `filter`.
+    pub fn into_updates(self, filter: &str) -> Vec<SubscribeUpdate> {
+        match self {
+            Fixture::One(transaction) => vec![transaction.into_update(filter)],
+            Fixture::Many(entries) => entries
+                .into_iter()
+                .map(|entry| match entry {
+                    FixtureEntry::Account { account } => account.into_update(filter),
+                    FixtureEntry::BlockMeta { block_meta } => block_meta.into_update(filter),
+                    FixtureEntry::Transaction(transaction) => transaction.into_update(filter),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl AccountFixture {
+    pub fn into_update(self, filter: &str) -> SubscribeUpdate {
+        SubscribeUpdate {
+            filters: vec![filter.to_string()],
+            created_at: None,
+            update_oneof: Some(UpdateOneof::Account(SubscribeUpdateAccount {
+                account: Some(SubscribeUpdateAccountInfo {
+                    pubkey: decode_base58(&self.pubkey),
+                    lamports: self.lamports,
+                    owner: decode_base58(&self.owner),
+                    executable: false,
+                    rent_epoch: 0,
+                    data: decode_hex(&self.data),
+                    write_version: 0,
+                    txn_signature: None,
+                }),
+                slot: self.slot,
+                is_startup: false,
+            })),
+        }
+    }
+}
+
+impl BlockMetaFixture {
+    pub fn into_update(self, filter: &str) -> SubscribeUpdate {
+        SubscribeUpdate {
+            filters: vec![filter.to_string()],
+            created_at: None,
+            update_oneof: Some(UpdateOneof::BlockMeta(SubscribeUpdateBlockMeta {
+                slot: self.slot,
+                block_time: Some(UnixTimestamp {
+                    timestamp: self.block_time,
+                }),
+                ..Default::default()
+            })),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InstructionFixture {
+    pub program_id_index: u32,
+    pub accounts: Vec<u8>,
+    /// Hex-encoded instruction data.
+    pub data: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InnerInstructionsFixture {
+    pub index: u32,
+    pub instructions: Vec<InnerInstructionFixture>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InnerInstructionFixture {
+    pub program_id_index: u32,
+    pub accounts: Vec<u8>,
+    pub data: String,
+    #[serde(default)]
+    pub stack_height: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TokenBalanceFixture {
+    pub account_index: u32,
+    pub mint: String,
+    #[serde(default)]
+    pub owner: String,
+    #[serde(default)]
+    pub program_id: String,
+    /// Raw amount as a decimal string, like `UiTokenAmount.amount`.
+    pub amount: String,
+    pub decimals: u32,
+}
+
+impl TransactionFixture {
+    pub fn into_update(self, filter: &str) -> SubscribeUpdate {
+        let signature = decode_base58(&self.signature);
+        let message = Message {
+            header: Some(MessageHeader {
+                num_required_signatures: 1,
+                num_readonly_signed_accounts: 0,
+                num_readonly_unsigned_accounts: 0,
+            }),
+            account_keys: self
+                .account_keys
+                .iter()
+                .map(|key| decode_base58(key))
+                .collect(),
+            recent_blockhash: vec![0u8; 32],
+            instructions: self
+                .instructions
+                .iter()
+                .map(|ix| CompiledInstruction {
+                    program_id_index: ix.program_id_index,
+                    accounts: ix.accounts.clone(),
+                    data: decode_hex(&ix.data),
+                })
+                .collect(),
+            versioned: self.versioned,
+            address_table_lookups: Vec::new(),
+        };
+
+        let meta = TransactionStatusMeta {
+            err: self.err.as_ref().map(|err| TransactionError {
+                err: decode_hex(err),
+            }),
+            fee: self.fee,
+            pre_balances: self.pre_balances.clone(),
+            post_balances: self.post_balances.clone(),
+            inner_instructions: self
+                .inner_instructions
+                .iter()
+                .map(|inner| InnerInstructions {
+                    index: inner.index,
+                    instructions: inner
+                        .instructions
+                        .iter()
+                        .map(|ix| InnerInstruction {
+                            program_id_index: ix.program_id_index,
+                            accounts: ix.accounts.clone(),
+                            data: decode_hex(&ix.data),
+                            stack_height: ix.stack_height,
+                        })
+                        .collect(),
+                })
+                .collect(),
+            log_messages: self.log_messages.clone(),
+            pre_token_balances: self.pre_token_balances.iter().map(token_balance).collect(),
+            post_token_balances: self.post_token_balances.iter().map(token_balance).collect(),
+            loaded_writable_addresses: self
+                .loaded_writable_addresses
+                .iter()
+                .map(|key| decode_base58(key))
+                .collect(),
+            loaded_readonly_addresses: self
+                .loaded_readonly_addresses
+                .iter()
+                .map(|key| decode_base58(key))
+                .collect(),
+            compute_units_consumed: self.compute_units_consumed,
+            ..Default::default()
+        };
+
+        SubscribeUpdate {
+            filters: vec![filter.to_string()],
+            created_at: None,
+            update_oneof: Some(UpdateOneof::Transaction(SubscribeUpdateTransaction {
+                transaction: Some(SubscribeUpdateTransactionInfo {
+                    signature: signature.clone(),
+                    is_vote: false,
+                    transaction: Some(Transaction {
+                        signatures: vec![signature],
+                        message: Some(message),
+                    }),
+                    meta: Some(meta),
+                    index: self.index,
+                }),
+                slot: self.slot,
+            })),
+        }
+    }
+}
+
+fn token_balance(balance: &TokenBalanceFixture) -> TokenBalance {
+    let raw = balance
+        .amount
+        .parse::<u64>()
+        .expect("fixture amount must be a u64");
+    let ui_amount = raw as f64 / 10_f64.powi(balance.decimals as i32);
+
+    TokenBalance {
+        account_index: balance.account_index,
+        mint: balance.mint.clone(),
+        ui_token_amount: Some(UiTokenAmount {
+            ui_amount,
+            decimals: balance.decimals,
+            amount: balance.amount.clone(),
+            ui_amount_string: ui_amount.to_string(),
+        }),
+        owner: balance.owner.clone(),
+        program_id: balance.program_id.clone(),
+    }
+}
+
+/// Loads `<dir>/<name>.json`, `.bin` or `.bin.zst` as a list of updates, tagging
+/// JSON fixtures with the ingestor filter `filter`.
+pub fn load_fixture(dir: &Path, name: &str, filter: &str) -> Vec<SubscribeUpdate> {
+    let json = dir.join(format!("{name}.json"));
+    if json.exists() {
+        let contents = std::fs::read_to_string(&json).expect("read fixture");
+        let fixture: Fixture = serde_json::from_str(&contents)
+            .unwrap_or_else(|err| panic!("invalid fixture {}: {}", json.display(), err));
+        return fixture.into_updates(filter);
+    }
+
+    for extension in ["bin", "bin.zst"] {
+        let path = dir.join(format!("{name}.{extension}"));
+        if path.exists() {
+            return UpdateReader::open(&path)
+                .and_then(|reader| reader.collect())
+                .unwrap_or_else(|err| panic!("invalid recording {}: {}", path.display(), err));
+        }
+    }
+
+    panic!("no fixture named {name} in {}", dir.display());
+}
+
+/// Compares `events` with `<dir>/<name>.json`, or rewrites it when `UPDATE_GOLDEN`
+/// is set.
+pub fn assert_golden<E: Serialize>(dir: &Path, name: &str, events: &[E]) {
+    let path = dir.join(format!("{name}.json"));
+    let actual = serde_json::to_value(events).expect("events serialize");
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        let mut contents = serde_json::to_string_pretty(&actual).unwrap();
+        contents.push('\n');
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, contents).unwrap();
+        return;
+    }
+
+    let contents = std::fs::read_to_string(&path).unwrap_or_else(|_| {
+        panic!(
+            "missing {}; run with UPDATE_GOLDEN=1 to create it",
+            path.display()
+        )
+    });
+    let expected: Value = serde_json::from_str(&contents).expect("golden file is JSON");
+
+    assert_eq!(
+        actual,
+        expected,
+        "output for {name} differs from {}; run with UPDATE_GOLDEN=1 if the change is intended",
+        path.display()
+    );
+}
+
+fn decode_base58(value: &str) -> Vec<u8> {
+    bs58::decode(value)
+        .into_vec()
+        .unwrap_or_else(|err| panic!("invalid base58 {value}: {err}"))
+}
+
+fn decode_hex(value: &str) -> Vec<u8> {
+    assert!(value.len().is_multiple_of(2), "odd-length hex: {value}");
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&value[i..i + 2], 16).expect("invalid hex"))
+        .collect()
+}
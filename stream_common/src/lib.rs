@@ -0,0 +1,8 @@
+//! Code shared by the ingestor, the mock Geyser server and the indexers.
+
+pub mod amount;
+pub mod call_tree;
+#[cfg(feature = "fixtures")]
+pub mod fixtures;
+pub mod recording;
+pub mod shutdown;
@@ -0,0 +1,95 @@
+//! Recordings written by the ingestor's record mode: varint length-delimited
+//! `SubscribeUpdate`s, optionally zstd-compressed. Readers detect compression
+//! from the zstd frame magic.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, ErrorKind, Read};
+use std::path::Path;
+
+use prost::Message as _;
+use yellowstone_grpc_proto::prelude::SubscribeUpdate;
+
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+/// Largest update accepted from a recording. Geyser updates are far smaller, so a
+/// bigger length prefix means the file is corrupt.
+const MAX_UPDATE_LEN: u64 = 64 * 1024 * 1024;
+
+/// Loads a whole recording into memory.
+pub fn read_updates(path: &Path) -> std::io::Result<Vec<SubscribeUpdate>> {
+    UpdateReader::open(path)?.collect()
+}
+
+/// Streams the updates of a recording one at a time.
+pub struct UpdateReader {
+    reader: Box<dyn Read>,
+}
+
+impl UpdateReader {
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        let mut file = BufReader::new(File::open(path)?);
+        let reader: Box<dyn Read> = if file.fill_buf()?.starts_with(&ZSTD_MAGIC) {
+            Box::new(zstd::Decoder::with_buffer(file)?)
+        } else {
+            Box::new(file)
+        };
+
+        Ok(Self { reader })
+    }
+
+    fn read_len(&mut self) -> std::io::Result<Option<u64>> {
+        let mut len: u64 = 0;
+        for shift in (0..64).step_by(7) {
+            let mut byte = [0u8; 1];
+            match self.reader.read_exact(&mut byte) {
+                Ok(()) => {}
+                Err(err) if err.kind() == ErrorKind::UnexpectedEof && shift == 0 => {
+                    return Ok(None);
+                }
+                Err(err) => return Err(err),
+            }
+
+            len |= u64::from(byte[0] & 0x7f) << shift;
+            if byte[0] & 0x80 == 0 {
+                return Ok(Some(len));
+            }
+        }
+
+        Err(std::io::Error::new(
+            ErrorKind::InvalidData,
+            "length prefix overflows u64",
+        ))
+    }
+}
+
+impl Iterator for UpdateReader {
+    type Item = std::io::Result<SubscribeUpdate>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let len = match self.read_len() {
+            Ok(Some(len)) => len,
+            Ok(None) => return None,
+            Err(err) => return Some(Err(err)),
+        };
+
+        if len > MAX_UPDATE_LEN {
+            return Some(Err(std::io::Error::new(
+                ErrorKind::InvalidData,
+                format!("update length {len} exceeds {MAX_UPDATE_LEN} bytes"),
+            )));
+        }
+
+        // Grows with the bytes actually read, so a truncated file fails on EOF
+        // rather than after allocating the claimed length.
+        let mut buf = Vec::new();
+        match (&mut self.reader).take(len).read_to_end(&mut buf) {
+            Ok(read) if read as u64 == len => {}
+            Ok(_) => return Some(Err(ErrorKind::UnexpectedEof.into())),
+            Err(err) => return Some(Err(err)),
+        }
+
+        Some(
+            SubscribeUpdate::decode(buf.as_slice())
+                .map_err(|err| std::io::Error::new(ErrorKind::InvalidData, err)),
+        )
+    }
+}
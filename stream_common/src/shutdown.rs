@@ -0,0 +1,55 @@
+use std::time::Duration;
+
+use tokio::sync::watch;
+
+const DEFAULT_SHUTDOWN_TIMEOUT_SECS: u64 = 10;
+
+/// Spawns a task that flips the returned receiver to `true` on SIGINT/SIGTERM.
+pub fn listen_for_signals() -> watch::Receiver<bool> {
+    let (tx, rx) = watch::channel(false);
+
+    tokio::spawn(async move {
+        wait_for_signal().await;
+        println!("Shutdown signal received, draining...");
+        let _ = tx.send(true);
+    });
+
+    rx
+}
+
+pub fn timeout_from_env() -> Duration {
+    let secs = std::env::var("SHUTDOWN_TIMEOUT_SECS")
+        .ok()
+        .and_then(|value| value.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT_SECS);
+    Duration::from_secs(secs)
+}
+
+/// Resolves once shutdown has been requested (or the signal task is gone).
+pub async fn requested(shutdown: &mut watch::Receiver<bool>) {
+    let _ = shutdown.wait_for(|stop| *stop).await;
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut sigterm = match signal(SignalKind::terminate()) {
+        Ok(sigterm) => sigterm,
+        Err(err) => {
+            eprintln!("Failed to install SIGTERM handler: {}", err);
+            let _ = tokio::signal::ctrl_c().await;
+            return;
+        }
+    };
+
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = sigterm.recv() => {}
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_signal() {
+    let _ = tokio::signal::ctrl_c().await;
+}
@@ -0,0 +1,74 @@
+//! Associated Token Account program.
+//!
+//! Instruction data is a single discriminant byte; an empty payload is the original
+//! `Create`.
+
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::event::{AssociatedAccount, EventContext, RecoverNested, TokenEvent};
+use crate::token::InstructionAccounts;
+
+pub(crate) const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey =
+    pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+
+const CREATE: u8 = 0;
+const CREATE_IDEMPOTENT: u8 = 1;
+const RECOVER_NESTED: u8 = 2;
+
+pub(crate) fn decode(
+    keys: &InstructionAccounts,
+    data: &[u8],
+    context: EventContext,
+) -> Option<TokenEvent> {
+    match data.first().copied().unwrap_or(CREATE) {
+        // [payer, associated_account, wallet, mint, system_program, token_program]
+        CREATE => create(keys, context).map(TokenEvent::CreateAssociatedAccount),
+        CREATE_IDEMPOTENT => {
+            create(keys, context).map(TokenEvent::CreateAssociatedAccountIdempotent)
+        }
+        // [nested_account, nested_mint, destination, owner_account, owner_mint, wallet,
+        //  token_program]
+        RECOVER_NESTED => Some(TokenEvent::RecoverNested(RecoverNested {
+            context,
+            nested_account: keys.key(0)?,
+            nested_mint: keys.key(1)?,
+            destination: keys.key(2)?,
+            owner_account: keys.key(3)?,
+            owner_mint: keys.key(4)?,
+            wallet: keys.key(5)?,
+            token_program: keys.key(6)?,
+        })),
+        _ => None,
+    }
+}
+
+fn create(keys: &InstructionAccounts, context: EventContext) -> Option<AssociatedAccount> {
+    Some(AssociatedAccount {
+        context,
+        payer: keys.key(0)?,
+        account: keys.key(1)?,
+        owner: keys.key(2)?,
+        mint: keys.key(3)?,
+        token_program: keys.key(5)?,
+        created: true,
+    })
+}
+
+/// `CreateIdempotent` succeeds without doing anything when the account exists, so it
+/// only created one if the account had no lamports before the transaction.
+pub(crate) fn mark_created(
+    events: &mut [TokenEvent],
+    account_keys: &[Pubkey],
+    pre_balances: &[u64],
+) {
+    for event in events {
+        if let TokenEvent::CreateAssociatedAccountIdempotent(create) = event {
+            let pre_lamports = account_keys
+                .iter()
+                .position(|key| *key == create.account)
+                .and_then(|index| pre_balances.get(index));
+            create.created = pre_lamports.copied().unwrap_or(0) == 0;
+        }
+    }
+}
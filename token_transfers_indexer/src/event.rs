@@ -0,0 +1,766 @@
+use std::fmt::Display;
+
+use serde::{Serialize, Serializer};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+
+use crate::amount::FixedDecimal;
+
+/// A decoded SPL Token / Token-2022 instruction.
+///
+/// Serialized as a flat JSON object tagged with `type`; pubkeys and signatures are
+/// base58 strings and amounts are raw base units (scale by `decimals` for display).
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TokenEvent {
+    Transfer(Transfer),
+    TransferChecked(Transfer),
+    MintTo(MintTo),
+    MintToChecked(MintTo),
+    Burn(Burn),
+    BurnChecked(Burn),
+    Approve(Approve),
+    ApproveChecked(Approve),
+    Revoke(Revoke),
+    SetAuthority(SetAuthority),
+    CloseAccount(CloseAccount),
+    FreezeAccount(AccountFreeze),
+    ThawAccount(AccountFreeze),
+    InitializeMint(InitializeMint),
+    InitializeMint2(InitializeMint),
+    InitializeAccount(InitializeAccount),
+    InitializeAccount2(InitializeAccount),
+    InitializeAccount3(InitializeAccount),
+    SyncNative(SyncNative),
+    AmountToUiAmount(AmountToUiAmount),
+    TransferCheckedWithFee(Transfer),
+    InitializeTransferFeeConfig(TransferFeeConfig),
+    SetTransferFee(SetTransferFee),
+    WithdrawWithheldTokensFromMint(WithdrawWithheld),
+    WithdrawWithheldTokensFromAccounts(WithdrawWithheld),
+    HarvestWithheldTokensToMint(HarvestWithheld),
+    ConfidentialTransfer(ConfidentialTransfer),
+    InitializeInterestBearingMint(InterestRate),
+    UpdateInterestRate(InterestRate),
+    InitializeTransferHook(TransferHook),
+    UpdateTransferHook(TransferHook),
+    InitializeMetadataPointer(MetadataPointer),
+    UpdateMetadataPointer(MetadataPointer),
+    InitializeGroupPointer(GroupPointer),
+    UpdateGroupPointer(GroupPointer),
+    InitializeDefaultAccountState(DefaultAccountState),
+    UpdateDefaultAccountState(DefaultAccountState),
+    EnableCpiGuard(AccountToggle),
+    DisableCpiGuard(AccountToggle),
+    EnableRequiredMemoTransfers(AccountToggle),
+    DisableRequiredMemoTransfers(AccountToggle),
+    SystemTransfer(NativeTransfer),
+    SystemTransferWithSeed(NativeTransfer),
+    SystemCreateAccount(CreateAccount),
+    CreateAssociatedAccount(AssociatedAccount),
+    CreateAssociatedAccountIdempotent(AssociatedAccount),
+    RecoverNested(RecoverNested),
+    BalanceChange(BalanceChange),
+    LamportChange(LamportChange),
+}
+
+impl TokenEvent {
+    /// The instruction an event was decoded from; `None` for transaction-level
+    /// events such as `balance_change`.
+    pub fn context(&self) -> Option<&EventContext> {
+        match self {
+            TokenEvent::Transfer(event)
+            | TokenEvent::TransferChecked(event)
+            | TokenEvent::TransferCheckedWithFee(event) => Some(&event.context),
+            TokenEvent::MintTo(event) | TokenEvent::MintToChecked(event) => Some(&event.context),
+            TokenEvent::Burn(event) | TokenEvent::BurnChecked(event) => Some(&event.context),
+            TokenEvent::Approve(event) | TokenEvent::ApproveChecked(event) => Some(&event.context),
+            TokenEvent::Revoke(event) => Some(&event.context),
+            TokenEvent::SetAuthority(event) => Some(&event.context),
+            TokenEvent::CloseAccount(event) => Some(&event.context),
+            TokenEvent::FreezeAccount(event) | TokenEvent::ThawAccount(event) => {
+                Some(&event.context)
+            }
+            TokenEvent::InitializeMint(event) | TokenEvent::InitializeMint2(event) => {
+                Some(&event.context)
+            }
+            TokenEvent::InitializeAccount(event)
+            | TokenEvent::InitializeAccount2(event)
+            | TokenEvent::InitializeAccount3(event) => Some(&event.context),
+            TokenEvent::SyncNative(event) => Some(&event.context),
+            TokenEvent::AmountToUiAmount(event) => Some(&event.context),
+            TokenEvent::InitializeTransferFeeConfig(event) => Some(&event.context),
+            TokenEvent::SetTransferFee(event) => Some(&event.context),
+            TokenEvent::WithdrawWithheldTokensFromMint(event)
+            | TokenEvent::WithdrawWithheldTokensFromAccounts(event) => Some(&event.context),
+            TokenEvent::HarvestWithheldTokensToMint(event) => Some(&event.context),
+            TokenEvent::ConfidentialTransfer(event) => Some(&event.context),
+            TokenEvent::InitializeInterestBearingMint(event)
+            | TokenEvent::UpdateInterestRate(event) => Some(&event.context),
+            TokenEvent::InitializeTransferHook(event) | TokenEvent::UpdateTransferHook(event) => {
+                Some(&event.context)
+            }
+            TokenEvent::InitializeMetadataPointer(event)
+            | TokenEvent::UpdateMetadataPointer(event) => Some(&event.context),
+            TokenEvent::InitializeGroupPointer(event) | TokenEvent::UpdateGroupPointer(event) => {
+                Some(&event.context)
+            }
+            TokenEvent::InitializeDefaultAccountState(event)
+            | TokenEvent::UpdateDefaultAccountState(event) => Some(&event.context),
+            TokenEvent::EnableCpiGuard(event)
+            | TokenEvent::DisableCpiGuard(event)
+            | TokenEvent::EnableRequiredMemoTransfers(event)
+            | TokenEvent::DisableRequiredMemoTransfers(event) => Some(&event.context),
+            TokenEvent::SystemTransfer(event) | TokenEvent::SystemTransferWithSeed(event) => {
+                Some(&event.context)
+            }
+            TokenEvent::SystemCreateAccount(event) => Some(&event.context),
+            TokenEvent::CreateAssociatedAccount(event)
+            | TokenEvent::CreateAssociatedAccountIdempotent(event) => Some(&event.context),
+            TokenEvent::RecoverNested(event) => Some(&event.context),
+            TokenEvent::BalanceChange(_) | TokenEvent::LamportChange(_) => None,
+        }
+    }
+
+    /// The transfer carried by `transfer`, `transfer_checked` and
+    /// `transfer_checked_with_fee` events.
+    pub fn transfer(&self) -> Option<&Transfer> {
+        match self {
+            TokenEvent::Transfer(event)
+            | TokenEvent::TransferChecked(event)
+            | TokenEvent::TransferCheckedWithFee(event) => Some(event),
+            _ => None,
+        }
+    }
+
+    pub fn transfer_mut(&mut self) -> Option<&mut Transfer> {
+        match self {
+            TokenEvent::Transfer(event)
+            | TokenEvent::TransferChecked(event)
+            | TokenEvent::TransferCheckedWithFee(event) => Some(event),
+            _ => None,
+        }
+    }
+
+    /// The withdrawal carried by `withdraw_withheld_tokens_from_mint` and
+    /// `withdraw_withheld_tokens_from_accounts` events.
+    pub fn withdraw_withheld(&self) -> Option<&WithdrawWithheld> {
+        match self {
+            TokenEvent::WithdrawWithheldTokensFromMint(event)
+            | TokenEvent::WithdrawWithheldTokensFromAccounts(event) => Some(event),
+            _ => None,
+        }
+    }
+
+    pub fn withdraw_withheld_mut(&mut self) -> Option<&mut WithdrawWithheld> {
+        match self {
+            TokenEvent::WithdrawWithheldTokensFromMint(event)
+            | TokenEvent::WithdrawWithheldTokensFromAccounts(event) => Some(event),
+            _ => None,
+        }
+    }
+}
+
+/// The transaction an event belongs to, shared by every event it produces.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TransactionContext {
+    pub slot: u64,
+    #[serde(serialize_with = "display")]
+    pub signature: Signature,
+    /// Unix seconds, known only once the slot's block meta has been seen.
+    pub block_time: Option<i64>,
+    /// Position of the transaction within its slot.
+    pub transaction_index: u64,
+    #[serde(serialize_with = "display_option")]
+    pub fee_payer: Option<Pubkey>,
+    pub success: bool,
+    /// The `TransactionError`, e.g. `InstructionError(1, Custom(6001))`.
+    pub error: Option<String>,
+    pub compute_units_consumed: Option<u64>,
+    /// Lamports paid by the fee payer.
+    pub transaction_fee: u64,
+}
+
+/// Where an event came from: the transaction and the instruction inside it.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct EventContext {
+    #[serde(flatten)]
+    pub transaction: TransactionContext,
+    #[serde(serialize_with = "display")]
+    pub program_id: Pubkey,
+    /// Index of the top-level instruction (the parent, for inner instructions).
+    pub instruction_index: u32,
+    /// Position inside the parent's inner instructions, `None` for top-level.
+    pub inner_index: Option<u32>,
+    /// CPI depth: 1 for a top-level instruction, 2 for a program it invokes, ...
+    pub stack_height: u32,
+    /// Programs whose CPIs led to this instruction, outermost first, e.g. a router
+    /// then PumpSwap for a token transfer made by a routed swap.
+    #[serde(serialize_with = "display_vec")]
+    pub invoked_by: Vec<Pubkey>,
+    pub status: InstructionStatus,
+}
+
+/// How an instruction ended. Only instructions that ran produce events; those after
+/// the one a transaction failed in never execute.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InstructionStatus {
+    Success,
+    /// Completed, but the transaction failed later and rolled it back.
+    Reverted,
+    /// On the failing call path, or in a failed transaction whose logs do not say
+    /// which instructions completed.
+    Failed,
+}
+
+impl EventContext {
+    pub fn is_inner(&self) -> bool {
+        self.inner_index.is_some()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Transfer {
+    #[serde(flatten)]
+    pub context: EventContext,
+    #[serde(serialize_with = "display")]
+    pub source: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub destination: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub authority: Pubkey,
+    /// Always set for the checked variants; for `transfer` it comes from token balances.
+    #[serde(serialize_with = "display_option")]
+    pub mint: Option<Pubkey>,
+    /// Gross amount debited from `source`.
+    pub amount: u64,
+    pub decimals: Option<u8>,
+    /// `amount` scaled by `decimals`, when the decimals are known.
+    pub ui_amount: Option<FixedDecimal>,
+    /// Token-2022 transfer fee withheld in `destination`; `None` when it could not be
+    /// determined.
+    pub fee: Option<u64>,
+    /// `amount - fee`: what `destination` can actually spend.
+    pub net_amount: Option<u64>,
+    pub fee_source: Option<FeeSource>,
+    /// Wallets owning `source` / `destination`, from the transaction's token balances.
+    #[serde(serialize_with = "display_option")]
+    pub source_owner: Option<Pubkey>,
+    #[serde(serialize_with = "display_option")]
+    pub destination_owner: Option<Pubkey>,
+}
+
+/// How a transfer's fee was determined.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeeSource {
+    /// SPL Token has no transfer fees.
+    SplToken,
+    /// Stated by `TransferCheckedWithFee` (the program rejects a wrong value).
+    Instruction,
+    /// Computed from the mint's `TransferFeeConfig` for the transfer's epoch.
+    MintState,
+    /// Gross amount minus the destination's balance change.
+    BalanceDelta,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct MintTo {
+    #[serde(flatten)]
+    pub context: EventContext,
+    #[serde(serialize_with = "display")]
+    pub mint: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub destination: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub authority: Pubkey,
+    pub amount: u64,
+    pub decimals: Option<u8>,
+    /// `amount` scaled by `decimals`, when the decimals are known.
+    pub ui_amount: Option<FixedDecimal>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Burn {
+    #[serde(flatten)]
+    pub context: EventContext,
+    #[serde(serialize_with = "display")]
+    pub account: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub mint: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub authority: Pubkey,
+    pub amount: u64,
+    pub decimals: Option<u8>,
+    /// `amount` scaled by `decimals`, when the decimals are known.
+    pub ui_amount: Option<FixedDecimal>,
+}
+
+/// Grants `delegate` the right to move up to `amount` from `source`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Approve {
+    #[serde(flatten)]
+    pub context: EventContext,
+    #[serde(serialize_with = "display")]
+    pub source: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub delegate: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub owner: Pubkey,
+    /// Always set for `approve_checked`; for `approve` it comes from token balances.
+    #[serde(serialize_with = "display_option")]
+    pub mint: Option<Pubkey>,
+    pub amount: u64,
+    pub decimals: Option<u8>,
+    /// `amount` scaled by `decimals`, when the decimals are known.
+    pub ui_amount: Option<FixedDecimal>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Revoke {
+    #[serde(flatten)]
+    pub context: EventContext,
+    #[serde(serialize_with = "display")]
+    pub source: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub owner: Pubkey,
+}
+
+/// `account` is a mint or a token account depending on `authority_type`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SetAuthority {
+    #[serde(flatten)]
+    pub context: EventContext,
+    #[serde(serialize_with = "display")]
+    pub account: Pubkey,
+    pub authority_type: AuthorityType,
+    #[serde(serialize_with = "display")]
+    pub current_authority: Pubkey,
+    /// `None` removes the authority for good.
+    #[serde(serialize_with = "display_option")]
+    pub new_authority: Option<Pubkey>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct CloseAccount {
+    #[serde(flatten)]
+    pub context: EventContext,
+    #[serde(serialize_with = "display")]
+    pub account: Pubkey,
+    /// Receives the account's remaining lamports.
+    #[serde(serialize_with = "display")]
+    pub destination: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub owner: Pubkey,
+    /// Lamports moved to `destination`, tracked through the transaction's decoded
+    /// instructions; for wrapped SOL this is the unwrapped amount plus rent.
+    pub lamports: Option<u64>,
+    /// The account held wrapped SOL, so closing it unwraps.
+    pub native: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AccountFreeze {
+    #[serde(flatten)]
+    pub context: EventContext,
+    #[serde(serialize_with = "display")]
+    pub account: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub mint: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub freeze_authority: Pubkey,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct InitializeMint {
+    #[serde(flatten)]
+    pub context: EventContext,
+    #[serde(serialize_with = "display")]
+    pub mint: Pubkey,
+    pub decimals: u8,
+    #[serde(serialize_with = "display")]
+    pub mint_authority: Pubkey,
+    #[serde(serialize_with = "display_option")]
+    pub freeze_authority: Option<Pubkey>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct InitializeAccount {
+    #[serde(flatten)]
+    pub context: EventContext,
+    #[serde(serialize_with = "display")]
+    pub account: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub mint: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub owner: Pubkey,
+}
+
+/// Syncs a wrapped SOL account's token amount with its lamports.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SyncNative {
+    #[serde(flatten)]
+    pub context: EventContext,
+    #[serde(serialize_with = "display")]
+    pub account: Pubkey,
+    /// Lamports sent to the account by System Program transfers since it was
+    /// created or last synced in this transaction.
+    pub wrapped_lamports: Option<u64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AmountToUiAmount {
+    #[serde(flatten)]
+    pub context: EventContext,
+    #[serde(serialize_with = "display")]
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TransferFeeConfig {
+    #[serde(flatten)]
+    pub context: EventContext,
+    #[serde(serialize_with = "display")]
+    pub mint: Pubkey,
+    #[serde(serialize_with = "display_option")]
+    pub transfer_fee_config_authority: Option<Pubkey>,
+    #[serde(serialize_with = "display_option")]
+    pub withdraw_withheld_authority: Option<Pubkey>,
+    pub transfer_fee_basis_points: u16,
+    pub maximum_fee: u64,
+}
+
+/// New fee parameters; they take effect two epochs later.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SetTransferFee {
+    #[serde(flatten)]
+    pub context: EventContext,
+    #[serde(serialize_with = "display")]
+    pub mint: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub authority: Pubkey,
+    pub transfer_fee_basis_points: u16,
+    pub maximum_fee: u64,
+}
+
+/// Moves withheld fees to `destination`, either from the mint or directly from
+/// the listed token accounts (`sources` is empty for the mint variant).
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct WithdrawWithheld {
+    #[serde(flatten)]
+    pub context: EventContext,
+    #[serde(serialize_with = "display")]
+    pub mint: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub destination: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub authority: Pubkey,
+    #[serde(serialize_with = "display_vec")]
+    pub sources: Vec<Pubkey>,
+    /// Withheld fees moved, from `destination`'s balance change; `None` when the
+    /// transaction failed or another instruction also touches `destination`.
+    pub amount: Option<u64>,
+    pub decimals: Option<u8>,
+    pub ui_amount: Option<FixedDecimal>,
+}
+
+/// Permissionless sweep of withheld fees from token accounts into the mint.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct HarvestWithheld {
+    #[serde(flatten)]
+    pub context: EventContext,
+    #[serde(serialize_with = "display")]
+    pub mint: Pubkey,
+    #[serde(serialize_with = "display_vec")]
+    pub sources: Vec<Pubkey>,
+}
+
+/// A confidential transfer extension instruction. Amounts are only public for
+/// `deposit` and `withdraw`; everything else moves encrypted balances.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ConfidentialTransfer {
+    #[serde(flatten)]
+    pub context: EventContext,
+    pub instruction: &'static str,
+    /// The token account, or the mint for `initialize_mint` / `update_mint`.
+    #[serde(serialize_with = "display")]
+    pub account: Pubkey,
+    #[serde(serialize_with = "display_option")]
+    pub mint: Option<Pubkey>,
+    #[serde(serialize_with = "display_option")]
+    pub destination: Option<Pubkey>,
+    pub amount: Option<u64>,
+    pub decimals: Option<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct InterestRate {
+    #[serde(flatten)]
+    pub context: EventContext,
+    #[serde(serialize_with = "display")]
+    pub mint: Pubkey,
+    /// Configured authority on initialize, signing authority on update.
+    #[serde(serialize_with = "display_option")]
+    pub rate_authority: Option<Pubkey>,
+    pub rate_bps: i16,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TransferHook {
+    #[serde(flatten)]
+    pub context: EventContext,
+    #[serde(serialize_with = "display")]
+    pub mint: Pubkey,
+    /// Configured authority on initialize, signing authority on update.
+    #[serde(serialize_with = "display_option")]
+    pub authority: Option<Pubkey>,
+    #[serde(serialize_with = "display_option")]
+    pub hook_program_id: Option<Pubkey>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct MetadataPointer {
+    #[serde(flatten)]
+    pub context: EventContext,
+    #[serde(serialize_with = "display")]
+    pub mint: Pubkey,
+    /// Configured authority on initialize, signing authority on update.
+    #[serde(serialize_with = "display_option")]
+    pub authority: Option<Pubkey>,
+    #[serde(serialize_with = "display_option")]
+    pub metadata_address: Option<Pubkey>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct GroupPointer {
+    #[serde(flatten)]
+    pub context: EventContext,
+    #[serde(serialize_with = "display")]
+    pub mint: Pubkey,
+    /// Configured authority on initialize, signing authority on update.
+    #[serde(serialize_with = "display_option")]
+    pub authority: Option<Pubkey>,
+    #[serde(serialize_with = "display_option")]
+    pub group_address: Option<Pubkey>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DefaultAccountState {
+    #[serde(flatten)]
+    pub context: EventContext,
+    #[serde(serialize_with = "display")]
+    pub mint: Pubkey,
+    pub state: AccountState,
+    /// Signing freeze authority; only present on update.
+    #[serde(serialize_with = "display_option")]
+    pub freeze_authority: Option<Pubkey>,
+}
+
+/// Per-account switches (CPI guard, required memos) flipped by the account owner.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AccountToggle {
+    #[serde(flatten)]
+    pub context: EventContext,
+    #[serde(serialize_with = "display")]
+    pub account: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub owner: Pubkey,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountState {
+    Uninitialized,
+    Initialized,
+    Frozen,
+}
+
+impl AccountState {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        Some(match value {
+            0 => Self::Uninitialized,
+            1 => Self::Initialized,
+            2 => Self::Frozen,
+            _ => return None,
+        })
+    }
+}
+
+/// Net change of one wallet's holdings of one mint over a transaction, summed over
+/// all of its token accounts for that mint (from pre/post token balances).
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BalanceChange {
+    #[serde(flatten)]
+    pub transaction: TransactionContext,
+    #[serde(serialize_with = "display")]
+    pub owner: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub mint: Pubkey,
+    /// Token program that owns the accounts (SPL Token or Token-2022).
+    #[serde(serialize_with = "display_option")]
+    pub program_id: Option<Pubkey>,
+    pub decimals: u8,
+    /// Raw amounts; accounts created in the transaction count as 0 before, closed
+    /// ones as 0 after. A mint's supply fits in `u64`, so the sums do too.
+    pub pre_amount: u64,
+    pub post_amount: u64,
+    pub delta: i128,
+}
+
+/// A System Program lamport transfer.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct NativeTransfer {
+    #[serde(flatten)]
+    pub context: EventContext,
+    #[serde(serialize_with = "display")]
+    pub source: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub destination: Pubkey,
+    pub lamports: u64,
+    /// `lamports` in SOL.
+    pub ui_amount: FixedDecimal,
+    /// `TransferWithSeed` only: the base key and seed `source` is derived from.
+    #[serde(serialize_with = "display_option")]
+    pub base: Option<Pubkey>,
+    pub seed: Option<String>,
+}
+
+/// A System Program `CreateAccount`, funded by `funder`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct CreateAccount {
+    #[serde(flatten)]
+    pub context: EventContext,
+    #[serde(serialize_with = "display")]
+    pub funder: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub account: Pubkey,
+    pub lamports: u64,
+    pub ui_amount: FixedDecimal,
+    pub space: u64,
+    /// Program assigned as the new account's owner.
+    #[serde(serialize_with = "display")]
+    pub owner: Pubkey,
+}
+
+/// An Associated Token Account program `Create` or `CreateIdempotent`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AssociatedAccount {
+    #[serde(flatten)]
+    pub context: EventContext,
+    #[serde(serialize_with = "display")]
+    pub account: Pubkey,
+    /// Wallet the associated token account belongs to.
+    #[serde(serialize_with = "display")]
+    pub owner: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub mint: Pubkey,
+    /// Funds the account's rent.
+    #[serde(serialize_with = "display")]
+    pub payer: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub token_program: Pubkey,
+    /// False when `CreateIdempotent` found the account already there.
+    pub created: bool,
+}
+
+/// Associated Token Account `RecoverNested`: moves the tokens out of an associated
+/// account owned by another of the wallet's associated accounts, then closes it.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct RecoverNested {
+    #[serde(flatten)]
+    pub context: EventContext,
+    #[serde(serialize_with = "display")]
+    pub nested_account: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub nested_mint: Pubkey,
+    /// The wallet's own associated account for `nested_mint`.
+    #[serde(serialize_with = "display")]
+    pub destination: Pubkey,
+    /// The associated account that owns `nested_account`.
+    #[serde(serialize_with = "display")]
+    pub owner_account: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub owner_mint: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub wallet: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub token_program: Pubkey,
+}
+
+/// Net lamport change of one account over a transaction, from `pre_balances` /
+/// `post_balances`; the fee payer's includes the transaction fee.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct LamportChange {
+    #[serde(flatten)]
+    pub transaction: TransactionContext,
+    #[serde(serialize_with = "display")]
+    pub account: Pubkey,
+    pub pre_lamports: u64,
+    pub post_lamports: u64,
+    pub delta: i128,
+}
+
+/// `SetAuthority` authority kinds, numbered as in the instruction data. Token-2022
+/// extends the SPL Token list (0-3) with its extension authorities.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthorityType {
+    MintTokens,
+    FreezeAccount,
+    AccountOwner,
+    CloseAccount,
+    TransferFeeConfig,
+    WithheldWithdraw,
+    CloseMint,
+    InterestRate,
+    PermanentDelegate,
+    ConfidentialTransferMint,
+    TransferHookProgramId,
+    ConfidentialTransferFeeConfig,
+    MetadataPointer,
+    GroupPointer,
+    GroupMemberPointer,
+}
+
+impl AuthorityType {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        Some(match value {
+            0 => Self::MintTokens,
+            1 => Self::FreezeAccount,
+            2 => Self::AccountOwner,
+            3 => Self::CloseAccount,
+            4 => Self::TransferFeeConfig,
+            5 => Self::WithheldWithdraw,
+            6 => Self::CloseMint,
+            7 => Self::InterestRate,
+            8 => Self::PermanentDelegate,
+            9 => Self::ConfidentialTransferMint,
+            10 => Self::TransferHookProgramId,
+            11 => Self::ConfidentialTransferFeeConfig,
+            12 => Self::MetadataPointer,
+            13 => Self::GroupPointer,
+            14 => Self::GroupMemberPointer,
+            _ => return None,
+        })
+    }
+}
+
+fn display<T: Display, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+fn display_option<T: Display, S: Serializer>(
+    value: &Option<T>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match value {
+        Some(value) => serializer.collect_str(value),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn display_vec<T: Display, S: Serializer>(values: &[T], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(values.iter().map(ToString::to_string))
+}
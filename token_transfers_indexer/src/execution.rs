@@ -0,0 +1,114 @@
+//! Which instructions of a failed transaction ran, and how each one ended.
+//!
+//! A failed transaction still records the inner instructions invoked before the
+//! failure, and its logs carry one `Program <id> invoke [n]` line per executed
+//! instruction, in execution order, closed by `success` or `failed`.
+
+use std::collections::HashMap;
+
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::transaction::TransactionError;
+use yellowstone_grpc_proto::prelude::{Message, TransactionStatusMeta};
+
+use crate::event::InstructionStatus;
+
+pub(crate) struct Execution {
+    /// `None` for a successful transaction.
+    statuses: Option<HashMap<(u32, Option<u32>), InstructionStatus>>,
+}
+
+impl Execution {
+    pub(crate) fn new(message: &Message, meta: &TransactionStatusMeta) -> Self {
+        let Some(error) = &meta.err else {
+            return Self { statuses: None };
+        };
+
+        // Instructions after the failing one never run; without an index every
+        // top-level instruction may have.
+        let failed_at = match bincode::deserialize::<TransactionError>(&error.err) {
+            Ok(TransactionError::InstructionError(index, _)) => Some(u32::from(index)),
+            _ => None,
+        };
+        let executed: Vec<(u32, Option<u32>)> = (0..message.instructions.len() as u32)
+            .filter(|index| failed_at.is_none_or(|failed_at| *index <= failed_at))
+            .flat_map(|index| {
+                let inner_count = meta
+                    .inner_instructions
+                    .iter()
+                    .filter(|inner| inner.index == index)
+                    .map(|inner| inner.instructions.len() as u32)
+                    .sum::<u32>();
+                std::iter::once((index, None))
+                    .chain((0..inner_count).map(move |inner| (index, Some(inner))))
+            })
+            .collect();
+
+        let outcomes = invocation_outcomes(&meta.log_messages)
+            .filter(|outcomes| outcomes.len() == executed.len());
+        let statuses = executed
+            .iter()
+            .enumerate()
+            .map(|(position, &(index, inner))| {
+                let status = match &outcomes {
+                    Some(outcomes) if outcomes[position] => InstructionStatus::Reverted,
+                    Some(_) => InstructionStatus::Failed,
+                    // Without usable logs, only instructions before the failing one
+                    // are known to have completed.
+                    None if failed_at.is_some_and(|failed_at| index < failed_at) => {
+                        InstructionStatus::Reverted
+                    }
+                    None => InstructionStatus::Failed,
+                };
+                ((index, inner), status)
+            })
+            .collect();
+
+        Self {
+            statuses: Some(statuses),
+        }
+    }
+
+    /// `None` when the instruction never executed.
+    pub(crate) fn status(
+        &self,
+        instruction_index: u32,
+        inner_index: Option<u32>,
+    ) -> Option<InstructionStatus> {
+        match &self.statuses {
+            None => Some(InstructionStatus::Success),
+            Some(statuses) => statuses.get(&(instruction_index, inner_index)).copied(),
+        }
+    }
+}
+
+/// Whether each program invocation succeeded, in execution order; `None` when the
+/// logs are truncated or the invoke/result lines do not pair up.
+fn invocation_outcomes(logs: &[String]) -> Option<Vec<bool>> {
+    let mut outcomes = Vec::new();
+    let mut open = Vec::new();
+    for line in logs {
+        if line == "Log truncated" {
+            return None;
+        }
+        // `Program log:` / `Program data:` lines have no program id and are skipped.
+        let Some((program, event)) = line
+            .strip_prefix("Program ")
+            .and_then(|rest| rest.split_once(' '))
+        else {
+            continue;
+        };
+        if program.parse::<Pubkey>().is_err() {
+            continue;
+        }
+
+        if event.starts_with("invoke [") {
+            open.push(outcomes.len());
+            outcomes.push(false);
+        } else if event == "success" {
+            outcomes[open.pop()?] = true;
+        } else if event.starts_with("failed") {
+            open.pop()?;
+        }
+    }
+    Some(outcomes)
+}
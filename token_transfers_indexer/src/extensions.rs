@@ -0,0 +1,328 @@
+//! Token-2022 extension instructions.
+//!
+//! `TokenInstruction2022::unpack` only yields a unit prefix such as
+//! `TransferFeeExtension`; the extension's own instruction starts at `data[1]` and
+//! is decoded here straight from the bytes.
+
+use solana_sdk::pubkey::Pubkey;
+
+use crate::event::{
+    AccountState, AccountToggle, ConfidentialTransfer, DefaultAccountState, EventContext,
+    FeeSource, GroupPointer, HarvestWithheld, InterestRate, MetadataPointer, SetTransferFee,
+    TokenEvent, Transfer, TransferFeeConfig, TransferHook, WithdrawWithheld,
+};
+use crate::token::{InstructionAccounts, ui_amount};
+
+const TRANSFER_FEE_EXTENSION: u8 = 26;
+const CONFIDENTIAL_TRANSFER_EXTENSION: u8 = 27;
+const DEFAULT_ACCOUNT_STATE_EXTENSION: u8 = 28;
+const MEMO_TRANSFER_EXTENSION: u8 = 30;
+const INTEREST_BEARING_MINT_EXTENSION: u8 = 33;
+const CPI_GUARD_EXTENSION: u8 = 34;
+const TRANSFER_HOOK_EXTENSION: u8 = 36;
+const METADATA_POINTER_EXTENSION: u8 = 39;
+const GROUP_POINTER_EXTENSION: u8 = 40;
+
+pub(crate) fn decode(
+    keys: &InstructionAccounts,
+    data: &[u8],
+    context: EventContext,
+) -> Option<TokenEvent> {
+    let (&extension, rest) = data.split_first()?;
+    let (&instruction, args) = rest.split_first()?;
+    let args = Args(args);
+
+    match extension {
+        TRANSFER_FEE_EXTENSION => transfer_fee(keys, instruction, args, context),
+        CONFIDENTIAL_TRANSFER_EXTENSION => confidential_transfer(keys, instruction, args, context),
+        DEFAULT_ACCOUNT_STATE_EXTENSION => {
+            let event = DefaultAccountState {
+                context,
+                mint: keys.key(0)?,
+                state: AccountState::from_u8(args.u8(0)?)?,
+                freeze_authority: keys.key(1),
+            };
+            match instruction {
+                0 => Some(TokenEvent::InitializeDefaultAccountState(
+                    DefaultAccountState {
+                        freeze_authority: None,
+                        ..event
+                    },
+                )),
+                1 => Some(TokenEvent::UpdateDefaultAccountState(event)),
+                _ => None,
+            }
+        }
+        MEMO_TRANSFER_EXTENSION => match instruction {
+            0 => toggle(keys, context).map(TokenEvent::EnableRequiredMemoTransfers),
+            1 => toggle(keys, context).map(TokenEvent::DisableRequiredMemoTransfers),
+            _ => None,
+        },
+        CPI_GUARD_EXTENSION => match instruction {
+            0 => toggle(keys, context).map(TokenEvent::EnableCpiGuard),
+            1 => toggle(keys, context).map(TokenEvent::DisableCpiGuard),
+            _ => None,
+        },
+        INTEREST_BEARING_MINT_EXTENSION => match instruction {
+            // Initialize { rate_authority: OptionalNonZeroPubkey, rate: i16 }
+            0 => Some(TokenEvent::InitializeInterestBearingMint(InterestRate {
+                context,
+                mint: keys.key(0)?,
+                rate_authority: args.optional_pubkey(0)?,
+                rate_bps: args.i16(32)?,
+            })),
+            // UpdateRate { rate: i16 }
+            1 => Some(TokenEvent::UpdateInterestRate(InterestRate {
+                context,
+                mint: keys.key(0)?,
+                rate_authority: keys.key(1),
+                rate_bps: args.i16(0)?,
+            })),
+            _ => None,
+        },
+        TRANSFER_HOOK_EXTENSION => match instruction {
+            // Initialize { authority, program_id }
+            0 => Some(TokenEvent::InitializeTransferHook(TransferHook {
+                context,
+                mint: keys.key(0)?,
+                authority: args.optional_pubkey(0)?,
+                hook_program_id: args.optional_pubkey(32)?,
+            })),
+            // Update { program_id }
+            1 => Some(TokenEvent::UpdateTransferHook(TransferHook {
+                context,
+                mint: keys.key(0)?,
+                authority: keys.key(1),
+                hook_program_id: args.optional_pubkey(0)?,
+            })),
+            _ => None,
+        },
+        METADATA_POINTER_EXTENSION => match instruction {
+            // Initialize { authority, metadata_address }
+            0 => Some(TokenEvent::InitializeMetadataPointer(MetadataPointer {
+                context,
+                mint: keys.key(0)?,
+                authority: args.optional_pubkey(0)?,
+                metadata_address: args.optional_pubkey(32)?,
+            })),
+            // Update { metadata_address }
+            1 => Some(TokenEvent::UpdateMetadataPointer(MetadataPointer {
+                context,
+                mint: keys.key(0)?,
+                authority: keys.key(1),
+                metadata_address: args.optional_pubkey(0)?,
+            })),
+            _ => None,
+        },
+        GROUP_POINTER_EXTENSION => match instruction {
+            // Initialize { authority, group_address }
+            0 => Some(TokenEvent::InitializeGroupPointer(GroupPointer {
+                context,
+                mint: keys.key(0)?,
+                authority: args.optional_pubkey(0)?,
+                group_address: args.optional_pubkey(32)?,
+            })),
+            // Update { group_address }
+            1 => Some(TokenEvent::UpdateGroupPointer(GroupPointer {
+                context,
+                mint: keys.key(0)?,
+                authority: keys.key(1),
+                group_address: args.optional_pubkey(0)?,
+            })),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn transfer_fee(
+    keys: &InstructionAccounts,
+    instruction: u8,
+    args: Args,
+    context: EventContext,
+) -> Option<TokenEvent> {
+    match instruction {
+        // InitializeTransferFeeConfig { config_authority: COption, withdraw_authority: COption,
+        // transfer_fee_basis_points: u16, maximum_fee: u64 }
+        0 => {
+            let (transfer_fee_config_authority, offset) = args.coption_pubkey(0)?;
+            let (withdraw_withheld_authority, offset) = args.coption_pubkey(offset)?;
+            Some(TokenEvent::InitializeTransferFeeConfig(TransferFeeConfig {
+                context,
+                mint: keys.key(0)?,
+                transfer_fee_config_authority,
+                withdraw_withheld_authority,
+                transfer_fee_basis_points: args.u16(offset)?,
+                maximum_fee: args.u64(offset + 2)?,
+            }))
+        }
+        // TransferCheckedWithFee { amount: u64, decimals: u8, fee: u64 }
+        1 => {
+            let amount = args.u64(0)?;
+            let decimals = args.u8(8)?;
+            Some(TokenEvent::TransferCheckedWithFee(Transfer {
+                context,
+                source: keys.key(0)?,
+                mint: Some(keys.key(1)?),
+                destination: keys.key(2)?,
+                authority: keys.key(3)?,
+                amount,
+                decimals: Some(decimals),
+                ui_amount: ui_amount(amount, Some(decimals)),
+                fee: Some(args.u64(9)?),
+                net_amount: None,
+                fee_source: Some(FeeSource::Instruction),
+                source_owner: None,
+                destination_owner: None,
+            }))
+        }
+        2 => Some(TokenEvent::WithdrawWithheldTokensFromMint(
+            WithdrawWithheld {
+                context,
+                mint: keys.key(0)?,
+                destination: keys.key(1)?,
+                authority: keys.key(2)?,
+                sources: Vec::new(),
+                amount: None,
+                decimals: None,
+                ui_amount: None,
+            },
+        )),
+        // WithdrawWithheldTokensFromAccounts { num_token_accounts: u8 }; the sources are
+        // the last accounts, after any multisig signers.
+        3 => {
+            let count = usize::from(args.u8(0)?);
+            let first_source = keys.len().checked_sub(count)?.max(3);
+            Some(TokenEvent::WithdrawWithheldTokensFromAccounts(
+                WithdrawWithheld {
+                    context,
+                    mint: keys.key(0)?,
+                    destination: keys.key(1)?,
+                    authority: keys.key(2)?,
+                    sources: keys.keys_from(first_source),
+                    amount: None,
+                    decimals: None,
+                    ui_amount: None,
+                },
+            ))
+        }
+        4 => Some(TokenEvent::HarvestWithheldTokensToMint(HarvestWithheld {
+            context,
+            mint: keys.key(0)?,
+            sources: keys.keys_from(1),
+        })),
+        // SetTransferFee { transfer_fee_basis_points: u16, maximum_fee: u64 }
+        5 => Some(TokenEvent::SetTransferFee(SetTransferFee {
+            context,
+            mint: keys.key(0)?,
+            authority: keys.key(1)?,
+            transfer_fee_basis_points: args.u16(0)?,
+            maximum_fee: args.u64(2)?,
+        })),
+        _ => None,
+    }
+}
+
+fn confidential_transfer(
+    keys: &InstructionAccounts,
+    instruction: u8,
+    args: Args,
+    context: EventContext,
+) -> Option<TokenEvent> {
+    let name = match instruction {
+        0 => "initialize_mint",
+        1 => "update_mint",
+        2 => "configure_account",
+        3 => "approve_account",
+        4 => "empty_account",
+        5 => "deposit",
+        6 => "withdraw",
+        7 => "transfer",
+        8 => "apply_pending_balance",
+        9 => "enable_confidential_credits",
+        10 => "disable_confidential_credits",
+        11 => "enable_non_confidential_credits",
+        12 => "disable_non_confidential_credits",
+        13 => "transfer_with_split_proofs",
+        _ => return None,
+    };
+
+    let account = keys.key(0)?;
+    let mint = match instruction {
+        0 | 1 => Some(account),
+        2 | 3 | 5 | 6 | 7 | 13 => keys.key(1),
+        _ => None,
+    };
+    let destination = match instruction {
+        7 | 13 => keys.key(2),
+        _ => None,
+    };
+    // Deposit and Withdraw both start with { amount: u64, decimals: u8 }.
+    let (amount, decimals) = match instruction {
+        5 | 6 => (Some(args.u64(0)?), Some(args.u8(8)?)),
+        _ => (None, None),
+    };
+
+    Some(TokenEvent::ConfidentialTransfer(ConfidentialTransfer {
+        context,
+        instruction: name,
+        account,
+        mint,
+        destination,
+        amount,
+        decimals,
+    }))
+}
+
+fn toggle(keys: &InstructionAccounts, context: EventContext) -> Option<AccountToggle> {
+    Some(AccountToggle {
+        context,
+        account: keys.key(0)?,
+        owner: keys.key(1)?,
+    })
+}
+
+/// Little-endian reads from an extension instruction's arguments.
+#[derive(Clone, Copy)]
+struct Args<'a>(&'a [u8]);
+
+impl Args<'_> {
+    fn bytes<const N: usize>(&self, offset: usize) -> Option<[u8; N]> {
+        self.0.get(offset..offset + N)?.try_into().ok()
+    }
+
+    fn u8(&self, offset: usize) -> Option<u8> {
+        self.0.get(offset).copied()
+    }
+
+    fn u16(&self, offset: usize) -> Option<u16> {
+        self.bytes(offset).map(u16::from_le_bytes)
+    }
+
+    fn i16(&self, offset: usize) -> Option<i16> {
+        self.bytes(offset).map(i16::from_le_bytes)
+    }
+
+    fn u64(&self, offset: usize) -> Option<u64> {
+        self.bytes(offset).map(u64::from_le_bytes)
+    }
+
+    /// `OptionalNonZeroPubkey`: 32 bytes, all zero meaning `None`.
+    fn optional_pubkey(&self, offset: usize) -> Option<Option<Pubkey>> {
+        let key = Pubkey::new_from_array(self.bytes(offset)?);
+        Some((key != Pubkey::default()).then_some(key))
+    }
+
+    /// `COption<Pubkey>` as packed by the transfer fee extension: a tag byte followed
+    /// by the key only when the tag is 1. Returns the value and the next offset.
+    fn coption_pubkey(&self, offset: usize) -> Option<(Option<Pubkey>, usize)> {
+        match self.u8(offset)? {
+            0 => Some((None, offset + 1)),
+            1 => Some((
+                Some(Pubkey::new_from_array(self.bytes(offset + 1)?)),
+                offset + 33,
+            )),
+            _ => None,
+        }
+    }
+}
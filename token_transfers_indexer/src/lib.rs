@@ -0,0 +1,8 @@
+mod associated;
+pub mod event;
+mod execution;
+mod extensions;
+mod native;
+pub mod token;
+
+pub use stream_common::{amount, call_tree};
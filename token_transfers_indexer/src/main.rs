@@ -1,15 +1,20 @@
 use std::error::Error;
+use std::io::Write as _;
+use std::path::Path;
+use std::sync::Arc;
 
 use base64::{Engine as _, engine::general_purpose};
 use futures::StreamExt;
 use prost::Message as _;
 use rdkafka::ClientConfig;
-use rdkafka::consumer::{Consumer, StreamConsumer};
+use rdkafka::consumer::{CommitMode, Consumer, StreamConsumer};
 use rdkafka::message::Message;
 use serde::Deserialize;
+use stream_common::recording::UpdateReader;
+use stream_common::shutdown;
 use yellowstone_grpc_proto::prelude::SubscribeUpdate;
 
-mod token;
+use token_transfers_indexer::token;
 
 fn build_consumer_config(brokers: &str, group_id: &str) -> Result<ClientConfig, Box<dyn Error>> {
     let mut config = ClientConfig::new();
@@ -17,6 +22,7 @@ fn build_consumer_config(brokers: &str, group_id: &str) -> Result<ClientConfig,
         .set("bootstrap.servers", brokers)
         .set("group.id", group_id)
         .set("enable.auto.commit", "true")
+        .set("enable.auto.offset.store", "false")
         .set("auto.offset.reset", "earliest");
 
     let security_protocol = std::env::var("KAFKA_SECURITY_PROTOCOL").ok();
@@ -60,6 +66,10 @@ struct KafkaPayload {
 async fn main() -> Result<(), Box<dyn Error>> {
     dotenv::dotenv().ok();
 
+    if let Ok(path) = std::env::var("REPLAY_PATH") {
+        return replay_file(Path::new(&path));
+    }
+
     let brokers = std::env::var("KAFKA_BROKERS").unwrap_or_else(|_| "localhost:9092".to_string());
     let group_id = std::env::var("KAFKA_GROUP_ID").unwrap_or_else(|_| "indexer.token".to_string());
     let topic = std::env::var("KAFKA_TOPIC").unwrap_or_else(|_| "ingest.token".to_string());
@@ -69,31 +79,34 @@ async fn main() -> Result<(), Box<dyn Error>> {
     println!("   Kafka group: {}", group_id);
     println!("   Kafka topic: {}", topic);
 
+    let shutdown_timeout = shutdown::timeout_from_env();
+    let mut shutdown = shutdown::listen_for_signals();
+
     let config = build_consumer_config(&brokers, &group_id)?;
-    let consumer: StreamConsumer = config.create()?;
+    let consumer: Arc<StreamConsumer> = Arc::new(config.create()?);
     consumer.subscribe(&[&topic])?;
 
     println!("Subscribed. Waiting for messages...");
 
-    let mut processor = token::TokenProcessor::new();
+    let mut processor = token::TokenProcessor::new()
+        .with_failed_transactions(token::FailedTransactions::from_env());
     let mut stream = consumer.stream();
-    while let Some(message) = stream.next().await {
+    loop {
+        let message = tokio::select! {
+            _ = shutdown::requested(&mut shutdown) => break,
+            message = stream.next() => match message {
+                Some(message) => message,
+                None => break,
+            },
+        };
+
         match message {
             Ok(msg) => {
-                let Some(payload) = payload_from_message(&msg) else {
-                    eprintln!("Skipping non-JSON Kafka payload");
-                    continue;
-                };
-
-                let update = match decode_update(&payload) {
-                    Ok(update) => update,
-                    Err(err) => {
-                        eprintln!("Failed to decode update: {}", err);
-                        continue;
-                    }
-                };
-
-                processor.handle_update(update);
+                handle_message(&mut processor, &msg);
+
+                if let Err(err) = consumer.store_offset_from_message(&msg) {
+                    eprintln!("Failed to store Kafka offset: {}", err);
+                }
             }
             Err(err) => {
                 eprintln!("Kafka error: {}", err);
@@ -101,9 +114,86 @@ async fn main() -> Result<(), Box<dyn Error>> {
         }
     }
 
+    drop(stream);
+    drain(consumer, shutdown_timeout).await;
+
+    Ok(())
+}
+
+fn handle_message(
+    processor: &mut token::TokenProcessor,
+    msg: &rdkafka::message::BorrowedMessage<'_>,
+) {
+    let Some(payload) = payload_from_message(msg) else {
+        eprintln!("Skipping non-JSON Kafka payload");
+        return;
+    };
+
+    let update = match decode_update(&payload) {
+        Ok(update) => update,
+        Err(err) => {
+            eprintln!("Failed to decode update: {}", err);
+            return;
+        }
+    };
+
+    emit(processor.handle_update(update));
+}
+
+fn emit(events: Vec<token::TokenEvent>) {
+    for event in events {
+        match serde_json::to_string(&event) {
+            Ok(line) => println!("{line}"),
+            Err(err) => eprintln!("Failed to serialize event: {}", err),
+        }
+    }
+}
+
+/// Feeds a file recorded by the ingestor (`INGESTOR_MODE=record`) through the
+/// processor instead of consuming Kafka.
+fn replay_file(path: &Path) -> Result<(), Box<dyn Error>> {
+    println!("Replaying updates from {}", path.display());
+
+    let mut processor = token::TokenProcessor::new()
+        .with_failed_transactions(token::FailedTransactions::from_env());
+    let mut count = 0u64;
+    for update in UpdateReader::open(path)? {
+        match update {
+            Ok(update) => {
+                emit(processor.handle_update(update));
+                count += 1;
+            }
+            Err(err) => {
+                eprintln!("Stopping replay after {} updates: {}", count, err);
+                break;
+            }
+        }
+    }
+
+    println!("Replayed {} updates", count);
     Ok(())
 }
 
+/// Commits stored offsets and flushes stdout, giving up after `timeout`.
+async fn drain(consumer: Arc<StreamConsumer>, timeout: std::time::Duration) {
+    println!("Committing offsets (timeout: {}s)...", timeout.as_secs());
+
+    let commit = tokio::task::spawn_blocking(move || {
+        let result = consumer.commit_consumer_state(CommitMode::Sync);
+        let _ = std::io::stdout().flush();
+        result
+    });
+
+    match tokio::time::timeout(timeout, commit).await {
+        Ok(Ok(Ok(()))) => println!("Offsets committed"),
+        Ok(Ok(Err(err))) => eprintln!("Offset commit failed: {}", err),
+        Ok(Err(err)) => eprintln!("Offset commit task failed: {}", err),
+        Err(_) => eprintln!("Offset commit timed out"),
+    }
+
+    println!("token_transfers_indexer stopped");
+}
+
 fn payload_from_message(message: &rdkafka::message::BorrowedMessage<'_>) -> Option<KafkaPayload> {
     let payload = match message.payload_view::<str>() {
         Some(Ok(payload)) => payload,
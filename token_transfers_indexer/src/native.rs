@@ -0,0 +1,178 @@
+//! Native SOL: System Program transfers, per-account lamport deltas, and the
+//! lamport side of wrapped SOL (`SyncNative` / `CloseAccount`).
+//!
+//! System instructions are bincode-encoded: a `u32` discriminant followed by the
+//! fields, with strings as a `u64` length and UTF-8 bytes.
+
+use std::collections::{HashMap, HashSet};
+
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::amount::FixedDecimal;
+use crate::event::{
+    CreateAccount, EventContext, LamportChange, NativeTransfer, TokenEvent, TransactionContext,
+};
+use crate::token::InstructionAccounts;
+
+pub(crate) const SYSTEM_PROGRAM_ID: Pubkey = pubkey!("11111111111111111111111111111111");
+pub(crate) const NATIVE_MINT: Pubkey = pubkey!("So11111111111111111111111111111111111111112");
+const SOL_DECIMALS: u8 = 9;
+
+const CREATE_ACCOUNT: u32 = 0;
+const TRANSFER: u32 = 2;
+const TRANSFER_WITH_SEED: u32 = 11;
+
+pub(crate) fn decode(
+    keys: &InstructionAccounts,
+    data: &[u8],
+    context: EventContext,
+) -> Option<TokenEvent> {
+    let discriminant = u32::from_le_bytes(data.get(..4)?.try_into().ok()?);
+    let lamports = u64::from_le_bytes(data.get(4..12)?.try_into().ok()?);
+    let ui_amount = FixedDecimal::new(lamports, SOL_DECIMALS);
+
+    match discriminant {
+        // CreateAccount { lamports, space: u64, owner: Pubkey }
+        CREATE_ACCOUNT => Some(TokenEvent::SystemCreateAccount(CreateAccount {
+            context,
+            funder: keys.key(0)?,
+            account: keys.key(1)?,
+            lamports,
+            ui_amount,
+            space: u64::from_le_bytes(data.get(12..20)?.try_into().ok()?),
+            owner: Pubkey::try_from(data.get(20..52)?).ok()?,
+        })),
+        // Transfer { lamports }
+        TRANSFER => Some(TokenEvent::SystemTransfer(NativeTransfer {
+            context,
+            source: keys.key(0)?,
+            destination: keys.key(1)?,
+            lamports,
+            ui_amount,
+            base: None,
+            seed: None,
+        })),
+        // TransferWithSeed { lamports, from_seed: String, from_owner: Pubkey }; the
+        // accounts are the derived source, its base, then the destination.
+        TRANSFER_WITH_SEED => {
+            let seed_len =
+                usize::try_from(u64::from_le_bytes(data.get(12..20)?.try_into().ok()?)).ok()?;
+            let seed = data.get(20..20usize.checked_add(seed_len)?)?;
+            Some(TokenEvent::SystemTransferWithSeed(NativeTransfer {
+                context,
+                source: keys.key(0)?,
+                destination: keys.key(2)?,
+                lamports,
+                ui_amount,
+                base: keys.key(1),
+                seed: Some(String::from_utf8_lossy(seed).into_owned()),
+            }))
+        }
+        _ => None,
+    }
+}
+
+/// One `lamport_change` per account whose lamports moved, in account-key order.
+pub(crate) fn lamport_changes(
+    account_keys: &[Pubkey],
+    pre_balances: &[u64],
+    post_balances: &[u64],
+    transaction: &TransactionContext,
+) -> Vec<TokenEvent> {
+    account_keys
+        .iter()
+        .zip(pre_balances.iter().zip(post_balances))
+        .filter(|(_, (pre, post))| pre != post)
+        .map(|(account, (&pre, &post))| {
+            TokenEvent::LamportChange(LamportChange {
+                transaction: transaction.clone(),
+                account: *account,
+                pre_lamports: pre,
+                post_lamports: post,
+                delta: i128::from(post) - i128::from(pre),
+            })
+        })
+        .collect()
+}
+
+/// Replays the lamport movements of the decoded events, in execution order, to
+/// fill in what each `SyncNative` wrapped and what each `CloseAccount` released.
+///
+/// Lamports moved by programs this indexer does not decode are not seen, so the
+/// figures are only as complete as the decoded instructions.
+pub(crate) fn link_wrapped_sol(
+    events: &mut [TokenEvent],
+    account_keys: &[Pubkey],
+    pre_balances: &[u64],
+    native_accounts: &HashSet<Pubkey>,
+) {
+    let mut lamports: HashMap<Pubkey, u64> = HashMap::new();
+    for (account, &balance) in account_keys.iter().zip(pre_balances) {
+        lamports.entry(*account).or_insert(balance);
+    }
+    let mut native_accounts = native_accounts.clone();
+    // System transfers into each account since it was last synced.
+    let mut unsynced: HashMap<Pubkey, u64> = HashMap::new();
+
+    for event in events.iter_mut() {
+        match event {
+            TokenEvent::SystemTransfer(transfer) | TokenEvent::SystemTransferWithSeed(transfer) => {
+                move_lamports(
+                    &mut lamports,
+                    transfer.source,
+                    transfer.destination,
+                    transfer.lamports,
+                );
+                let pending = unsynced.entry(transfer.destination).or_default();
+                *pending = pending.saturating_add(transfer.lamports);
+            }
+            TokenEvent::SystemCreateAccount(create) => {
+                move_lamports(
+                    &mut lamports,
+                    create.funder,
+                    create.account,
+                    create.lamports,
+                );
+            }
+            TokenEvent::InitializeAccount(init)
+            | TokenEvent::InitializeAccount2(init)
+            | TokenEvent::InitializeAccount3(init) => {
+                if init.mint == NATIVE_MINT {
+                    native_accounts.insert(init.account);
+                }
+            }
+            TokenEvent::Transfer(transfer)
+            | TokenEvent::TransferChecked(transfer)
+            | TokenEvent::TransferCheckedWithFee(transfer) => {
+                // Wrapped SOL token amounts are the account's lamports above rent.
+                if native_accounts.contains(&transfer.source) {
+                    move_lamports(
+                        &mut lamports,
+                        transfer.source,
+                        transfer.destination,
+                        transfer.amount,
+                    );
+                }
+            }
+            TokenEvent::SyncNative(sync) => {
+                sync.wrapped_lamports = unsynced.remove(&sync.account);
+            }
+            TokenEvent::CloseAccount(close) => {
+                close.native = native_accounts.contains(&close.account);
+                let released = lamports.get(&close.account).copied().unwrap_or(0);
+                move_lamports(&mut lamports, close.account, close.destination, released);
+                close.lamports = Some(released);
+                unsynced.remove(&close.account);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn move_lamports(lamports: &mut HashMap<Pubkey, u64>, from: Pubkey, to: Pubkey, amount: u64) {
+    let source = lamports.entry(from).or_default();
+    *source = source.saturating_sub(amount);
+    let destination = lamports.entry(to).or_default();
+    *destination = destination.saturating_add(amount);
+}
@@ -1,617 +1,1049 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::str::FromStr;
 
-use solana_sdk::bs58;
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::TransactionError;
 use spl_token::instruction::TokenInstruction as TokenInstructionLegacy;
+use spl_token_2022::extension::transfer_fee::TransferFeeConfig as MintTransferFeeConfig;
+use spl_token_2022::extension::{BaseStateWithExtensions, StateWithExtensions};
 use spl_token_2022::instruction::TokenInstruction as TokenInstruction2022;
+use spl_token_2022::solana_program::program_pack::Pack;
+use spl_token_2022::state::Mint;
 use yellowstone_grpc_proto::prelude::{
-    CompiledInstruction, InnerInstruction, Message, SubscribeUpdate, SubscribeUpdateTransaction,
+    Message, SubscribeUpdate, SubscribeUpdateAccount, SubscribeUpdateTransaction,
     TransactionStatusMeta, subscribe_update::UpdateOneof,
 };
 
-const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
-const TOKEN_2022_PROGRAM_ID: &str = "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb";
+use crate::amount::FixedDecimal;
+use crate::associated;
+use crate::call_tree::CallTree;
+use crate::execution::Execution;
+use crate::extensions;
+use crate::native;
+
+pub use crate::event::{
+    AccountFreeze, AmountToUiAmount, Approve, AuthorityType, BalanceChange, Burn, CloseAccount,
+    EventContext, FeeSource, InitializeAccount, InitializeMint, InstructionStatus, MintTo, Revoke,
+    SetAuthority, SyncNative, TokenEvent, TransactionContext, Transfer,
+};
+
+const TOKEN_PROGRAM_ID: Pubkey = pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+const TOKEN_2022_PROGRAM_ID: Pubkey = pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+
+/// Mainnet-beta epoch length (no warmup), used to pick the active transfer fee.
+const SLOTS_PER_EPOCH: u64 = 432_000;
+
+/// Token-2022 mints with extensions store the account type after the 165-byte base
+/// account area; a plain mint is exactly `Mint::LEN` bytes.
+const ACCOUNT_TYPE_OFFSET: usize = 165;
+const ACCOUNT_TYPE_MINT: u8 = 1;
+
+/// Slots of block times kept behind the newest block meta.
+const BLOCK_TIME_SLOTS: u64 = 512;
+
+/// Bound on the token account owner cache; it is cleared when full.
+const MAX_CACHED_OWNERS: usize = 1_000_000;
 
 #[derive(Clone, Debug)]
 struct BalanceInfo {
-    mint: String,
+    mint: Pubkey,
     decimals: u8,
-    pre_ui: Option<f64>,
-    post_ui: Option<f64>,
+    pre_amount: Option<u64>,
+    post_amount: Option<u64>,
+    owner: Option<Pubkey>,
+    program_id: Option<Pubkey>,
+}
+
+pub struct TokenProcessor {
+    /// Transfer fee config of Token-2022 mints seen in account updates; `None` for
+    /// mints without the extension.
+    mint_fees: HashMap<Pubkey, Option<MintTransferFeeConfig>>,
+    /// Owner wallets of token accounts created or reassigned in earlier
+    /// transactions, for transfers whose accounts have no token balance entry.
+    account_owners: HashMap<Pubkey, Pubkey>,
+    /// Block time by slot, from block meta updates.
+    block_times: BTreeMap<u64, i64>,
+    failed_transactions: FailedTransactions,
+}
+
+/// What to emit for the instructions of failed transactions. Either way the
+/// transaction-level balance and lamport changes are emitted, since the fee is
+/// still charged.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FailedTransactions {
+    /// Emit no instruction events.
+    #[default]
+    Drop,
+    /// Emit the instructions that ran, with `status` `reverted` or `failed`.
+    Flag,
+}
+
+impl FailedTransactions {
+    /// Reads `FAILED_TRANSACTIONS` (`drop` or `flag`), defaulting to `drop`.
+    pub fn from_env() -> Self {
+        match std::env::var("FAILED_TRANSACTIONS").as_deref() {
+            Ok("flag") => Self::Flag,
+            Ok("drop") | Err(_) => Self::Drop,
+            Ok(other) => {
+                eprintln!(
+                    "Unknown FAILED_TRANSACTIONS value {other:?}; dropping failed transactions"
+                );
+                Self::Drop
+            }
+        }
+    }
 }
 
-pub struct TokenProcessor;
+impl Default for TokenProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl TokenProcessor {
     pub fn new() -> Self {
-        Self
+        Self {
+            mint_fees: HashMap::new(),
+            account_owners: HashMap::new(),
+            block_times: BTreeMap::new(),
+            failed_transactions: FailedTransactions::default(),
+        }
+    }
+
+    pub fn with_failed_transactions(mut self, failed_transactions: FailedTransactions) -> Self {
+        self.failed_transactions = failed_transactions;
+        self
     }
 
-    pub fn handle_update(&mut self, update: SubscribeUpdate) {
-        if let Some(UpdateOneof::Transaction(tx_update)) = update.update_oneof {
-            self.handle_transaction_update(tx_update);
+    pub fn handle_update(&mut self, update: SubscribeUpdate) -> Vec<TokenEvent> {
+        match update.update_oneof {
+            Some(UpdateOneof::Transaction(tx_update)) => self.handle_transaction_update(tx_update),
+            Some(UpdateOneof::Account(account_update)) => {
+                self.handle_account_update(account_update);
+                Vec::new()
+            }
+            Some(UpdateOneof::BlockMeta(block_meta)) => {
+                if let Some(block_time) = block_meta.block_time {
+                    self.block_times
+                        .insert(block_meta.slot, block_time.timestamp);
+                }
+                // Only recent slots can still have transactions arriving.
+                let oldest = block_meta.slot.saturating_sub(BLOCK_TIME_SLOTS);
+                self.block_times = self.block_times.split_off(&oldest);
+                Vec::new()
+            }
+            _ => Vec::new(),
         }
     }
 
-    fn handle_transaction_update(&mut self, tx_update: SubscribeUpdateTransaction) {
-        let Some(tx) = &tx_update.transaction else {
+    /// Caches the transfer fee config of Token-2022 mints so transfers can be split
+    /// into fee and net amounts without relying on balance deltas.
+    fn handle_account_update(&mut self, account_update: SubscribeUpdateAccount) {
+        let Some(account) = account_update.account else {
             return;
         };
-        let Some(meta) = &tx.meta else {
+        if account.owner.as_slice() != TOKEN_2022_PROGRAM_ID.as_ref() {
             return;
+        }
+
+        let data = account.data.as_slice();
+        let is_mint =
+            data.len() == Mint::LEN || data.get(ACCOUNT_TYPE_OFFSET) == Some(&ACCOUNT_TYPE_MINT);
+        if !is_mint {
+            return;
+        }
+
+        let (Ok(pubkey), Ok(mint)) = (
+            Pubkey::try_from(account.pubkey.as_slice()),
+            StateWithExtensions::<Mint>::unpack(data),
+        ) else {
+            return;
+        };
+
+        let fee_config = mint.get_extension::<MintTransferFeeConfig>().ok().copied();
+        self.mint_fees.insert(pubkey, fee_config);
+    }
+
+    fn handle_transaction_update(
+        &mut self,
+        tx_update: SubscribeUpdateTransaction,
+    ) -> Vec<TokenEvent> {
+        let mut events = Vec::new();
+        let Some(tx) = &tx_update.transaction else {
+            return events;
+        };
+        let Some(meta) = &tx.meta else {
+            return events;
         };
         let Some(message) = &tx.transaction else {
-            return;
+            return events;
         };
         let Some(tx_message) = &message.message else {
-            return;
+            return events;
         };
 
-        let signature = bs58::encode(&tx.signature).into_string();
-        let balances = build_balance_map(meta);
+        let Ok(signature) = Signature::try_from(tx.signature.as_slice()) else {
+            return events;
+        };
+        let Some(account_keys) = resolve_account_keys(tx_message, meta) else {
+            eprintln!("Skipping transaction {signature}: malformed account key");
+            return events;
+        };
+        let balances = build_balance_map(meta, &account_keys);
+        let transaction = TransactionContext {
+            slot: tx_update.slot,
+            signature,
+            block_time: self.block_times.get(&tx_update.slot).copied(),
+            transaction_index: tx.index,
+            fee_payer: account_keys.first().copied(),
+            success: meta.err.is_none(),
+            error: meta.err.as_ref().map(|err| transaction_error(&err.err)),
+            compute_units_consumed: meta.compute_units_consumed,
+            transaction_fee: meta.fee,
+        };
+        let dropped = meta.err.is_some() && self.failed_transactions == FailedTransactions::Drop;
+        let execution = Execution::new(tx_message, meta);
+        let program_id =
+            |program_id_index: u32| account_keys.get(program_id_index as usize).copied();
+        let context = |tree: &CallTree<Option<Pubkey>>, instruction_index: u32, inner_index| {
+            if dropped {
+                return None;
+            }
+            let node = tree.node(inner_index)?;
+            Some(EventContext {
+                transaction: transaction.clone(),
+                program_id: node.program_id?,
+                instruction_index,
+                inner_index,
+                stack_height: node.stack_height,
+                invoked_by: tree.invoked_by(inner_index).into_iter().flatten().collect(),
+                status: execution.status(instruction_index, inner_index)?,
+            })
+        };
 
+        // Each top-level instruction is followed by its inner instructions, so events
+        // come out in execution order.
         for (idx, instruction) in tx_message.instructions.iter().enumerate() {
-            self.process_compiled_instruction(
-                tx_message,
-                meta,
-                instruction,
-                &balances,
-                tx_update.slot,
-                &signature,
-                idx as u32,
+            let inner_instructions: Vec<_> = meta
+                .inner_instructions
+                .iter()
+                .filter(|inner| inner.index == idx as u32)
+                .flat_map(|inner| inner.instructions.iter())
+                .collect();
+            // A program id index past the account keys leaves its node unresolved:
+            // that instruction is not decoded and is left out of `invoked_by`, while
+            // the instructions it invoked keep their place in the tree.
+            let tree = CallTree::build(
+                program_id(instruction.program_id_index),
+                inner_instructions
+                    .iter()
+                    .map(|inner| (program_id(inner.program_id_index), inner.stack_height)),
             );
-        }
 
-        for inner in &meta.inner_instructions {
-            for (inner_idx, instruction) in inner.instructions.iter().enumerate() {
-                self.process_inner_instruction(
-                    tx_message,
-                    meta,
-                    instruction,
+            let top_level = (None, &instruction.accounts, &instruction.data);
+            let inner = inner_instructions
+                .iter()
+                .enumerate()
+                .map(|(inner_idx, inner)| (Some(inner_idx as u32), &inner.accounts, &inner.data));
+            for (inner_index, accounts, data) in std::iter::once(top_level).chain(inner) {
+                let Some(context) = context(&tree, idx as u32, inner_index) else {
+                    continue;
+                };
+                events.extend(self.process_token_instruction(
+                    &account_keys,
+                    accounts,
+                    data,
                     &balances,
-                    tx_update.slot,
-                    &signature,
-                    inner.index,
-                    inner_idx as u32,
-                );
+                    context,
+                ));
             }
         }
-    }
 
-    fn process_compiled_instruction(
-        &self,
-        message: &Message,
-        meta: &TransactionStatusMeta,
-        instruction: &CompiledInstruction,
-        balances: &HashMap<u32, BalanceInfo>,
-        slot: u64,
-        signature: &str,
-        instruction_index: u32,
-    ) {
-        self.process_token_instruction(
-            message,
-            meta,
-            instruction.program_id_index as usize,
-            &instruction.accounts,
-            &instruction.data,
-            balances,
-            slot,
-            signature,
-            false,
-            instruction_index,
-            None,
+        self.apply_transfer_fees(&mut events, &balances, tx_update.slot / SLOTS_PER_EPOCH);
+        associated::mark_created(&mut events, &account_keys, &meta.pre_balances);
+        self.cache_owners(&events);
+        for transfer in events.iter_mut().filter_map(TokenEvent::transfer_mut) {
+            transfer.source_owner = self.owner_of(&transfer.source, &balances);
+            transfer.destination_owner = self.owner_of(&transfer.destination, &balances);
+        }
+        self.evict_closed(&events);
+        let native_accounts: HashSet<Pubkey> = balances
+            .iter()
+            .filter(|(_, info)| info.mint == native::NATIVE_MINT)
+            .map(|(account, _)| *account)
+            .collect();
+        native::link_wrapped_sol(
+            &mut events,
+            &account_keys,
+            &meta.pre_balances,
+            &native_accounts,
         );
+
+        events.extend(balance_changes(&balances, &transaction));
+        events.extend(native::lamport_changes(
+            &account_keys,
+            &meta.pre_balances,
+            &meta.post_balances,
+            &transaction,
+        ));
+        events
+    }
+
+    /// Remembers who owns the token accounts created or reassigned by `events`.
+    fn cache_owners(&mut self, events: &[TokenEvent]) {
+        for event in events.iter().filter(|event| succeeded(event)) {
+            let (account, owner) = match event {
+                TokenEvent::CreateAssociatedAccount(create)
+                | TokenEvent::CreateAssociatedAccountIdempotent(create) => {
+                    (create.account, create.owner)
+                }
+                TokenEvent::InitializeAccount(init)
+                | TokenEvent::InitializeAccount2(init)
+                | TokenEvent::InitializeAccount3(init) => (init.account, init.owner),
+                TokenEvent::SetAuthority(SetAuthority {
+                    account,
+                    authority_type: AuthorityType::AccountOwner,
+                    new_authority: Some(owner),
+                    ..
+                }) => (*account, *owner),
+                TokenEvent::RecoverNested(recover) => (recover.destination, recover.wallet),
+                _ => continue,
+            };
+            if self.account_owners.len() >= MAX_CACHED_OWNERS {
+                self.account_owners.clear();
+            }
+            self.account_owners.insert(account, owner);
+        }
+    }
+
+    /// Forgets accounts closed in this transaction, once its transfers have used them.
+    fn evict_closed(&mut self, events: &[TokenEvent]) {
+        for event in events.iter().filter(|event| succeeded(event)) {
+            match event {
+                TokenEvent::CloseAccount(close) => {
+                    self.account_owners.remove(&close.account);
+                }
+                TokenEvent::RecoverNested(recover) => {
+                    self.account_owners.remove(&recover.nested_account);
+                }
+                _ => {}
+            }
+        }
     }
 
-    fn process_inner_instruction(
+    /// The token balances' owner, else the cached one.
+    fn owner_of(
         &self,
-        message: &Message,
-        meta: &TransactionStatusMeta,
-        instruction: &InnerInstruction,
-        balances: &HashMap<u32, BalanceInfo>,
-        slot: u64,
-        signature: &str,
-        parent_index: u32,
-        inner_index: u32,
-    ) {
-        self.process_token_instruction(
-            message,
-            meta,
-            instruction.program_id_index as usize,
-            &instruction.accounts,
-            &instruction.data,
-            balances,
-            slot,
-            signature,
-            true,
-            parent_index,
-            Some(inner_index),
-        );
+        account: &Pubkey,
+        balances: &HashMap<Pubkey, BalanceInfo>,
+    ) -> Option<Pubkey> {
+        balances
+            .get(account)
+            .and_then(|info| info.owner)
+            .or_else(|| self.account_owners.get(account).copied())
     }
 
-    #[allow(clippy::too_many_arguments)]
-    fn process_token_instruction(
+    /// Fills in fee and net amounts on every transfer in a transaction, and the
+    /// amount of every withheld-fee withdrawal.
+    fn apply_transfer_fees(
         &self,
-        message: &Message,
-        _meta: &TransactionStatusMeta,
-        program_id_index: usize,
-        accounts: &[u8],
-        data: &[u8],
-        balances: &HashMap<u32, BalanceInfo>,
-        slot: u64,
-        signature: &str,
-        is_inner: bool,
-        instruction_index: u32,
-        inner_index: Option<u32>,
+        events: &mut [TokenEvent],
+        balances: &HashMap<Pubkey, BalanceInfo>,
+        epoch: u64,
     ) {
-        let program_id = match account_key(message, program_id_index) {
-            Some(id) => id,
-            None => return,
-        };
+        // A balance delta only isolates one instruction's amount when no other
+        // transfer or withdrawal in the transaction touches the same account.
+        let mut touches: HashMap<Pubkey, usize> = HashMap::new();
+        for transfer in events.iter().filter_map(TokenEvent::transfer) {
+            *touches.entry(transfer.source).or_default() += 1;
+            *touches.entry(transfer.destination).or_default() += 1;
+        }
+        for withdraw in events.iter().filter_map(TokenEvent::withdraw_withheld) {
+            *touches.entry(withdraw.destination).or_default() += 1;
+        }
 
-        if program_id != TOKEN_PROGRAM_ID && program_id != TOKEN_2022_PROGRAM_ID {
-            return;
+        for transfer in events.iter_mut().filter_map(TokenEvent::transfer_mut) {
+            let Some((fee, source)) = self.transfer_fee(transfer, balances, &touches, epoch) else {
+                continue;
+            };
+            transfer.fee = Some(fee);
+            transfer.net_amount = transfer.amount.checked_sub(fee);
+            transfer.fee_source = Some(source);
         }
 
-        if program_id == TOKEN_PROGRAM_ID {
-            let Ok(instruction) = TokenInstructionLegacy::unpack(data) else {
-                return;
+        for withdraw in events
+            .iter_mut()
+            .filter_map(TokenEvent::withdraw_withheld_mut)
+        {
+            if withdraw.context.status != InstructionStatus::Success
+                || touches.get(&withdraw.destination) != Some(&1)
+            {
+                continue;
+            }
+            let Some(destination) = balances.get(&withdraw.destination) else {
+                continue;
+            };
+            let Some(amount) = destination
+                .post_amount
+                .and_then(|post| post.checked_sub(destination.pre_amount.unwrap_or(0)))
+            else {
+                continue;
             };
+            withdraw.amount = Some(amount);
+            withdraw.decimals = Some(destination.decimals);
+            withdraw.ui_amount = ui_amount(amount, Some(destination.decimals));
+        }
+    }
+
+    fn transfer_fee(
+        &self,
+        transfer: &Transfer,
+        balances: &HashMap<Pubkey, BalanceInfo>,
+        touches: &HashMap<Pubkey, usize>,
+        epoch: u64,
+    ) -> Option<(u64, FeeSource)> {
+        if let (Some(fee), Some(source)) = (transfer.fee, transfer.fee_source) {
+            return Some((fee, source));
+        }
+        if transfer.context.program_id == TOKEN_PROGRAM_ID {
+            return Some((0, FeeSource::SplToken));
+        }
+
+        if let Some(fee_config) = transfer.mint.and_then(|mint| self.mint_fees.get(&mint)) {
+            let fee = match fee_config {
+                Some(config) => config.calculate_epoch_fee(epoch, transfer.amount)?,
+                None => 0,
+            };
+            return Some((fee, FeeSource::MintState));
+        }
+
+        // A failed transaction's balances do not reflect its transfers.
+        if transfer.context.status != InstructionStatus::Success
+            || transfer.source == transfer.destination
+            || touches.get(&transfer.destination) != Some(&1)
+        {
+            return None;
+        }
+        let destination = balances.get(&transfer.destination)?;
+        // An account created in this transaction has no pre balance.
+        let received = destination
+            .post_amount?
+            .checked_sub(destination.pre_amount.unwrap_or(0))?;
+        let fee = transfer.amount.checked_sub(received)?;
+        Some((fee, FeeSource::BalanceDelta))
+    }
+
+    fn process_token_instruction(
+        &self,
+        account_keys: &[Pubkey],
+        accounts: &[u8],
+        data: &[u8],
+        balances: &HashMap<Pubkey, BalanceInfo>,
+        context: EventContext,
+    ) -> Option<TokenEvent> {
+        if context.program_id == TOKEN_PROGRAM_ID {
+            let instruction = TokenInstructionLegacy::unpack(data).ok()?;
             self.log_token_instruction_legacy(
-                message,
+                account_keys,
                 accounts,
+                data,
                 balances,
-                slot,
-                signature,
-                &program_id,
+                context,
                 instruction,
-                is_inner,
-                instruction_index,
-                inner_index,
-            );
-        } else {
-            let Ok(instruction) = TokenInstruction2022::unpack(data) else {
-                return;
-            };
+            )
+        } else if context.program_id == TOKEN_2022_PROGRAM_ID {
+            let instruction = TokenInstruction2022::unpack(data).ok()?;
             self.log_token_instruction_2022(
-                message,
+                account_keys,
                 accounts,
+                data,
                 balances,
-                slot,
-                signature,
-                &program_id,
+                context,
                 instruction,
-                is_inner,
-                instruction_index,
-                inner_index,
-            );
+            )
+        } else if context.program_id == native::SYSTEM_PROGRAM_ID {
+            let keys = InstructionAccounts::new(account_keys, accounts, balances);
+            native::decode(&keys, data, context)
+        } else if context.program_id == associated::ASSOCIATED_TOKEN_PROGRAM_ID {
+            let keys = InstructionAccounts::new(account_keys, accounts, balances);
+            associated::decode(&keys, data, context)
+        } else {
+            None
         }
     }
 
-    #[allow(clippy::too_many_arguments)]
     fn log_token_instruction_legacy(
         &self,
-        message: &Message,
+        account_keys: &[Pubkey],
         accounts: &[u8],
-        balances: &HashMap<u32, BalanceInfo>,
-        slot: u64,
-        signature: &str,
-        program_id: &str,
+        data: &[u8],
+        balances: &HashMap<Pubkey, BalanceInfo>,
+        context: EventContext,
         instruction: TokenInstructionLegacy,
-        is_inner: bool,
-        instruction_index: u32,
-        inner_index: Option<u32>,
-    ) {
+    ) -> Option<TokenEvent> {
+        let keys = InstructionAccounts::new(account_keys, accounts, balances);
         match instruction {
             TokenInstructionLegacy::Transfer { amount } => {
-                let source = account_from_indices(message, accounts, 0);
-                let destination = account_from_indices(message, accounts, 1);
-                let decimals = decimals_for_account(balances, accounts, 0)
-                    .or_else(|| decimals_for_account(balances, accounts, 1));
-                let ui_amount = ui_amount_from(amount, decimals)
-                    .or_else(|| balance_delta_ui(balances, accounts, 1))
-                    .or_else(|| balance_delta_ui(balances, accounts, 0).map(|v| v.abs()));
-                let mint = mint_for_account(balances, accounts, 0)
-                    .or_else(|| mint_for_account(balances, accounts, 1));
-
-                log_event(
-                    "transfer",
-                    slot,
-                    signature,
-                    program_id,
-                    source,
-                    destination,
-                    mint,
-                    amount,
-                    ui_amount,
-                    is_inner,
-                    instruction_index,
-                    inner_index,
-                );
-            }
-            TokenInstructionLegacy::TransferChecked { amount, decimals } => {
-                let source = account_from_indices(message, accounts, 0);
-                let mint = account_from_indices(message, accounts, 1);
-                let destination = account_from_indices(message, accounts, 2);
-                let ui_amount = ui_amount_from(amount, Some(decimals));
-
-                log_event(
-                    "transfer_checked",
-                    slot,
-                    signature,
-                    program_id,
-                    source,
-                    destination,
-                    mint,
-                    amount,
-                    ui_amount,
-                    is_inner,
-                    instruction_index,
-                    inner_index,
-                );
+                keys.transfer(context, amount).map(TokenEvent::Transfer)
             }
+            TokenInstructionLegacy::TransferChecked { amount, decimals } => keys
+                .transfer_checked(context, amount, decimals)
+                .map(TokenEvent::TransferChecked),
             TokenInstructionLegacy::MintTo { amount } => {
-                let mint = account_from_indices(message, accounts, 0);
-                let destination = account_from_indices(message, accounts, 1);
-                let decimals = decimals_for_account(balances, accounts, 1);
-                let ui_amount = ui_amount_from(amount, decimals)
-                    .or_else(|| balance_delta_ui(balances, accounts, 1));
-
-                log_event(
-                    "mint_to",
-                    slot,
-                    signature,
-                    program_id,
-                    None,
-                    destination,
-                    mint,
-                    amount,
-                    ui_amount,
-                    is_inner,
-                    instruction_index,
-                    inner_index,
-                );
-            }
-            TokenInstructionLegacy::MintToChecked { amount, decimals } => {
-                let mint = account_from_indices(message, accounts, 0);
-                let destination = account_from_indices(message, accounts, 1);
-                let ui_amount = ui_amount_from(amount, Some(decimals));
-
-                log_event(
-                    "mint_to_checked",
-                    slot,
-                    signature,
-                    program_id,
-                    None,
-                    destination,
-                    mint,
-                    amount,
-                    ui_amount,
-                    is_inner,
-                    instruction_index,
-                    inner_index,
-                );
+                keys.mint_to(context, amount, None).map(TokenEvent::MintTo)
             }
+            TokenInstructionLegacy::MintToChecked { amount, decimals } => keys
+                .mint_to(context, amount, Some(decimals))
+                .map(TokenEvent::MintToChecked),
             TokenInstructionLegacy::Burn { amount } => {
-                let account = account_from_indices(message, accounts, 0);
-                let mint = account_from_indices(message, accounts, 1);
-                let decimals = decimals_for_account(balances, accounts, 0);
-                let ui_amount = ui_amount_from(amount, decimals)
-                    .or_else(|| balance_delta_ui(balances, accounts, 0).map(|v| v.abs()));
-
-                log_event(
-                    "burn",
-                    slot,
-                    signature,
-                    program_id,
-                    account,
-                    None,
-                    mint,
-                    amount,
-                    ui_amount,
-                    is_inner,
-                    instruction_index,
-                    inner_index,
-                );
+                keys.burn(context, amount, None).map(TokenEvent::Burn)
             }
-            TokenInstructionLegacy::BurnChecked { amount, decimals } => {
-                let account = account_from_indices(message, accounts, 0);
-                let mint = account_from_indices(message, accounts, 1);
-                let ui_amount = ui_amount_from(amount, Some(decimals));
-
-                log_event(
-                    "burn_checked",
-                    slot,
-                    signature,
-                    program_id,
-                    account,
-                    None,
-                    mint,
-                    amount,
-                    ui_amount,
-                    is_inner,
-                    instruction_index,
-                    inner_index,
-                );
+            TokenInstructionLegacy::BurnChecked { amount, decimals } => keys
+                .burn(context, amount, Some(decimals))
+                .map(TokenEvent::BurnChecked),
+            TokenInstructionLegacy::Approve { amount } => {
+                keys.approve(context, amount).map(TokenEvent::Approve)
+            }
+            TokenInstructionLegacy::ApproveChecked { amount, decimals } => keys
+                .approve_checked(context, amount, decimals)
+                .map(TokenEvent::ApproveChecked),
+            TokenInstructionLegacy::Revoke => keys.revoke(context).map(TokenEvent::Revoke),
+            TokenInstructionLegacy::SetAuthority { new_authority, .. } => keys
+                .set_authority(
+                    context,
+                    *data.get(1)?,
+                    optional_spl_key(new_authority.map(spl_key).into())?,
+                )
+                .map(TokenEvent::SetAuthority),
+            TokenInstructionLegacy::CloseAccount => {
+                keys.close_account(context).map(TokenEvent::CloseAccount)
+            }
+            TokenInstructionLegacy::FreezeAccount => {
+                keys.freeze(context).map(TokenEvent::FreezeAccount)
+            }
+            TokenInstructionLegacy::ThawAccount => {
+                keys.freeze(context).map(TokenEvent::ThawAccount)
             }
-            _ => {}
+            TokenInstructionLegacy::InitializeMint {
+                decimals,
+                mint_authority,
+                freeze_authority,
+            } => keys
+                .initialize_mint(
+                    context,
+                    decimals,
+                    spl_key(mint_authority)?,
+                    optional_spl_key(freeze_authority.map(spl_key).into())?,
+                )
+                .map(TokenEvent::InitializeMint),
+            TokenInstructionLegacy::InitializeMint2 {
+                decimals,
+                mint_authority,
+                freeze_authority,
+            } => keys
+                .initialize_mint(
+                    context,
+                    decimals,
+                    spl_key(mint_authority)?,
+                    optional_spl_key(freeze_authority.map(spl_key).into())?,
+                )
+                .map(TokenEvent::InitializeMint2),
+            TokenInstructionLegacy::InitializeAccount => keys
+                .initialize_account(context, None)
+                .map(TokenEvent::InitializeAccount),
+            TokenInstructionLegacy::InitializeAccount2 { owner } => keys
+                .initialize_account(context, Some(spl_key(owner)?))
+                .map(TokenEvent::InitializeAccount2),
+            TokenInstructionLegacy::InitializeAccount3 { owner } => keys
+                .initialize_account(context, Some(spl_key(owner)?))
+                .map(TokenEvent::InitializeAccount3),
+            TokenInstructionLegacy::SyncNative => {
+                keys.sync_native(context).map(TokenEvent::SyncNative)
+            }
+            TokenInstructionLegacy::AmountToUiAmount { amount } => keys
+                .amount_to_ui_amount(context, amount)
+                .map(TokenEvent::AmountToUiAmount),
+            _ => None,
         }
     }
-    #[allow(clippy::too_many_arguments)]
+
     fn log_token_instruction_2022(
         &self,
-        message: &Message,
+        account_keys: &[Pubkey],
         accounts: &[u8],
-        balances: &HashMap<u32, BalanceInfo>,
-        slot: u64,
-        signature: &str,
-        program_id: &str,
+        data: &[u8],
+        balances: &HashMap<Pubkey, BalanceInfo>,
+        context: EventContext,
         instruction: TokenInstruction2022,
-        is_inner: bool,
-        instruction_index: u32,
-        inner_index: Option<u32>,
-    ) {
+    ) -> Option<TokenEvent> {
+        let keys = InstructionAccounts::new(account_keys, accounts, balances);
         match instruction {
             TokenInstruction2022::Transfer { amount } => {
-                let source = account_from_indices(message, accounts, 0);
-                let destination = account_from_indices(message, accounts, 1);
-                let decimals = decimals_for_account(balances, accounts, 0)
-                    .or_else(|| decimals_for_account(balances, accounts, 1));
-                let ui_amount = ui_amount_from(amount, decimals)
-                    .or_else(|| balance_delta_ui(balances, accounts, 1))
-                    .or_else(|| balance_delta_ui(balances, accounts, 0).map(|v| v.abs()));
-                let mint = mint_for_account(balances, accounts, 0)
-                    .or_else(|| mint_for_account(balances, accounts, 1));
-
-                log_event(
-                    "transfer",
-                    slot,
-                    signature,
-                    program_id,
-                    source,
-                    destination,
-                    mint,
-                    amount,
-                    ui_amount,
-                    is_inner,
-                    instruction_index,
-                    inner_index,
-                );
-            }
-            TokenInstruction2022::TransferChecked { amount, decimals } => {
-                let source = account_from_indices(message, accounts, 0);
-                let mint = account_from_indices(message, accounts, 1);
-                let destination = account_from_indices(message, accounts, 2);
-                let ui_amount = ui_amount_from(amount, Some(decimals));
-
-                log_event(
-                    "transfer_checked",
-                    slot,
-                    signature,
-                    program_id,
-                    source,
-                    destination,
-                    mint,
-                    amount,
-                    ui_amount,
-                    is_inner,
-                    instruction_index,
-                    inner_index,
-                );
+                keys.transfer(context, amount).map(TokenEvent::Transfer)
             }
+            TokenInstruction2022::TransferChecked { amount, decimals } => keys
+                .transfer_checked(context, amount, decimals)
+                .map(TokenEvent::TransferChecked),
             TokenInstruction2022::MintTo { amount } => {
-                let mint = account_from_indices(message, accounts, 0);
-                let destination = account_from_indices(message, accounts, 1);
-                let decimals = decimals_for_account(balances, accounts, 1);
-                let ui_amount = ui_amount_from(amount, decimals)
-                    .or_else(|| balance_delta_ui(balances, accounts, 1));
-
-                log_event(
-                    "mint_to",
-                    slot,
-                    signature,
-                    program_id,
-                    None,
-                    destination,
-                    mint,
-                    amount,
-                    ui_amount,
-                    is_inner,
-                    instruction_index,
-                    inner_index,
-                );
-            }
-            TokenInstruction2022::MintToChecked { amount, decimals } => {
-                let mint = account_from_indices(message, accounts, 0);
-                let destination = account_from_indices(message, accounts, 1);
-                let ui_amount = ui_amount_from(amount, Some(decimals));
-
-                log_event(
-                    "mint_to_checked",
-                    slot,
-                    signature,
-                    program_id,
-                    None,
-                    destination,
-                    mint,
-                    amount,
-                    ui_amount,
-                    is_inner,
-                    instruction_index,
-                    inner_index,
-                );
+                keys.mint_to(context, amount, None).map(TokenEvent::MintTo)
             }
+            TokenInstruction2022::MintToChecked { amount, decimals } => keys
+                .mint_to(context, amount, Some(decimals))
+                .map(TokenEvent::MintToChecked),
             TokenInstruction2022::Burn { amount } => {
-                let account = account_from_indices(message, accounts, 0);
-                let mint = account_from_indices(message, accounts, 1);
-                let decimals = decimals_for_account(balances, accounts, 0);
-                let ui_amount = ui_amount_from(amount, decimals)
-                    .or_else(|| balance_delta_ui(balances, accounts, 0).map(|v| v.abs()));
-
-                log_event(
-                    "burn",
-                    slot,
-                    signature,
-                    program_id,
-                    account,
-                    None,
-                    mint,
-                    amount,
-                    ui_amount,
-                    is_inner,
-                    instruction_index,
-                    inner_index,
-                );
+                keys.burn(context, amount, None).map(TokenEvent::Burn)
             }
-            TokenInstruction2022::BurnChecked { amount, decimals } => {
-                let account = account_from_indices(message, accounts, 0);
-                let mint = account_from_indices(message, accounts, 1);
-                let ui_amount = ui_amount_from(amount, Some(decimals));
-
-                log_event(
-                    "burn_checked",
-                    slot,
-                    signature,
-                    program_id,
-                    account,
-                    None,
-                    mint,
-                    amount,
-                    ui_amount,
-                    is_inner,
-                    instruction_index,
-                    inner_index,
-                );
+            TokenInstruction2022::BurnChecked { amount, decimals } => keys
+                .burn(context, amount, Some(decimals))
+                .map(TokenEvent::BurnChecked),
+            TokenInstruction2022::Approve { amount } => {
+                keys.approve(context, amount).map(TokenEvent::Approve)
+            }
+            TokenInstruction2022::ApproveChecked { amount, decimals } => keys
+                .approve_checked(context, amount, decimals)
+                .map(TokenEvent::ApproveChecked),
+            TokenInstruction2022::Revoke => keys.revoke(context).map(TokenEvent::Revoke),
+            TokenInstruction2022::SetAuthority { new_authority, .. } => keys
+                .set_authority(
+                    context,
+                    *data.get(1)?,
+                    optional_spl_key(new_authority.map(spl_key).into())?,
+                )
+                .map(TokenEvent::SetAuthority),
+            TokenInstruction2022::CloseAccount => {
+                keys.close_account(context).map(TokenEvent::CloseAccount)
+            }
+            TokenInstruction2022::FreezeAccount => {
+                keys.freeze(context).map(TokenEvent::FreezeAccount)
+            }
+            TokenInstruction2022::ThawAccount => keys.freeze(context).map(TokenEvent::ThawAccount),
+            TokenInstruction2022::InitializeMint {
+                decimals,
+                mint_authority,
+                freeze_authority,
+            } => keys
+                .initialize_mint(
+                    context,
+                    decimals,
+                    spl_key(mint_authority)?,
+                    optional_spl_key(freeze_authority.map(spl_key).into())?,
+                )
+                .map(TokenEvent::InitializeMint),
+            TokenInstruction2022::InitializeMint2 {
+                decimals,
+                mint_authority,
+                freeze_authority,
+            } => keys
+                .initialize_mint(
+                    context,
+                    decimals,
+                    spl_key(mint_authority)?,
+                    optional_spl_key(freeze_authority.map(spl_key).into())?,
+                )
+                .map(TokenEvent::InitializeMint2),
+            TokenInstruction2022::InitializeAccount => keys
+                .initialize_account(context, None)
+                .map(TokenEvent::InitializeAccount),
+            TokenInstruction2022::InitializeAccount2 { owner } => keys
+                .initialize_account(context, Some(spl_key(owner)?))
+                .map(TokenEvent::InitializeAccount2),
+            TokenInstruction2022::InitializeAccount3 { owner } => keys
+                .initialize_account(context, Some(spl_key(owner)?))
+                .map(TokenEvent::InitializeAccount3),
+            TokenInstruction2022::SyncNative => {
+                keys.sync_native(context).map(TokenEvent::SyncNative)
             }
-            _ => {}
+            TokenInstruction2022::AmountToUiAmount { amount } => keys
+                .amount_to_ui_amount(context, amount)
+                .map(TokenEvent::AmountToUiAmount),
+            TokenInstruction2022::TransferFeeExtension
+            | TokenInstruction2022::ConfidentialTransferExtension
+            | TokenInstruction2022::DefaultAccountStateExtension
+            | TokenInstruction2022::MemoTransferExtension
+            | TokenInstruction2022::InterestBearingMintExtension
+            | TokenInstruction2022::CpiGuardExtension
+            | TokenInstruction2022::TransferHookExtension
+            | TokenInstruction2022::MetadataPointerExtension
+            | TokenInstruction2022::GroupPointerExtension => {
+                extensions::decode(&keys, data, context)
+            }
+            _ => None,
         }
     }
 }
 
-fn build_balance_map(meta: &TransactionStatusMeta) -> HashMap<u32, BalanceInfo> {
-    let mut balances: HashMap<u32, BalanceInfo> = HashMap::new();
+/// The accounts of one instruction, resolved against the transaction's keys and
+/// token balances.
+pub(crate) struct InstructionAccounts<'a> {
+    account_keys: &'a [Pubkey],
+    accounts: &'a [u8],
+    balances: &'a HashMap<Pubkey, BalanceInfo>,
+}
 
-    for balance in &meta.pre_token_balances {
-        let decimals = balance
-            .ui_token_amount
-            .as_ref()
-            .map(|amount| amount.decimals as u8)
-            .unwrap_or(0);
-        let entry = balances
-            .entry(balance.account_index)
-            .or_insert_with(|| BalanceInfo {
-                mint: balance.mint.clone(),
-                decimals,
-                pre_ui: None,
-                post_ui: None,
-            });
-        entry.mint = balance.mint.clone();
-        entry.decimals = decimals;
-        entry.pre_ui = balance
-            .ui_token_amount
-            .as_ref()
-            .map(|amount| amount.ui_amount);
+impl<'a> InstructionAccounts<'a> {
+    fn new(
+        account_keys: &'a [Pubkey],
+        accounts: &'a [u8],
+        balances: &'a HashMap<Pubkey, BalanceInfo>,
+    ) -> Self {
+        Self {
+            account_keys,
+            accounts,
+            balances,
+        }
+    }
+
+    pub(crate) fn key(&self, position: usize) -> Option<Pubkey> {
+        let index = *self.accounts.get(position)? as usize;
+        self.account_keys.get(index).copied()
+    }
+
+    /// Keys of every account from `position` to the end of the instruction.
+    pub(crate) fn keys_from(&self, position: usize) -> Vec<Pubkey> {
+        (position..self.accounts.len())
+            .filter_map(|position| self.key(position))
+            .collect()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.accounts.len()
     }
 
-    for balance in &meta.post_token_balances {
+    fn balance(&self, position: usize) -> Option<&BalanceInfo> {
+        self.balances.get(&self.key(position)?)
+    }
+
+    /// `Transfer` carries no mint or decimals; take them from either side's balance.
+    fn transfer(&self, context: EventContext, amount: u64) -> Option<Transfer> {
+        let balance = self.balance(0).or_else(|| self.balance(1));
+        Some(Transfer {
+            context,
+            source: self.key(0)?,
+            destination: self.key(1)?,
+            authority: self.key(2)?,
+            mint: balance.map(|info| info.mint),
+            amount,
+            decimals: balance.map(|info| info.decimals),
+            ui_amount: ui_amount(amount, balance.map(|info| info.decimals)),
+            fee: None,
+            net_amount: None,
+            fee_source: None,
+            source_owner: None,
+            destination_owner: None,
+        })
+    }
+
+    fn transfer_checked(
+        &self,
+        context: EventContext,
+        amount: u64,
+        decimals: u8,
+    ) -> Option<Transfer> {
+        Some(Transfer {
+            context,
+            source: self.key(0)?,
+            destination: self.key(2)?,
+            authority: self.key(3)?,
+            mint: Some(self.key(1)?),
+            amount,
+            decimals: Some(decimals),
+            ui_amount: ui_amount(amount, Some(decimals)),
+            fee: None,
+            net_amount: None,
+            fee_source: None,
+            source_owner: None,
+            destination_owner: None,
+        })
+    }
+
+    fn mint_to(&self, context: EventContext, amount: u64, decimals: Option<u8>) -> Option<MintTo> {
+        let decimals = decimals.or_else(|| self.balance(1).map(|info| info.decimals));
+        Some(MintTo {
+            context,
+            mint: self.key(0)?,
+            destination: self.key(1)?,
+            authority: self.key(2)?,
+            amount,
+            decimals,
+            ui_amount: ui_amount(amount, decimals),
+        })
+    }
+
+    fn burn(&self, context: EventContext, amount: u64, decimals: Option<u8>) -> Option<Burn> {
+        let decimals = decimals.or_else(|| self.balance(0).map(|info| info.decimals));
+        Some(Burn {
+            context,
+            account: self.key(0)?,
+            mint: self.key(1)?,
+            authority: self.key(2)?,
+            amount,
+            decimals,
+            ui_amount: ui_amount(amount, decimals),
+        })
+    }
+
+    /// `Approve` carries no mint or decimals; take them from the source balance.
+    fn approve(&self, context: EventContext, amount: u64) -> Option<Approve> {
+        let balance = self.balance(0);
+        Some(Approve {
+            context,
+            source: self.key(0)?,
+            delegate: self.key(1)?,
+            owner: self.key(2)?,
+            mint: balance.map(|info| info.mint),
+            amount,
+            decimals: balance.map(|info| info.decimals),
+            ui_amount: ui_amount(amount, balance.map(|info| info.decimals)),
+        })
+    }
+
+    fn approve_checked(&self, context: EventContext, amount: u64, decimals: u8) -> Option<Approve> {
+        Some(Approve {
+            context,
+            source: self.key(0)?,
+            delegate: self.key(2)?,
+            owner: self.key(3)?,
+            mint: Some(self.key(1)?),
+            amount,
+            decimals: Some(decimals),
+            ui_amount: ui_amount(amount, Some(decimals)),
+        })
+    }
+
+    fn revoke(&self, context: EventContext) -> Option<Revoke> {
+        Some(Revoke {
+            context,
+            source: self.key(0)?,
+            owner: self.key(1)?,
+        })
+    }
+
+    /// The authority type is read from the raw instruction byte so the SPL Token and
+    /// Token-2022 variants share one mapping.
+    fn set_authority(
+        &self,
+        context: EventContext,
+        authority_type: u8,
+        new_authority: Option<Pubkey>,
+    ) -> Option<SetAuthority> {
+        Some(SetAuthority {
+            context,
+            account: self.key(0)?,
+            authority_type: AuthorityType::from_u8(authority_type)?,
+            current_authority: self.key(1)?,
+            new_authority,
+        })
+    }
+
+    fn close_account(&self, context: EventContext) -> Option<CloseAccount> {
+        Some(CloseAccount {
+            context,
+            account: self.key(0)?,
+            destination: self.key(1)?,
+            owner: self.key(2)?,
+            lamports: None,
+            native: false,
+        })
+    }
+
+    fn freeze(&self, context: EventContext) -> Option<AccountFreeze> {
+        Some(AccountFreeze {
+            context,
+            account: self.key(0)?,
+            mint: self.key(1)?,
+            freeze_authority: self.key(2)?,
+        })
+    }
+
+    fn initialize_mint(
+        &self,
+        context: EventContext,
+        decimals: u8,
+        mint_authority: Pubkey,
+        freeze_authority: Option<Pubkey>,
+    ) -> Option<InitializeMint> {
+        Some(InitializeMint {
+            context,
+            mint: self.key(0)?,
+            decimals,
+            mint_authority,
+            freeze_authority,
+        })
+    }
+
+    /// `InitializeAccount` passes the owner as the third account; the `2` and `3`
+    /// variants carry it in the instruction data.
+    fn initialize_account(
+        &self,
+        context: EventContext,
+        owner: Option<Pubkey>,
+    ) -> Option<InitializeAccount> {
+        Some(InitializeAccount {
+            context,
+            account: self.key(0)?,
+            mint: self.key(1)?,
+            owner: owner.or_else(|| self.key(2))?,
+        })
+    }
+
+    fn sync_native(&self, context: EventContext) -> Option<SyncNative> {
+        Some(SyncNative {
+            context,
+            account: self.key(0)?,
+            wrapped_lamports: None,
+        })
+    }
+
+    fn amount_to_ui_amount(&self, context: EventContext, amount: u64) -> Option<AmountToUiAmount> {
+        Some(AmountToUiAmount {
+            context,
+            mint: self.key(0)?,
+            amount,
+        })
+    }
+}
+
+fn build_balance_map(
+    meta: &TransactionStatusMeta,
+    account_keys: &[Pubkey],
+) -> HashMap<Pubkey, BalanceInfo> {
+    let mut balances: HashMap<Pubkey, BalanceInfo> = HashMap::new();
+
+    let pre = meta
+        .pre_token_balances
+        .iter()
+        .map(|balance| (balance, true));
+    let post = meta
+        .post_token_balances
+        .iter()
+        .map(|balance| (balance, false));
+    for (balance, is_pre) in pre.chain(post) {
+        let Some(account) = account_keys.get(balance.account_index as usize) else {
+            continue;
+        };
+        let Ok(mint) = Pubkey::from_str(&balance.mint) else {
+            continue;
+        };
         let decimals = balance
             .ui_token_amount
             .as_ref()
             .map(|amount| amount.decimals as u8)
             .unwrap_or(0);
-        let entry = balances
-            .entry(balance.account_index)
-            .or_insert_with(|| BalanceInfo {
-                mint: balance.mint.clone(),
-                decimals,
-                pre_ui: None,
-                post_ui: None,
-            });
-        entry.mint = balance.mint.clone();
-        entry.decimals = decimals;
-        entry.post_ui = balance
+        let amount = balance
             .ui_token_amount
             .as_ref()
-            .map(|amount| amount.ui_amount);
+            .and_then(|amount| amount.amount.parse::<u64>().ok());
+
+        let owner = Pubkey::from_str(&balance.owner).ok();
+        let program_id = Pubkey::from_str(&balance.program_id).ok();
+
+        let entry = balances.entry(*account).or_insert(BalanceInfo {
+            mint,
+            decimals,
+            pre_amount: None,
+            post_amount: None,
+            owner,
+            program_id,
+        });
+        entry.mint = mint;
+        entry.decimals = decimals;
+        entry.owner = owner.or(entry.owner);
+        entry.program_id = program_id.or(entry.program_id);
+        if is_pre {
+            entry.pre_amount = amount;
+        } else {
+            entry.post_amount = amount;
+        }
     }
 
     balances
 }
 
-fn account_key(message: &Message, index: usize) -> Option<String> {
-    message
-        .account_keys
-        .get(index)
-        .map(|key| bs58::encode(key).into_string())
+/// Sums pre/post token balances per (owner, mint), so a wallet moving tokens between
+/// its own accounts nets out. Accounts without a reported owner are skipped.
+fn balance_changes(
+    balances: &HashMap<Pubkey, BalanceInfo>,
+    transaction: &TransactionContext,
+) -> Vec<TokenEvent> {
+    let mut totals: BTreeMap<(Pubkey, Pubkey), BalanceChange> = BTreeMap::new();
+    for info in balances.values() {
+        let Some(owner) = info.owner else {
+            continue;
+        };
+        let entry = totals
+            .entry((owner, info.mint))
+            .or_insert_with(|| BalanceChange {
+                transaction: transaction.clone(),
+                owner,
+                mint: info.mint,
+                program_id: info.program_id,
+                decimals: info.decimals,
+                pre_amount: 0,
+                post_amount: 0,
+                delta: 0,
+            });
+        entry.pre_amount = entry
+            .pre_amount
+            .saturating_add(info.pre_amount.unwrap_or(0));
+        entry.post_amount = entry
+            .post_amount
+            .saturating_add(info.post_amount.unwrap_or(0));
+    }
+
+    totals
+        .into_values()
+        .filter_map(|mut change| {
+            change.delta = i128::from(change.post_amount) - i128::from(change.pre_amount);
+            (change.delta != 0).then_some(TokenEvent::BalanceChange(change))
+        })
+        .collect()
 }
 
-fn account_from_indices(message: &Message, accounts: &[u8], position: usize) -> Option<String> {
-    let index = *accounts.get(position)? as usize;
-    account_key(message, index)
+/// Whether an instruction event took effect; reverted and failed ones did not.
+fn succeeded(event: &TokenEvent) -> bool {
+    event
+        .context()
+        .is_some_and(|context| context.status == InstructionStatus::Success)
 }
 
-fn balance_delta_ui(
-    balances: &HashMap<u32, BalanceInfo>,
-    accounts: &[u8],
-    position: usize,
-) -> Option<f64> {
-    let index = *accounts.get(position)? as u32;
-    let info = balances.get(&index)?;
-    match (info.pre_ui, info.post_ui) {
-        (Some(pre), Some(post)) => Some(post - pre),
-        _ => None,
+/// Renders the bincode-encoded `TransactionError` carried in the protobuf, falling
+/// back to hex if it does not decode.
+fn transaction_error(err: &[u8]) -> String {
+    match bincode::deserialize::<TransactionError>(err) {
+        Ok(err) => format!("{err:?}"),
+        Err(_) => err.iter().map(|byte| format!("{byte:02x}")).collect(),
     }
 }
 
-fn decimals_for_account(
-    balances: &HashMap<u32, BalanceInfo>,
-    accounts: &[u8],
-    position: usize,
-) -> Option<u8> {
-    let index = *accounts.get(position)? as u32;
-    balances.get(&index).map(|info| info.decimals)
+pub(crate) fn ui_amount(amount: u64, decimals: Option<u8>) -> Option<FixedDecimal> {
+    decimals.map(|decimals| FixedDecimal::new(amount, decimals))
 }
 
-fn mint_for_account(
-    balances: &HashMap<u32, BalanceInfo>,
-    accounts: &[u8],
-    position: usize,
-) -> Option<String> {
-    let index = *accounts.get(position)? as u32;
-    balances.get(&index).map(|info| info.mint.clone())
+/// Converts a pubkey from the SPL crates, which may be built against a different
+/// `solana-program` than `solana-sdk`.
+fn spl_key(key: impl AsRef<[u8]>) -> Option<Pubkey> {
+    Pubkey::try_from(key.as_ref()).ok()
 }
 
-fn ui_amount_from(amount: u64, decimals: Option<u8>) -> Option<f64> {
-    let decimals = decimals?;
-    let divisor = 10_f64.powi(decimals as i32);
-    Some(amount as f64 / divisor)
+/// An optional SPL key through [`spl_key`]: `Some(None)` when it is absent, `None`
+/// when it does not convert.
+fn optional_spl_key(key: Option<Option<Pubkey>>) -> Option<Option<Pubkey>> {
+    match key {
+        Some(key) => key.map(Some),
+        None => Some(None),
+    }
 }
 
-#[allow(clippy::too_many_arguments)]
-fn log_event(
-    kind: &str,
-    slot: u64,
-    signature: &str,
-    program_id: &str,
-    source: Option<String>,
-    destination: Option<String>,
-    mint: Option<String>,
-    amount: u64,
-    ui_amount: Option<f64>,
-    is_inner: bool,
-    instruction_index: u32,
-    inner_index: Option<u32>,
-) {
-    let src = source.unwrap_or_else(|| "unknown".to_string());
-    let dst = destination.unwrap_or_else(|| "unknown".to_string());
-    let mint = mint.unwrap_or_else(|| "unknown".to_string());
-    let inner = if is_inner { "inner" } else { "outer" };
-    let inner_index = inner_index
-        .map(|idx| idx.to_string())
-        .unwrap_or_else(|| "-".to_string());
-    let ui = ui_amount
-        .map(|value| format!("{value:.9}"))
-        .unwrap_or_else(|| "unknown".to_string());
-    println!(
-        "----------------------------------------\nTOKEN {kind}\n  slot: {slot}\n  sig: {signature}\n  program: {program_id}\n  source: {src}\n  dest: {dst}\n  mint: {mint}\n  amount: {amount}\n  ui_amount: {ui}\n  loc: {inner}:{instruction_index}:{inner_index}"
-    );
+/// Static keys followed by lookup-table addresses, matching how v0 instructions
+/// index accounts; `None` if any key is not 32 bytes.
+fn resolve_account_keys(message: &Message, meta: &TransactionStatusMeta) -> Option<Vec<Pubkey>> {
+    message
+        .account_keys
+        .iter()
+        .chain(meta.loaded_writable_addresses.iter())
+        .chain(meta.loaded_readonly_addresses.iter())
+        .map(|key| Pubkey::try_from(key.as_slice()).ok())
+        .collect()
 }
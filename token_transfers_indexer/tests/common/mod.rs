@@ -0,0 +1,30 @@
+//! Golden-output harness shared by the integration tests: fixtures are read from
+//! `tests/fixtures` and compared with `tests/golden` by `stream_common::fixtures`.
+
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use stream_common::fixtures;
+use yellowstone_grpc_proto::prelude::SubscribeUpdate;
+
+/// Filter name the ingestor routes this indexer's updates under.
+pub const FILTER_NAME: &str = "token";
+
+fn tests_dir(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join(name)
+}
+
+/// Loads `tests/fixtures/<name>.json`, `.bin` or `.bin.zst` as a list of updates.
+pub fn load_fixture(name: &str) -> Vec<SubscribeUpdate> {
+    fixtures::load_fixture(&tests_dir("fixtures"), name, FILTER_NAME)
+}
+
+/// Compares `events` with `tests/golden/<name>.json`, or rewrites it when
+/// `UPDATE_GOLDEN` is set.
+pub fn assert_golden<E: Serialize>(name: &str, events: &[E]) {
+    fixtures::assert_golden(&tests_dir("golden"), name, events);
+}
@@ -0,0 +1,72 @@
+mod common;
+
+use token_transfers_indexer::token::{FailedTransactions, TokenProcessor};
+
+fn run(name: &str) {
+    run_with(TokenProcessor::new(), name, name);
+}
+
+fn run_with(mut processor: TokenProcessor, fixture: &str, golden: &str) {
+    let events = common::load_fixture(fixture)
+        .into_iter()
+        .flat_map(|update| processor.handle_update(update))
+        .collect::<Vec<_>>();
+
+    common::assert_golden(golden, &events);
+}
+
+macro_rules! golden {
+    ($($name:ident),* $(,)?) => {
+        $(
+            #[test]
+            fn $name() {
+                run(stringify!($name));
+            }
+        )*
+    };
+}
+
+golden!(
+    account_lifecycle,
+    ata_create_and_transfer,
+    failed_router_partial_inner,
+    legacy_transfer,
+    native_sol_wrap_unwrap,
+    nested_cpi_call_chain,
+    token2022_extension_config,
+    token2022_transfer_checked_inner,
+    token2022_transfer_fee_amounts,
+    token2022_transfer_with_fee,
+    token2022_withdraw_withheld,
+    unresolved_program_id,
+    v0_mint_to_and_burn_checked,
+);
+
+#[test]
+fn failed_transfer_dropped() {
+    run_with(
+        TokenProcessor::new(),
+        "failed_transfer_with_block_time",
+        "failed_transfer_dropped",
+    );
+}
+
+#[test]
+fn failed_transfer_with_block_time() {
+    let processor = TokenProcessor::new().with_failed_transactions(FailedTransactions::Flag);
+    run_with(
+        processor,
+        "failed_transfer_with_block_time",
+        "failed_transfer_with_block_time",
+    );
+}
+
+#[test]
+fn failed_router_partial_inner_flagged() {
+    let processor = TokenProcessor::new().with_failed_transactions(FailedTransactions::Flag);
+    run_with(
+        processor,
+        "failed_router_partial_inner",
+        "failed_router_partial_inner_flagged",
+    );
+}